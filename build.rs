@@ -0,0 +1,27 @@
+use std::fs;
+
+// stremio-core is a git dependency, so its version and revision are only known from the lockfile
+fn main() {
+    println!("cargo:rerun-if-changed=Cargo.lock");
+    let lockfile = fs::read_to_string("Cargo.lock").unwrap_or_default();
+    let package = lockfile
+        .split("[[package]]")
+        .find(|package| package.contains("name = \"stremio-core\""))
+        .unwrap_or_default();
+    let field = |name: &str| {
+        package
+            .lines()
+            .find_map(|line| line.strip_prefix(&format!("{} = \"", name)))
+            .map(|value| value.trim_end_matches('"').to_owned())
+    };
+    let version = field("version").unwrap_or_else(|| "unknown".to_owned());
+    let revision = field("source")
+        .and_then(|source| {
+            source
+                .rfind('#')
+                .map(|index| source[index + 1..].to_owned())
+        })
+        .unwrap_or_default();
+    println!("cargo:rustc-env=STREMIO_CORE_VERSION={}", version);
+    println!("cargo:rustc-env=STREMIO_CORE_REVISION={}", revision);
+}
@@ -0,0 +1,61 @@
+use crate::env::WebEnv;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::cell::RefCell;
+use stremio_core::runtime::Env;
+
+thread_local! {
+    static ACCOUNT_DETAILS: RefCell<Option<(String, AccountDetails)>> = Default::default();
+}
+
+// The API user carries more than core keeps in the profile, only the parts the account page shows are read
+#[derive(Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AccountDetails {
+    pub avatar: Option<String>,
+    pub date_registered: Option<DateTime<Utc>>,
+    #[serde(alias = "premium_expire")]
+    pub premium_expire: Option<DateTime<Utc>>,
+    pub trakt: Option<serde_json::Value>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Account {
+    pub avatar: Option<String>,
+    pub date_registered: Option<DateTime<Utc>>,
+    pub premium: bool,
+    pub premium_expire: Option<DateTime<Utc>>,
+    pub trakt_connected: bool,
+}
+
+pub fn set(uid: String, account_details: AccountDetails) {
+    ACCOUNT_DETAILS.with(|current| *current.borrow_mut() = Some((uid, account_details)));
+}
+
+pub fn clear() {
+    ACCOUNT_DETAILS.with(|current| current.borrow_mut().take());
+}
+
+// Details fetched for another user are never shown, e.g. right after switching accounts
+pub fn account(uid: &Option<String>) -> Option<Account> {
+    let uid = uid.as_ref()?;
+    ACCOUNT_DETAILS.with(|account_details| {
+        account_details
+            .borrow()
+            .as_ref()
+            .filter(|(account_uid, _)| account_uid == uid)
+            .map(|(_, account_details)| Account {
+                avatar: account_details.avatar.to_owned(),
+                date_registered: account_details.date_registered,
+                premium: account_details
+                    .premium_expire
+                    .map_or(false, |premium_expire| premium_expire > WebEnv::now()),
+                premium_expire: account_details.premium_expire,
+                trakt_connected: account_details
+                    .trakt
+                    .as_ref()
+                    .map_or(false, |trakt| !trakt.is_null()),
+            })
+    })
+}
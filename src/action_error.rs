@@ -0,0 +1,20 @@
+use crate::js_value::from_js_value;
+use crate::middleware;
+use serde::Serialize;
+use stremio_core::runtime::msg::Action;
+use wasm_bindgen::JsValue;
+
+#[derive(Serialize)]
+#[serde(tag = "type")]
+pub enum ActionError {
+    InvalidAction { message: String, path: Vec<String> },
+}
+
+// Serde errors don't carry a location, the path is the chain of action tags that were understood
+// before the payload got rejected
+pub fn deserialize_action(action: &JsValue) -> Result<Action, ActionError> {
+    from_js_value::<Action>(action.to_owned()).map_err(|error| ActionError::InvalidAction {
+        message: error.to_string(),
+        path: middleware::action_path(action),
+    })
+}
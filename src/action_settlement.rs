@@ -0,0 +1,198 @@
+use crate::effect_groups;
+use crate::js_value::to_js_value;
+use std::cell::{Cell, RefCell};
+use stremio_core::runtime::msg::{Action, ActionCtx, Event};
+use wasm_bindgen::closure::Closure;
+use wasm_bindgen::{JsCast, JsValue};
+
+const SETTLEMENT_TIMEOUT: i32 = 60_000;
+
+thread_local! {
+    static NEXT_SETTLEMENT_ID: Cell<u32> = Cell::new(0);
+    static PENDING_SETTLEMENTS: RefCell<Vec<PendingSettlement>> = Default::default();
+}
+
+type EventMatcher = Box<dyn Fn(&Event) -> bool>;
+
+// The promise handed out for an action, it travels with the action while the action waits
+pub struct Settlement {
+    resolve: js_sys::Function,
+    reject: js_sys::Function,
+}
+
+impl Settlement {
+    fn resolve(self, value: &JsValue) {
+        let _ = self.resolve.call1(&JsValue::NULL, value);
+    }
+    pub fn reject(self, error: &JsValue) {
+        let _ = self.reject.call1(&JsValue::NULL, error);
+    }
+}
+
+struct PendingSettlement {
+    id: u32,
+    matcher: Option<EventMatcher>,
+    settlement: Settlement,
+    timeout_handle: i32,
+}
+
+// Actions that end with a core event settle on it (or on an error caused by it),
+// every other action settles once all of its effects are finished
+fn event_matcher(action: &Action) -> Option<EventMatcher> {
+    match action.to_owned() {
+        Action::Ctx(ActionCtx::Authenticate(_)) => Some(Box::new(|event| {
+            matches!(event, Event::UserAuthenticated { .. })
+        })),
+        Action::Ctx(ActionCtx::Logout) => Some(Box::new(|event| {
+            matches!(event, Event::UserLoggedOut { .. })
+        })),
+        Action::Ctx(ActionCtx::InstallAddon(descriptor)) => {
+            Some(Box::new(move |event| match event {
+                Event::AddonInstalled { transport_url, .. } => {
+                    *transport_url == descriptor.transport_url
+                }
+                _ => false,
+            }))
+        }
+        Action::Ctx(ActionCtx::UninstallAddon(descriptor)) => {
+            Some(Box::new(move |event| match event {
+                Event::AddonUninstalled { transport_url, .. } => {
+                    *transport_url == descriptor.transport_url
+                }
+                _ => false,
+            }))
+        }
+        Action::Ctx(ActionCtx::UpdateSettings(_)) => Some(Box::new(|event| {
+            matches!(event, Event::SettingsUpdated { .. })
+        })),
+        Action::Ctx(ActionCtx::AddToLibrary(meta_item)) => {
+            Some(Box::new(move |event| match event {
+                Event::LibraryItemAdded { id } => *id == meta_item.id,
+                _ => false,
+            }))
+        }
+        Action::Ctx(ActionCtx::RemoveFromLibrary(item_id)) => {
+            Some(Box::new(move |event| match event {
+                Event::LibraryItemRemoved { id } => *id == item_id,
+                _ => false,
+            }))
+        }
+        _ => None,
+    }
+}
+
+pub fn promise() -> (js_sys::Promise, Settlement) {
+    let mut functions = None;
+    let promise = js_sys::Promise::new(&mut |resolve, reject| functions = Some((resolve, reject)));
+    let (resolve, reject) = functions.expect("promise executor was not called");
+    (promise, Settlement { resolve, reject })
+}
+
+// Called once core got the action, group_id is the effect group of its dispatch
+pub fn register(action: &Action, group_id: u32, settlement: Settlement) {
+    let id = NEXT_SETTLEMENT_ID.with(|next_id| {
+        let id = next_id.get();
+        next_id.set(id.wrapping_add(1));
+        id
+    });
+    let matcher = event_matcher(action);
+    let wait_for_effects = matcher.is_none();
+    let timeout_callback = Closure::once_into_js(move || {
+        if let Some(settlement) = take(id) {
+            settlement.reject(&JsValue::from_str("action timed out"));
+        };
+    });
+    let timeout_handle = web_sys::window()
+        .expect("window is not available")
+        .set_timeout_with_callback_and_timeout_and_arguments_0(
+            timeout_callback.unchecked_ref(),
+            SETTLEMENT_TIMEOUT,
+        )
+        .expect("set timeout failed");
+    PENDING_SETTLEMENTS.with(|pending_settlements| {
+        pending_settlements.borrow_mut().push(PendingSettlement {
+            id,
+            matcher,
+            settlement,
+            timeout_handle,
+        })
+    });
+    if wait_for_effects {
+        effect_groups::on_finished(group_id, move || {
+            if let Some(settlement) = take(id) {
+                settlement.resolve(&JsValue::UNDEFINED);
+            };
+        });
+    };
+}
+
+fn take(id: u32) -> Option<Settlement> {
+    let pending_settlement = PENDING_SETTLEMENTS.with(|pending_settlements| {
+        let mut pending_settlements = pending_settlements.borrow_mut();
+        let index = pending_settlements
+            .iter()
+            .position(|pending_settlement| pending_settlement.id == id)?;
+        Some(pending_settlements.remove(index))
+    })?;
+    web_sys::window()
+        .expect("window is not available")
+        .clear_timeout_with_handle(pending_settlement.timeout_handle);
+    Some(pending_settlement.settlement)
+}
+
+pub fn settle(events: &[Event]) {
+    let settled = PENDING_SETTLEMENTS.with(|pending_settlements| {
+        let mut pending_settlements = pending_settlements.borrow_mut();
+        let mut settled = vec![];
+        let mut index = 0;
+        while index < pending_settlements.len() {
+            let result = pending_settlements[index]
+                .matcher
+                .as_ref()
+                .and_then(|matcher| {
+                    events.iter().find_map(|event| match event {
+                        Event::Error { source, .. } if matcher(source) => Some(Err(event)),
+                        _ if matcher(event) => Some(Ok(to_js_value(event).unwrap())),
+                        _ => None,
+                    })
+                });
+            match result {
+                Some(result) => {
+                    let pending_settlement = pending_settlements.remove(index);
+                    settled.push((
+                        pending_settlement,
+                        result.map_err(|event| to_js_value(event).unwrap()),
+                    ));
+                }
+                None => index += 1,
+            };
+        }
+        settled
+    });
+    for (pending_settlement, result) in settled {
+        web_sys::window()
+            .expect("window is not available")
+            .clear_timeout_with_handle(pending_settlement.timeout_handle);
+        match result {
+            Ok(value) => pending_settlement.settlement.resolve(&value),
+            Err(error) => pending_settlement.settlement.reject(&error),
+        };
+    }
+}
+
+pub fn clear() {
+    let pending_settlements = PENDING_SETTLEMENTS.with(|pending_settlements| {
+        pending_settlements
+            .borrow_mut()
+            .drain(..)
+            .collect::<Vec<_>>()
+    });
+    for pending_settlement in pending_settlements {
+        web_sys::window()
+            .expect("window is not available")
+            .clear_timeout_with_handle(pending_settlement.timeout_handle);
+        pending_settlement
+            .settlement
+            .reject(&JsValue::from_str("runtime was destroyed"));
+    }
+}
@@ -0,0 +1,212 @@
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+use url::Url;
+
+pub const ADDON_MIRRORS_STORAGE_KEY: &str = "addon_mirrors";
+const FAILOVER_THRESHOLD: u32 = 3;
+const MANIFEST_PATH: &str = "manifest.json";
+
+thread_local! {
+    static ADDON_MIRRORS: RefCell<BTreeMap<Url, AddonEndpoints>> = Default::default();
+}
+
+struct AddonEndpoints {
+    mirrors: Vec<Url>,
+    active: usize,
+    failures: u32,
+}
+
+pub struct Endpoint {
+    pub transport_url: Url,
+    pub url: String,
+}
+
+pub fn init(mirrors: BTreeMap<Url, Vec<Url>>) {
+    ADDON_MIRRORS.with(|addon_mirrors| {
+        *addon_mirrors.borrow_mut() = mirrors
+            .into_iter()
+            .map(|(transport_url, mirrors)| {
+                (
+                    transport_url,
+                    AddonEndpoints {
+                        mirrors,
+                        active: 0,
+                        failures: 0,
+                    },
+                )
+            })
+            .collect();
+    });
+}
+
+pub fn set_mirrors(transport_url: Url, mirrors: Vec<Url>) -> BTreeMap<Url, Vec<Url>> {
+    ADDON_MIRRORS.with(|addon_mirrors| {
+        let mut addon_mirrors = addon_mirrors.borrow_mut();
+        if mirrors.is_empty() {
+            addon_mirrors.remove(&transport_url);
+        } else {
+            addon_mirrors.insert(
+                transport_url,
+                AddonEndpoints {
+                    mirrors,
+                    active: 0,
+                    failures: 0,
+                },
+            );
+        };
+        addon_mirrors
+            .iter()
+            .map(|(transport_url, endpoints)| {
+                (transport_url.to_owned(), endpoints.mirrors.to_owned())
+            })
+            .collect()
+    })
+}
+
+pub fn mirrors(transport_url: &Url) -> Vec<Url> {
+    ADDON_MIRRORS.with(|addon_mirrors| {
+        addon_mirrors
+            .borrow()
+            .get(transport_url)
+            .map(|endpoints| endpoints.mirrors.to_owned())
+            .unwrap_or_default()
+    })
+}
+
+pub fn active_transport_url(transport_url: &Url) -> Url {
+    ADDON_MIRRORS.with(|addon_mirrors| {
+        addon_mirrors
+            .borrow()
+            .get(transport_url)
+            .and_then(|endpoints| {
+                endpoints
+                    .active
+                    .checked_sub(1)
+                    .and_then(|index| endpoints.mirrors.get(index))
+                    .cloned()
+            })
+            .unwrap_or_else(|| transport_url.to_owned())
+    })
+}
+
+// When several addons share a base, the most specific one is picked
+pub fn resolve(url: &str) -> Option<Endpoint> {
+    let transport_url = ADDON_MIRRORS.with(|addon_mirrors| {
+        addon_mirrors
+            .borrow()
+            .keys()
+            .filter(|transport_url| url.starts_with(transport_base(transport_url)))
+            .max_by_key(|transport_url| transport_base(transport_url).len())
+            .cloned()
+    })?;
+    let path = url.strip_prefix(transport_base(&transport_url))?;
+    let active_transport_url = active_transport_url(&transport_url);
+    let url = format!("{}{}", transport_base(&active_transport_url), path);
+    Some(Endpoint { transport_url, url })
+}
+
+pub fn record_result(transport_url: &Url, success: bool) {
+    ADDON_MIRRORS.with(|addon_mirrors| {
+        if let Some(endpoints) = addon_mirrors.borrow_mut().get_mut(transport_url) {
+            if success {
+                endpoints.failures = 0;
+            } else {
+                endpoints.failures += 1;
+                if endpoints.failures >= FAILOVER_THRESHOLD {
+                    endpoints.active = (endpoints.active + 1) % (endpoints.mirrors.len() + 1);
+                    endpoints.failures = 0;
+                };
+            };
+        };
+    });
+}
+
+fn transport_base(transport_url: &Url) -> &str {
+    let transport_url = transport_url.as_str();
+    transport_url
+        .strip_suffix(MANIFEST_PATH)
+        .unwrap_or(transport_url)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn url(url: &str) -> Url {
+        Url::parse(url).unwrap()
+    }
+
+    fn resolved_url(url: &str) -> Option<String> {
+        resolve(url).map(|endpoint| endpoint.url)
+    }
+
+    #[test]
+    fn resolve_picks_the_most_specific_base() {
+        init(
+            vec![
+                (
+                    url("https://example.com/manifest.json"),
+                    vec![url("https://mirror.example.com/manifest.json")],
+                ),
+                (
+                    url("https://example.com/cinemeta/manifest.json"),
+                    vec![url("https://cinemeta.example.org/manifest.json")],
+                ),
+            ]
+            .into_iter()
+            .collect(),
+        );
+        let endpoint = resolve("https://example.com/cinemeta/meta/movie/tt1.json").unwrap();
+        assert_eq!(
+            endpoint.transport_url,
+            url("https://example.com/cinemeta/manifest.json")
+        );
+        assert_eq!(
+            endpoint.url,
+            "https://example.com/cinemeta/meta/movie/tt1.json"
+        );
+        assert_eq!(
+            resolve("https://example.com/meta/movie/tt1.json")
+                .unwrap()
+                .transport_url,
+            url("https://example.com/manifest.json")
+        );
+        assert!(resolve("https://other.example.com/meta/movie/tt1.json").is_none());
+    }
+
+    #[test]
+    fn resolve_fails_over_to_mirrors() {
+        let transport_url = url("https://example.com/manifest.json");
+        init(
+            vec![(
+                transport_url.to_owned(),
+                vec![url("https://mirror.example.com/addon/manifest.json")],
+            )]
+            .into_iter()
+            .collect(),
+        );
+        for _ in 1..FAILOVER_THRESHOLD {
+            record_result(&transport_url, false);
+        }
+        record_result(&transport_url, true);
+        record_result(&transport_url, false);
+        assert_eq!(
+            resolved_url("https://example.com/meta/movie/tt1.json"),
+            Some("https://example.com/meta/movie/tt1.json".to_owned())
+        );
+        for _ in 1..FAILOVER_THRESHOLD {
+            record_result(&transport_url, false);
+        }
+        assert_eq!(
+            resolved_url("https://example.com/meta/movie/tt1.json"),
+            Some("https://mirror.example.com/addon/meta/movie/tt1.json".to_owned())
+        );
+        for _ in 0..FAILOVER_THRESHOLD {
+            record_result(&transport_url, false);
+        }
+        assert_eq!(
+            resolved_url("https://example.com/meta/movie/tt1.json"),
+            Some("https://example.com/meta/movie/tt1.json".to_owned())
+        );
+    }
+}
@@ -0,0 +1,35 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+use url::Url;
+
+pub const ADDON_PRIORITIES_STORAGE_KEY: &str = "addon_priorities";
+
+thread_local! {
+    static ADDON_PRIORITIES: RefCell<HashMap<Url, i32>> = Default::default();
+}
+
+pub fn init(addon_priorities: HashMap<Url, i32>) {
+    ADDON_PRIORITIES.with(|current| *current.borrow_mut() = addon_priorities);
+}
+
+pub fn priority(transport_url: &Url) -> i32 {
+    ADDON_PRIORITIES.with(|addon_priorities| {
+        addon_priorities
+            .borrow()
+            .get(transport_url)
+            .copied()
+            .unwrap_or_default()
+    })
+}
+
+pub fn set_priority(transport_url: Url, priority: i32) -> HashMap<Url, i32> {
+    ADDON_PRIORITIES.with(|addon_priorities| {
+        let mut addon_priorities = addon_priorities.borrow_mut();
+        if priority == 0 {
+            addon_priorities.remove(&transport_url);
+        } else {
+            addon_priorities.insert(transport_url, priority);
+        };
+        addon_priorities.to_owned()
+    })
+}
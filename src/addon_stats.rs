@@ -0,0 +1,102 @@
+use serde::Serialize;
+use std::cell::RefCell;
+use std::collections::{HashMap, VecDeque};
+use stremio_core::types::addon::Descriptor;
+use url::Url;
+
+const STATS_WINDOW: f64 = 60.0 * 60.0 * 1000.0;
+const MAX_RECORDS: usize = 1000;
+const MANIFEST_PATH: &str = "manifest.json";
+
+thread_local! {
+    static FETCH_RECORDS: RefCell<VecDeque<FetchRecord>> = Default::default();
+}
+
+struct FetchRecord {
+    url: String,
+    time: f64,
+    latency: f64,
+    result: Result<usize, String>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AddonStats<'a> {
+    pub transport_url: &'a Url,
+    pub name: &'a String,
+    pub requests: HashMap<String, u32>,
+    pub median_latency: Option<f64>,
+    pub bytes_transferred: usize,
+    pub errors: HashMap<String, u32>,
+}
+
+pub fn record(url: String, latency: f64, result: Result<usize, String>) {
+    let time = js_sys::Date::now();
+    FETCH_RECORDS.with(|fetch_records| {
+        let mut fetch_records = fetch_records.borrow_mut();
+        while fetch_records.len() >= MAX_RECORDS
+            || fetch_records
+                .front()
+                .map_or(false, |record| time - record.time > STATS_WINDOW)
+        {
+            fetch_records.pop_front();
+        }
+        fetch_records.push_back(FetchRecord {
+            url,
+            time,
+            latency,
+            result,
+        });
+    });
+}
+
+pub fn addon_stats(addons: &[Descriptor]) -> Vec<AddonStats> {
+    let now = js_sys::Date::now();
+    FETCH_RECORDS.with(|fetch_records| {
+        let fetch_records = fetch_records.borrow();
+        addons
+            .iter()
+            .map(|addon| {
+                let transport_url = addon.transport_url.as_str();
+                let base = transport_url
+                    .strip_suffix(MANIFEST_PATH)
+                    .unwrap_or(transport_url);
+                let records = fetch_records
+                    .iter()
+                    .filter(|record| now - record.time <= STATS_WINDOW)
+                    .filter(|record| record.url.starts_with(base))
+                    .collect::<Vec<_>>();
+                let mut requests = HashMap::new();
+                let mut errors = HashMap::new();
+                for record in records.iter() {
+                    let resource = record.url[base.len()..]
+                        .trim_start_matches('/')
+                        .split(|c| c == '/' || c == '.')
+                        .next()
+                        .unwrap_or_default()
+                        .to_owned();
+                    *requests.entry(resource).or_insert(0) += 1;
+                    if let Err(error) = &record.result {
+                        *errors.entry(error.to_owned()).or_insert(0) += 1;
+                    };
+                }
+                let mut latencies = records
+                    .iter()
+                    .map(|record| record.latency)
+                    .collect::<Vec<_>>();
+                latencies.sort_by(|a, b| a.partial_cmp(b).expect("latency is NaN"));
+                AddonStats {
+                    transport_url: &addon.transport_url,
+                    name: &addon.manifest.name,
+                    requests,
+                    median_latency: latencies.get(latencies.len() / 2).copied(),
+                    bytes_transferred: records
+                        .iter()
+                        .filter_map(|record| record.result.as_ref().ok())
+                        .sum(),
+                    errors,
+                }
+            })
+            .collect()
+    })
+}
@@ -0,0 +1,337 @@
+use crate::env::{js_error_message, WebEnv};
+use crate::js_value::from_js_value;
+use crate::runtime_pause;
+use futures::FutureExt;
+use serde::Serialize;
+use serde_json::Value;
+use std::cell::{Cell, RefCell};
+use stremio_core::runtime::Env;
+use url::Url;
+use wasm_bindgen::closure::Closure;
+use wasm_bindgen::{JsCast, JsValue};
+use wasm_bindgen_futures::JsFuture;
+
+pub const ANALYTICS_ENABLED_STORAGE_KEY: &str = "analytics_enabled";
+pub const ANALYTICS_INSTALL_ID_STORAGE_KEY: &str = "analytics_install_id";
+const ANALYTICS_ID_LENGTH: u32 = 16;
+const ANALYTICS_BATCH_SIZE: usize = 10;
+const ANALYTICS_FLUSH_INTERVAL: i32 = 30_000;
+const MAX_QUEUED_MESSAGES: usize = 100;
+const MAX_DELIVERY_ATTEMPTS: u32 = 3;
+
+thread_local! {
+    static ANALYTICS_ENABLED: Cell<bool> = Cell::new(true);
+    static ANALYTICS_URL: RefCell<Option<Url>> = Default::default();
+    static ANALYTICS_CONTEXT: RefCell<Value> = RefCell::new(Value::Null);
+    static GET_APP_CONTEXT: RefCell<Option<js_sys::Function>> = Default::default();
+    static ROUTE_CONTEXT: RefCell<Value> = RefCell::new(Value::Null);
+    static INSTALL_ID: RefCell<Option<String>> = Default::default();
+    static SESSION: RefCell<Option<Session>> = Default::default();
+    static QUEUED_MESSAGES: RefCell<Vec<AnalyticsMessage>> = Default::default();
+    static SCHEDULED: Cell<bool> = Cell::new(false);
+}
+
+struct Session {
+    id: String,
+    started_at: i64,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AnalyticsMessage {
+    pub event: &'static str,
+    pub args: Value,
+    pub app_context: Value,
+    pub install_id: Option<String>,
+    pub session_id: Option<String>,
+    pub time: i64,
+    #[serde(skip)]
+    attempts: u32,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct AnalyticsBatch {
+    context: Value,
+    messages: Vec<AnalyticsMessage>,
+}
+
+pub fn init(analytics_url: Option<Url>) {
+    ANALYTICS_URL.with(|current| *current.borrow_mut() = analytics_url);
+    if SCHEDULED.with(|scheduled| scheduled.replace(true)) {
+        return;
+    };
+    let window = web_sys::window().expect("window is not available");
+    let flush_interval = Closure::wrap(Box::new(|| {
+        if !runtime_pause::is_paused() {
+            flush(false);
+        };
+    }) as Box<dyn FnMut()>);
+    window
+        .set_interval_with_callback_and_timeout_and_arguments_0(
+            flush_interval.as_ref().unchecked_ref(),
+            ANALYTICS_FLUSH_INTERVAL,
+        )
+        .expect("set interval failed");
+    // Pending fetches don't survive unload, beacons do
+    let flush_on_unload = Closure::wrap(Box::new(|| {
+        end_session();
+        flush(true);
+    }) as Box<dyn FnMut()>);
+    window
+        .add_event_listener_with_callback("pagehide", flush_on_unload.as_ref().unchecked_ref())
+        .expect("add pagehide listener failed");
+    flush_interval.forget();
+    flush_on_unload.forget();
+}
+
+pub fn is_allowed() -> bool {
+    ANALYTICS_ENABLED.with(|enabled| enabled.get())
+}
+
+// Nothing is collected unless the user allows it and there is somewhere to deliver it
+pub fn is_enabled() -> bool {
+    is_allowed() && ANALYTICS_URL.with(|analytics_url| analytics_url.borrow().is_some())
+}
+
+pub fn set_enabled(enabled: bool) {
+    ANALYTICS_ENABLED.with(|current| current.set(enabled));
+    if !enabled {
+        QUEUED_MESSAGES.with(|queued_messages| queued_messages.borrow_mut().clear());
+    };
+}
+
+// Install ids are random and only tie together events coming from the same browser
+fn random_id() -> Result<String, JsValue> {
+    let bytes = js_sys::Uint8Array::new_with_length(ANALYTICS_ID_LENGTH);
+    web_sys::window()
+        .expect("window is not available")
+        .crypto()?
+        .get_random_values_with_array_buffer_view(&bytes)?;
+    Ok(bytes
+        .to_vec()
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect())
+}
+
+// The install id is only created once there is something to deliver,
+// so nothing is persisted for users who never opted in
+pub fn start_session(install_id: Option<String>) {
+    INSTALL_ID.with(|current| *current.borrow_mut() = install_id);
+    let session_id = match random_id() {
+        Ok(session_id) => session_id,
+        Err(error) => {
+            web_sys::console::error_2(&JsValue::from("analytics session id failed"), &error);
+            return;
+        }
+    };
+    let started_at = WebEnv::now().timestamp_millis();
+    SESSION.with(|session| {
+        *session.borrow_mut() = Some(Session {
+            id: session_id,
+            started_at,
+        })
+    });
+    track("sessionStart", Value::Null);
+}
+
+fn install_id() -> Option<String> {
+    if let Some(install_id) = INSTALL_ID.with(|install_id| install_id.borrow().to_owned()) {
+        return Some(install_id);
+    };
+    match random_id() {
+        Ok(install_id) => {
+            INSTALL_ID.with(|current| *current.borrow_mut() = Some(install_id.to_owned()));
+            WebEnv::exec(
+                WebEnv::set_storage(ANALYTICS_INSTALL_ID_STORAGE_KEY, Some(&install_id))
+                    .map(|_| ()),
+            );
+            Some(install_id)
+        }
+        Err(error) => {
+            web_sys::console::error_2(&JsValue::from("analytics install id failed"), &error);
+            None
+        }
+    }
+}
+
+fn end_session() {
+    let started_at =
+        SESSION.with(|session| session.borrow().as_ref().map(|session| session.started_at));
+    if let Some(started_at) = started_at {
+        track(
+            "sessionEnd",
+            serde_json::json!({
+                "duration": WebEnv::now().timestamp_millis() - started_at,
+            }),
+        );
+    };
+}
+
+pub fn set_get_app_context(get_app_context: Option<js_sys::Function>) {
+    GET_APP_CONTEXT.with(|current| *current.borrow_mut() = get_app_context);
+}
+
+pub fn set_route_context(route_context: Value) {
+    ROUTE_CONTEXT.with(|current| *current.borrow_mut() = route_context);
+}
+
+// The app context is taken when the message is created, so it reflects the route the event came from.
+// It is derived from the route state, anything the app still provides takes precedence.
+fn app_context() -> Value {
+    let route_context = ROUTE_CONTEXT.with(|route_context| route_context.borrow().to_owned());
+    match (route_context, provided_app_context()) {
+        (Value::Object(mut route_context), Value::Object(app_context)) => {
+            route_context.extend(app_context);
+            Value::Object(route_context)
+        }
+        (route_context, Value::Null) => route_context,
+        (_, app_context) => app_context,
+    }
+}
+
+fn provided_app_context() -> Value {
+    GET_APP_CONTEXT.with(|get_app_context| match &*get_app_context.borrow() {
+        Some(get_app_context) => match get_app_context.call0(&JsValue::NULL) {
+            Ok(app_context) => from_js_value(app_context).unwrap_or(Value::Null),
+            Err(error) => {
+                web_sys::console::error_2(&JsValue::from("get app context failed"), &error);
+                Value::Null
+            }
+        },
+        None => Value::Null,
+    })
+}
+
+pub fn set_context(context: Value) {
+    ANALYTICS_CONTEXT.with(|current| *current.borrow_mut() = context);
+}
+
+pub fn track(event: &'static str, args: Value) {
+    if !is_enabled() {
+        return;
+    };
+    let app_context = app_context();
+    let session_id = SESSION.with(|session| {
+        session
+            .borrow()
+            .as_ref()
+            .map(|session| session.id.to_owned())
+    });
+    let batch_full = QUEUED_MESSAGES.with(|queued_messages| {
+        let mut queued_messages = queued_messages.borrow_mut();
+        if queued_messages.len() >= MAX_QUEUED_MESSAGES {
+            queued_messages.remove(0);
+        };
+        queued_messages.push(AnalyticsMessage {
+            event,
+            args,
+            app_context,
+            install_id: None,
+            session_id,
+            time: WebEnv::now().timestamp_millis(),
+            attempts: 0,
+        });
+        queued_messages.len() >= ANALYTICS_BATCH_SIZE
+    });
+    if batch_full {
+        flush(false);
+    };
+}
+
+fn flush(beacon: bool) {
+    let analytics_url = ANALYTICS_URL.with(|analytics_url| analytics_url.borrow().to_owned());
+    let analytics_url = match analytics_url {
+        Some(analytics_url) => analytics_url,
+        None => return,
+    };
+    if !is_enabled() {
+        return;
+    };
+    let mut messages = QUEUED_MESSAGES
+        .with(|queued_messages| queued_messages.borrow_mut().drain(..).collect::<Vec<_>>());
+    if messages.is_empty() {
+        return;
+    };
+    let install_id = install_id();
+    for message in messages.iter_mut() {
+        message.install_id = install_id.to_owned();
+    }
+    let batch = AnalyticsBatch {
+        context: ANALYTICS_CONTEXT.with(|context| context.borrow().to_owned()),
+        messages,
+    };
+    if beacon {
+        let body = serde_json::to_string(&batch).expect("analytics batch serialization failed");
+        let _ = web_sys::window()
+            .expect("window is not available")
+            .navigator()
+            .send_beacon_with_opt_str(analytics_url.as_str(), Some(&body));
+    } else {
+        WebEnv::exec(async move {
+            if let Err(error) = deliver(&analytics_url, &batch).await {
+                web_sys::console::warn_1(&JsValue::from(format!(
+                    "analytics delivery failed: {}",
+                    error
+                )));
+                requeue(batch.messages);
+            };
+        });
+    };
+}
+
+// Delivery only depends on the status, the collector may answer without a body
+async fn deliver(analytics_url: &Url, batch: &AnalyticsBatch) -> Result<(), String> {
+    let body = serde_json::to_string(batch).map_err(|error| error.to_string())?;
+    let headers = web_sys::Headers::new().map_err(js_error_message)?;
+    headers
+        .set("Content-Type", "application/json")
+        .map_err(js_error_message)?;
+    let mut request_options = web_sys::RequestInit::new();
+    request_options
+        .method("POST")
+        .headers(&headers)
+        .body(Some(&JsValue::from_str(&body)));
+    let request = web_sys::Request::new_with_str_and_init(analytics_url.as_str(), &request_options)
+        .map_err(js_error_message)?;
+    let resp = JsFuture::from(
+        web_sys::window()
+            .expect("window is not available")
+            .fetch_with_request(&request),
+    )
+    .await
+    .map_err(js_error_message)?
+    .unchecked_into::<web_sys::Response>();
+    if resp.ok() {
+        Ok(())
+    } else {
+        Err(format!("Unexpected HTTP status code {}", resp.status()))
+    }
+}
+
+// Failed messages go back in front of the newer ones, until they run out of attempts
+// or the queue is full
+fn requeue(messages: Vec<AnalyticsMessage>) {
+    if !is_enabled() {
+        return;
+    };
+    QUEUED_MESSAGES.with(|queued_messages| {
+        let mut queued_messages = queued_messages.borrow_mut();
+        let mut requeued_messages = messages
+            .into_iter()
+            .filter_map(|mut message| {
+                message.attempts += 1;
+                if message.attempts < MAX_DELIVERY_ATTEMPTS {
+                    Some(message)
+                } else {
+                    None
+                }
+            })
+            .collect::<Vec<_>>();
+        requeued_messages.append(&mut queued_messages);
+        let overflow = requeued_messages.len().saturating_sub(MAX_QUEUED_MESSAGES);
+        requeued_messages.drain(..overflow);
+        *queued_messages = requeued_messages;
+    });
+}
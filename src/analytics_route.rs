@@ -0,0 +1,33 @@
+use crate::analytics;
+use crate::model::{route_context, RouteContext, WebModel, WebModelField};
+use crate::route_timing::route_name;
+use std::cell::Cell;
+use stremio_core::runtime::msg::Action;
+
+thread_local! {
+    static CURRENT_ROUTE: Cell<Option<&'static str>> = Cell::new(None);
+}
+
+// The route is the last one loaded, unloading it (or everything) leaves no route
+pub fn navigate(action: &Action, field: Option<&WebModelField>) {
+    let route = field.and_then(route_name);
+    match action {
+        Action::Load(_) if route.is_some() => CURRENT_ROUTE.with(|current| current.set(route)),
+        Action::Unload => CURRENT_ROUTE.with(|current| {
+            if route.is_none() || current.get() == route {
+                current.set(None);
+            };
+        }),
+        _ => {}
+    };
+}
+
+pub fn context(model: &WebModel) -> Option<RouteContext> {
+    CURRENT_ROUTE
+        .with(|current| current.get())
+        .map(|route| route_context(model, route))
+}
+
+pub fn update(model: &WebModel) {
+    analytics::set_route_context(serde_json::to_value(&context(model)).unwrap_or_default());
+}
@@ -0,0 +1,121 @@
+use crate::ui_state;
+use serde::{Deserialize, Serialize};
+use stremio_core::types::resource::{MetaItem, PosterShape};
+use url::Url;
+
+pub const ARTWORK_UI_STATE_KEY: &str = "artwork";
+const METAHUB_HOST: &str = "images.metahub.space";
+const METAHUB_SIZES: &[&str] = &["small", "medium", "large"];
+
+#[derive(Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ArtworkKind {
+    Poster,
+    Landscape,
+    Square,
+    Background,
+    Logo,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ArtworkVariant {
+    pub kind: ArtworkKind,
+    pub url: String,
+    pub resolution: Option<String>,
+}
+
+#[derive(Clone, Copy, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ArtworkSelection {
+    pub hero: Option<ArtworkKind>,
+    pub card: Option<ArtworkKind>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Artwork {
+    pub variants: Vec<ArtworkVariant>,
+    pub hero: Option<ArtworkKind>,
+    pub card: Option<ArtworkKind>,
+}
+
+fn poster_kind(poster_shape: &PosterShape) -> ArtworkKind {
+    match poster_shape {
+        PosterShape::Landscape => ArtworkKind::Landscape,
+        PosterShape::Square => ArtworkKind::Square,
+        PosterShape::Poster => ArtworkKind::Poster,
+    }
+}
+
+// Metahub urls carry their size in the path, so every size it serves is listed
+fn variants(kind: ArtworkKind, url: &str) -> Vec<ArtworkVariant> {
+    let parsed = Url::parse(url)
+        .ok()
+        .filter(|url| url.host_str() == Some(METAHUB_HOST));
+    let segments = parsed
+        .as_ref()
+        .and_then(|url| url.path_segments())
+        .map(|segments| {
+            segments
+                .map(|segment| segment.to_owned())
+                .collect::<Vec<_>>()
+        })
+        .unwrap_or_default();
+    match parsed.as_ref().zip(segments.get(1)) {
+        Some((parsed, size)) if METAHUB_SIZES.contains(&size.as_str()) => METAHUB_SIZES
+            .iter()
+            .map(|size| {
+                let mut variant_url = parsed.to_owned();
+                let mut path = segments.to_owned();
+                path[1] = (*size).to_owned();
+                variant_url.set_path(&path.join("/"));
+                ArtworkVariant {
+                    kind,
+                    url: variant_url.to_string(),
+                    resolution: Some((*size).to_owned()),
+                }
+            })
+            .collect(),
+        _ => vec![ArtworkVariant {
+            kind,
+            url: url.to_owned(),
+            resolution: None,
+        }],
+    }
+}
+
+pub fn selection(id: &str) -> ArtworkSelection {
+    ui_state::get(id)
+        .remove(ARTWORK_UI_STATE_KEY)
+        .and_then(|selection| serde_json::from_value(selection).ok())
+        .unwrap_or_default()
+}
+
+// A selected kind the item doesn't have falls back to the default, text-free backdrops for the hero
+// and the poster in its own shape for cards
+pub fn artwork(meta_item: &MetaItem) -> Artwork {
+    let variants = vec![
+        (poster_kind(&meta_item.poster_shape), &meta_item.poster),
+        (ArtworkKind::Background, &meta_item.background),
+        (ArtworkKind::Logo, &meta_item.logo),
+    ]
+    .into_iter()
+    .filter_map(|(kind, url)| url.as_ref().map(|url| variants(kind, url)))
+    .flatten()
+    .collect::<Vec<_>>();
+    let selection = selection(&meta_item.id);
+    let available = |kind: ArtworkKind| variants.iter().any(|variant| variant.kind == kind);
+    let pick = |selected: Option<ArtworkKind>, defaults: &[ArtworkKind]| {
+        selected
+            .into_iter()
+            .chain(defaults.iter().copied())
+            .find(|kind| available(*kind))
+    };
+    let poster_kind = poster_kind(&meta_item.poster_shape);
+    Artwork {
+        hero: pick(selection.hero, &[ArtworkKind::Background, poster_kind]),
+        card: pick(selection.card, &[poster_kind, ArtworkKind::Background]),
+        variants,
+    }
+}
@@ -0,0 +1,94 @@
+use serde::{Deserialize, Serialize};
+use std::cell::RefCell;
+use std::collections::HashMap;
+use stremio_core::types::addon::ResourceRequest;
+
+pub const BOARD_LAYOUTS_STORAGE_KEY: &str = "board_layouts";
+const ANONYMOUS_PROFILE_KEY: &str = "";
+
+thread_local! {
+    static BOARD_LAYOUTS: RefCell<HashMap<String, BoardLayout>> = Default::default();
+}
+
+#[derive(Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BoardLayout {
+    pub pinned: Vec<String>,
+    pub order: Vec<String>,
+}
+
+impl BoardLayout {
+    pub fn is_pinned(&self, row_id: &str) -> bool {
+        self.pinned.iter().any(|pinned| pinned == row_id)
+    }
+    // Pinned rows come first in pin order, then manually ordered rows, then the rest in the given order
+    pub fn position(&self, row_id: &str) -> (usize, usize) {
+        match self.pinned.iter().position(|pinned| pinned == row_id) {
+            Some(position) => (0, position),
+            None => match self.order.iter().position(|ordered| ordered == row_id) {
+                Some(position) => (1, position),
+                None => (2, 0),
+            },
+        }
+    }
+}
+
+pub fn row_id(request: &ResourceRequest) -> String {
+    format!(
+        "{}/{}/{}",
+        request.base, request.path.r#type, request.path.id
+    )
+}
+
+pub fn init(board_layouts: HashMap<String, BoardLayout>) {
+    BOARD_LAYOUTS.with(|current| *current.borrow_mut() = board_layouts);
+}
+
+pub fn board_layout(uid: &Option<String>) -> BoardLayout {
+    BOARD_LAYOUTS.with(|board_layouts| {
+        board_layouts
+            .borrow()
+            .get(profile_key(uid))
+            .cloned()
+            .unwrap_or_default()
+    })
+}
+
+pub fn set_pinned(
+    uid: &Option<String>,
+    row_id: String,
+    pinned: bool,
+) -> HashMap<String, BoardLayout> {
+    update(uid, |board_layout| {
+        board_layout.pinned.retain(|current| *current != row_id);
+        if pinned {
+            board_layout.pinned.push(row_id);
+        };
+    })
+}
+
+pub fn set_order(uid: &Option<String>, row_ids: Vec<String>) -> HashMap<String, BoardLayout> {
+    update(uid, |board_layout| board_layout.order = row_ids)
+}
+
+pub fn reset(uid: &Option<String>) -> HashMap<String, BoardLayout> {
+    BOARD_LAYOUTS.with(|board_layouts| {
+        let mut board_layouts = board_layouts.borrow_mut();
+        board_layouts.remove(profile_key(uid));
+        board_layouts.to_owned()
+    })
+}
+
+fn update<F: FnOnce(&mut BoardLayout)>(uid: &Option<String>, f: F) -> HashMap<String, BoardLayout> {
+    BOARD_LAYOUTS.with(|board_layouts| {
+        let mut board_layouts = board_layouts.borrow_mut();
+        f(board_layouts
+            .entry(profile_key(uid).to_owned())
+            .or_default());
+        board_layouts.to_owned()
+    })
+}
+
+fn profile_key(uid: &Option<String>) -> &str {
+    uid.as_deref().unwrap_or(ANONYMOUS_PROFILE_KEY)
+}
@@ -0,0 +1,170 @@
+use crate::env::WebEnv;
+use crate::js_value::from_js_value;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::cell::{Cell, RefCell};
+use std::collections::VecDeque;
+use stremio_core::runtime::Env;
+use wasm_bindgen::JsValue;
+
+const MAX_ENTRIES: usize = 1000;
+const REDACTED: &str = "[redacted]";
+// Credentials show up under these keys in actions, core events and the errors they caused
+const SENSITIVE_KEYS: &[&str] = &[
+    "password",
+    "token",
+    "authKey",
+    "auth_key",
+    "authRequest",
+    "auth_request",
+];
+
+thread_local! {
+    static RECORDING: Cell<bool> = Cell::new(false);
+    static ENTRIES: RefCell<VecDeque<DebugLogEntry>> = Default::default();
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum DebugLogEntry {
+    #[serde(rename_all = "camelCase")]
+    Action {
+        time: i64,
+        action: Value,
+        field: Value,
+        #[serde(default)]
+        redacted: bool,
+    },
+    #[serde(rename_all = "camelCase")]
+    Event {
+        time: i64,
+        event: Value,
+        #[serde(default)]
+        redacted: bool,
+    },
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DebugLog {
+    pub version: &'static str,
+    pub exported_at: i64,
+    pub entries: Vec<DebugLogEntry>,
+}
+
+#[derive(Deserialize)]
+pub struct RecordedDebugLog {
+    pub entries: Vec<DebugLogEntry>,
+}
+
+// A fresh recording starts with every enable, the log of the previous one stays exportable until then
+pub fn set_recording(recording: bool) {
+    if recording && !RECORDING.with(|current| current.replace(true)) {
+        ENTRIES.with(|entries| entries.borrow_mut().clear());
+    } else if !recording {
+        RECORDING.with(|current| current.set(false));
+    };
+}
+
+pub fn clear() {
+    RECORDING.with(|recording| recording.set(false));
+    ENTRIES.with(|entries| entries.borrow_mut().clear());
+}
+
+pub fn is_recording() -> bool {
+    RECORDING.with(|recording| recording.get())
+}
+
+// Actions are recorded as they come from JS, before middlewares and validation
+pub fn record_action(action: &JsValue, field: &JsValue) {
+    if !is_recording() {
+        return;
+    };
+    let mut action = from_js_value(action.to_owned()).unwrap_or(Value::Null);
+    let redacted = redact_action(&mut action);
+    push(DebugLogEntry::Action {
+        time: WebEnv::now().timestamp_millis(),
+        action,
+        field: from_js_value(field.to_owned()).unwrap_or(Value::Null),
+        redacted,
+    });
+}
+
+pub fn record_event(event: &Value) {
+    if !is_recording() {
+        return;
+    };
+    let mut event = event.to_owned();
+    let redacted = redact(&mut event);
+    push(DebugLogEntry::Event {
+        time: WebEnv::now().timestamp_millis(),
+        event,
+        redacted,
+    });
+}
+
+pub fn debug_log() -> DebugLog {
+    DebugLog {
+        version: env!("CARGO_PKG_VERSION"),
+        exported_at: WebEnv::now().timestamp_millis(),
+        entries: ENTRIES.with(|entries| entries.borrow().iter().cloned().collect()),
+    }
+}
+
+// Only the actions are replayed, the events are expected to follow from them.
+// Redacted actions are skipped, dispatching them without their credentials would fail anyway.
+pub fn replay_actions(debug_log: RecordedDebugLog) -> Vec<(Value, Value)> {
+    debug_log
+        .entries
+        .into_iter()
+        .filter_map(|entry| match entry {
+            DebugLogEntry::Action {
+                action,
+                field,
+                redacted: false,
+                ..
+            } => Some((action, field)),
+            DebugLogEntry::Action { .. } | DebugLogEntry::Event { .. } => None,
+        })
+        .collect()
+}
+
+// Authenticate carries the whole login form, so its args are dropped rather than picked through
+fn redact_action(action: &mut Value) -> bool {
+    let authenticate = action.get("action").and_then(Value::as_str) == Some("Ctx")
+        && action.pointer("/args/action").and_then(Value::as_str) == Some("Authenticate");
+    match action.pointer_mut("/args/args") {
+        Some(args) if authenticate => {
+            *args = Value::from(REDACTED);
+            true
+        }
+        _ => redact(action),
+    }
+}
+
+fn redact(value: &mut Value) -> bool {
+    match value {
+        Value::Object(object) => object.iter_mut().fold(false, |redacted, (key, value)| {
+            if SENSITIVE_KEYS.contains(&key.as_str()) && !value.is_null() {
+                *value = Value::from(REDACTED);
+                true
+            } else {
+                redact(value) || redacted
+            }
+        }),
+        Value::Array(values) => values
+            .iter_mut()
+            .fold(false, |redacted, value| redact(value) || redacted),
+        _ => false,
+    }
+}
+
+fn push(entry: DebugLogEntry) {
+    ENTRIES.with(|entries| {
+        let mut entries = entries.borrow_mut();
+        if entries.len() >= MAX_ENTRIES {
+            entries.pop_front();
+        };
+        entries.push_back(entry);
+    });
+}
@@ -0,0 +1,38 @@
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::BTreeSet;
+use stremio_core::types::addon::Manifest;
+
+#[derive(Serialize)]
+pub struct ManifestChange {
+    pub key: String,
+    pub before: Option<Value>,
+    pub after: Option<Value>,
+}
+
+pub fn is_dev_addon_host(host: Option<&str>) -> bool {
+    matches!(host, Some("localhost") | Some("127.0.0.1"))
+}
+
+pub fn manifest_changes(before: Option<&Manifest>, after: &Manifest) -> Vec<ManifestChange> {
+    let before = match before.map(serde_json::to_value) {
+        Some(Ok(Value::Object(before))) => before,
+        _ => Default::default(),
+    };
+    let after = match serde_json::to_value(after) {
+        Ok(Value::Object(after)) => after,
+        _ => Default::default(),
+    };
+    before
+        .keys()
+        .chain(after.keys())
+        .collect::<BTreeSet<_>>()
+        .into_iter()
+        .filter(|key| before.get(*key) != after.get(*key))
+        .map(|key| ManifestChange {
+            key: key.to_owned(),
+            before: before.get(key).cloned(),
+            after: after.get(key).cloned(),
+        })
+        .collect()
+}
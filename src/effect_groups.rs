@@ -0,0 +1,191 @@
+use futures::future::LocalBoxFuture;
+use futures::FutureExt;
+use std::cell::{Cell, RefCell};
+use std::collections::HashMap;
+use std::future::Future;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+thread_local! {
+    static NEXT_GROUP_ID: Cell<u32> = Cell::new(0);
+    static CURRENT_GROUP_ID: Cell<Option<u32>> = Cell::new(None);
+    static GROUPS: RefCell<HashMap<u32, EffectGroup>> = Default::default();
+}
+
+#[derive(Default)]
+struct EffectGroup {
+    effects: usize,
+    callbacks: Vec<Box<dyn FnOnce()>>,
+}
+
+// Core spawns the effects of a message while the effect that produced it is still running,
+// so an effect spawned while another one is polled belongs to the same group
+pub struct GroupedEffect {
+    group_id: Option<u32>,
+    future: LocalBoxFuture<'static, ()>,
+}
+
+impl Future for GroupedEffect {
+    type Output = ();
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<()> {
+        let previous_group_id =
+            CURRENT_GROUP_ID.with(|current_group_id| current_group_id.replace(self.group_id));
+        let poll = self.future.as_mut().poll(cx);
+        CURRENT_GROUP_ID.with(|current_group_id| current_group_id.set(previous_group_id));
+        poll
+    }
+}
+
+// Effects that are cancelled or never run are dropped, which counts as finishing them
+impl Drop for GroupedEffect {
+    fn drop(&mut self) {
+        if let Some(group_id) = self.group_id {
+            finish_effect(group_id);
+        };
+    }
+}
+
+// Every effect spawned by f, and every effect those spawn in turn, is counted in a new group
+pub fn run<F: FnOnce()>(f: F) -> u32 {
+    let group_id = NEXT_GROUP_ID.with(|next_group_id| {
+        let group_id = next_group_id.get();
+        next_group_id.set(group_id.wrapping_add(1));
+        group_id
+    });
+    GROUPS.with(|groups| groups.borrow_mut().insert(group_id, EffectGroup::default()));
+    let previous_group_id =
+        CURRENT_GROUP_ID.with(|current_group_id| current_group_id.replace(Some(group_id)));
+    f();
+    CURRENT_GROUP_ID.with(|current_group_id| current_group_id.set(previous_group_id));
+    GROUPS.with(|groups| {
+        let mut groups = groups.borrow_mut();
+        if groups
+            .get(&group_id)
+            .map_or(false, |group| group.effects == 0)
+        {
+            groups.remove(&group_id);
+        };
+    });
+    group_id
+}
+
+pub fn track<F: Future<Output = ()> + 'static>(future: F) -> GroupedEffect {
+    let group_id = CURRENT_GROUP_ID.with(|current_group_id| current_group_id.get());
+    let group_id = group_id.filter(|group_id| {
+        GROUPS.with(|groups| match groups.borrow_mut().get_mut(group_id) {
+            Some(group) => {
+                group.effects += 1;
+                true
+            }
+            None => false,
+        })
+    });
+    GroupedEffect {
+        group_id,
+        future: future.boxed_local(),
+    }
+}
+
+// Runs the callback once all effects of the group are finished, right away if there are none
+pub fn on_finished<F: FnOnce() + 'static>(group_id: u32, callback: F) {
+    let callback = GROUPS.with(|groups| match groups.borrow_mut().get_mut(&group_id) {
+        Some(group) => {
+            group.callbacks.push(Box::new(callback));
+            None
+        }
+        None => Some(callback),
+    });
+    if let Some(callback) = callback {
+        callback();
+    };
+}
+
+fn finish_effect(group_id: u32) {
+    let callbacks = GROUPS.with(|groups| {
+        let mut groups = groups.borrow_mut();
+        let group = groups.get_mut(&group_id)?;
+        group.effects -= 1;
+        if group.effects > 0 {
+            return None;
+        };
+        groups.remove(&group_id).map(|group| group.callbacks)
+    });
+    for callback in callbacks.into_iter().flatten() {
+        callback();
+    }
+}
+
+pub fn clear() {
+    GROUPS.with(|groups| groups.borrow_mut().clear());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::channel::oneshot;
+    use futures::executor::LocalPool;
+    use futures::task::LocalSpawnExt;
+    use std::rc::Rc;
+
+    fn finished_flag(group_id: u32) -> Rc<Cell<bool>> {
+        let finished = Rc::new(Cell::new(false));
+        on_finished(group_id, {
+            let finished = finished.to_owned();
+            move || finished.set(true)
+        });
+        finished
+    }
+
+    #[test]
+    fn group_without_effects_finishes_right_away() {
+        let group_id = run(|| {});
+        assert!(finished_flag(group_id).get());
+    }
+
+    #[test]
+    fn group_waits_for_the_effects_of_its_effects() {
+        let mut pool = LocalPool::new();
+        let spawner = pool.spawner();
+        let (sender, receiver) = oneshot::channel::<()>();
+        let group_id = run(|| {
+            let inner_spawner = spawner.to_owned();
+            spawner
+                .spawn_local(track(async move {
+                    inner_spawner
+                        .spawn_local(track(receiver.map(|_| ())))
+                        .unwrap();
+                }))
+                .unwrap();
+        });
+        let finished = finished_flag(group_id);
+        pool.run_until_stalled();
+        assert!(!finished.get());
+        sender.send(()).unwrap();
+        pool.run_until_stalled();
+        assert!(finished.get());
+    }
+
+    #[test]
+    fn dropped_effects_count_as_finished() {
+        let (_sender, receiver) = oneshot::channel::<()>();
+        let mut effect = None;
+        let group_id = run(|| effect = Some(track(receiver.map(|_| ()))));
+        let finished = finished_flag(group_id);
+        assert!(!finished.get());
+        drop(effect);
+        assert!(finished.get());
+    }
+
+    #[test]
+    fn effects_outside_of_a_group_are_not_counted() {
+        let mut pool = LocalPool::new();
+        let (sender, receiver) = oneshot::channel::<()>();
+        pool.spawner()
+            .spawn_local(track(receiver.map(|_| ())))
+            .unwrap();
+        let group_id = run(|| {});
+        assert!(finished_flag(group_id).get());
+        sender.send(()).unwrap();
+        pool.run_until_stalled();
+    }
+}
@@ -1,25 +1,273 @@
+use crate::addon_mirrors;
+use crate::addon_stats;
+use crate::effect_groups;
+use crate::errors_feed;
+use crate::event::{self, WebEvent};
+use crate::js_value::to_js_value;
+use crate::library_recent;
+use crate::meta_prefetch;
+use crate::network_error::{self, NetworkErrorKind};
+use crate::request_limiter::{self, Slot};
+use crate::runtime_pause;
+use crate::storage_writes::{self, StorageWriteStats};
 use chrono::offset::TimeZone;
-use chrono::{DateTime, Utc};
-use futures::future::Either;
+use chrono::{DateTime, FixedOffset, Utc};
+use futures::channel::oneshot;
+use futures::future::{LocalBoxFuture, Shared};
 use futures::{future, Future, FutureExt, TryFutureExt};
+use http::header::CONTENT_TYPE;
 use http::{Method, Request};
+use lazy_static::lazy_static;
 use serde::{Deserialize, Serialize};
+use std::cell::{Cell, RefCell};
 use std::collections::HashMap;
+use std::rc::Rc;
+use std::sync::RwLock;
+use stremio_core::constants::{API_URL, LIBRARY_RECENT_STORAGE_KEY, LIBRARY_STORAGE_KEY};
 use stremio_core::runtime::{Env, EnvError, EnvFuture};
+use stremio_core::types::library::LibraryBucket;
+use wasm_bindgen::closure::Closure;
 use wasm_bindgen::{JsCast, JsValue};
 use wasm_bindgen_futures::{spawn_local, JsFuture};
 
+const CLOCK_SKEW_THRESHOLD: i64 = 30_000;
+const DATA_SAVER_STORAGE_KEY: &str = "data_saver";
+const FETCH_MAX_ATTEMPTS: u32 = 3;
+const FETCH_RETRY_BACKOFF: i32 = 500;
+const FETCH_RETRY_BUDGET_CAPACITY: u32 = 10;
+const FETCH_RETRY_URL_SHARE: u32 = 2;
+
+lazy_static! {
+    static ref CLOCK_SKEW: RwLock<Option<i64>> = Default::default();
+    static ref STORAGE_KEY_PREFIX: RwLock<String> = Default::default();
+}
+
+thread_local! {
+    static EFFECTS_CANCELLATION: RefCell<EffectsCancellation> =
+        RefCell::new(EffectsCancellation::new());
+    static DATA_SAVER: RefCell<Option<DataSaver>> = Default::default();
+    static DATA_SAVER_WATCHED: Cell<bool> = Cell::new(false);
+    static FETCH_RETRY_BUDGET: RefCell<Rc<RefCell<FetchRetryBudget>>> =
+        RefCell::new(Rc::new(RefCell::new(FetchRetryBudget::new(None))));
+}
+
+// Dropping the sender resolves every receiver, which ends the effects racing against it
+struct EffectsCancellation {
+    _sender: oneshot::Sender<()>,
+    cancelled: Shared<oneshot::Receiver<()>>,
+}
+
+impl EffectsCancellation {
+    fn new() -> Self {
+        let (sender, receiver) = oneshot::channel();
+        EffectsCancellation {
+            _sender: sender,
+            cancelled: receiver.shared(),
+        }
+    }
+}
+
+// Requests share the budget of the route load they were made for. No url may spend more
+// than its share of it, so one failing addon leaves retries for the others. Once a url or
+// the whole load is out of retries, failed requests are final and their rows end up errored.
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FetchRetryBudget {
+    pub route: Option<&'static str>,
+    pub capacity: u32,
+    pub spent: u32,
+    pub spent_by_url: HashMap<String, u32>,
+}
+
+impl FetchRetryBudget {
+    fn new(route: Option<&'static str>) -> Self {
+        FetchRetryBudget {
+            route,
+            capacity: FETCH_RETRY_BUDGET_CAPACITY,
+            spent: 0,
+            spent_by_url: HashMap::new(),
+        }
+    }
+    fn try_spend(&mut self, url: &str) -> bool {
+        let spent_by_url = self.spent_by_url.entry(url.to_owned()).or_default();
+        if self.spent < self.capacity && *spent_by_url < FETCH_RETRY_URL_SHARE {
+            *spent_by_url += 1;
+            self.spent += 1;
+            true
+        } else {
+            false
+        }
+    }
+}
+
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DataSaver {
+    pub enabled: bool,
+    pub auto_activated: bool,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Diagnostics {
+    pub fetch_retry_budget: FetchRetryBudget,
+    pub clock_skew: Option<i64>,
+    pub storage_writes: StorageWriteStats,
+}
+
+#[derive(Default)]
+pub struct FetchOptions {
+    pub timeout: Option<u32>,
+    pub abort_signal: Option<web_sys::AbortSignal>,
+    // Speculative requests leave no trace in the errors feed, the addon stats or the retry budget
+    pub prefetch: bool,
+}
+
+enum FetchError {
+    Network(NetworkErrorKind, String),
+    Status(u16),
+    Aborted,
+}
+
+impl FetchError {
+    fn from_js_error(error: JsValue) -> Self {
+        match error.dyn_ref::<js_sys::Error>() {
+            Some(error) if error.name() == "AbortError" => FetchError::Aborted,
+            _ => FetchError::Network(NetworkErrorKind::Unknown, js_error_message(error)),
+        }
+    }
+    fn hint(&self) -> Option<&'static str> {
+        match self {
+            FetchError::Network(kind, _) => kind.hint(),
+            _ => None,
+        }
+    }
+    fn kind(&self) -> String {
+        match self {
+            FetchError::Network(kind, _) => kind.description().to_owned(),
+            FetchError::Status(status) => format!("status {}", status),
+            FetchError::Aborted => "aborted".to_owned(),
+        }
+    }
+    fn is_retryable(&self) -> bool {
+        match self {
+            FetchError::Network(kind, _) => kind.is_retryable(),
+            FetchError::Status(status) => *status >= 500,
+            FetchError::Aborted => false,
+        }
+    }
+    // Client errors say nothing about the endpoint being down, a mirror would answer the same
+    fn is_endpoint_failure(&self) -> bool {
+        match self {
+            FetchError::Network(..) => true,
+            FetchError::Status(status) => *status >= 500,
+            FetchError::Aborted => false,
+        }
+    }
+}
+
+impl From<FetchError> for EnvError {
+    fn from(error: FetchError) -> Self {
+        match error {
+            FetchError::Network(kind, message) => match kind.hint() {
+                Some(hint) => EnvError::Fetch(format!("{}: {}", message, hint)),
+                None => EnvError::Fetch(message),
+            },
+            FetchError::Status(status) => {
+                EnvError::Fetch(format!("Unexpected HTTP status code {}", status))
+            }
+            FetchError::Aborted => EnvError::Fetch("Request aborted".to_owned()),
+        }
+    }
+}
+
 pub enum WebEnv {}
 
 impl Env for WebEnv {
     fn fetch<IN, OUT>(request: Request<IN>) -> EnvFuture<OUT>
+    where
+        IN: Serialize,
+        for<'de> OUT: Deserialize<'de> + 'static,
+    {
+        WebEnv::fetch_with_options(request, FetchOptions::default())
+    }
+    fn get_storage<T>(key: &str) -> EnvFuture<Option<T>>
+    where
+        for<'de> T: Deserialize<'de> + 'static,
+    {
+        future::ready(get_storage_sync(key)).boxed_local()
+    }
+    fn set_storage<T: Serialize>(key: &str, value: Option<&T>) -> EnvFuture<()> {
+        let library_value = match key {
+            LIBRARY_RECENT_STORAGE_KEY | LIBRARY_STORAGE_KEY => {
+                value.and_then(|value| serde_json::to_value(value).ok())
+            }
+            _ => None,
+        };
+        let result = match library_value {
+            // The whole library is only written on sync, that's when the recent window is applied
+            Some(library_value)
+                if key == LIBRARY_RECENT_STORAGE_KEY
+                    && library_recent::within_policy(&library_value, WebEnv::now()) =>
+            {
+                set_storage_sync(key, Some(&library_value))
+            }
+            Some(library_value) => match serde_json::from_value::<LibraryBucket>(library_value) {
+                Ok(bucket) => set_library_storage_sync(bucket),
+                Err(_) => set_storage_sync(key, value),
+            },
+            None => set_storage_sync(key, value),
+        };
+        future::ready(result).boxed_local()
+    }
+    fn exec<F>(future: F)
+    where
+        F: Future<Output = ()> + 'static,
+    {
+        let cancelled =
+            EFFECTS_CANCELLATION.with(|cancellation| cancellation.borrow().cancelled.to_owned());
+        let future =
+            effect_groups::track(future::select(future.boxed_local(), cancelled).map(|_| ()));
+        if runtime_pause::is_paused() {
+            runtime_pause::defer_effect(future.boxed_local());
+        } else {
+            spawn_local(future);
+        };
+    }
+    fn now() -> DateTime<Utc> {
+        let clock_skew = *CLOCK_SKEW.read().expect("clock skew read failed");
+        skewed_now(js_sys::Date::now() as i64, clock_skew)
+    }
+    #[cfg(debug_assertions)]
+    fn log(message: String) {
+        web_sys::console::log_1(&JsValue::from(message));
+    }
+}
+
+impl WebEnv {
+    pub fn fetch_with_options<IN, OUT>(
+        request: Request<IN>,
+        options: FetchOptions,
+    ) -> EnvFuture<OUT>
     where
         IN: Serialize,
         for<'de> OUT: Deserialize<'de> + 'static,
     {
         let (parts, body) = request.into_parts();
         let url = parts.uri.to_string();
+        if parts.method == Method::GET {
+            if let Some(text) = meta_prefetch::take(&url) {
+                return future::ready(serde_json::from_str(&text).map_err(EnvError::from))
+                    .boxed_local();
+            };
+        };
         let method = parts.method.as_str();
+        let body = match serde_json::to_string(&body) {
+            Ok(ref body) if body != "null" && parts.method != Method::GET => {
+                Some(JsValue::from_str(&body))
+            }
+            _ => None,
+        };
         let headers = {
             let mut headers = HashMap::new();
             for (key, value) in parts.headers.iter() {
@@ -27,67 +275,359 @@ impl Env for WebEnv {
                 let value = String::from_utf8_lossy(value.as_bytes()).into_owned();
                 headers.entry(key).or_insert_with(Vec::new).push(value);
             }
-            JsValue::from_serde(&headers).unwrap()
+            if body.is_some() && !parts.headers.contains_key(CONTENT_TYPE) {
+                headers.insert(
+                    CONTENT_TYPE.as_str().to_owned(),
+                    vec!["application/json".to_owned()],
+                );
+            };
+            to_js_value(&headers).unwrap()
         };
-        let body = match serde_json::to_string(&body) {
-            Ok(ref body) if body != "null" && parts.method != Method::GET => {
-                Some(JsValue::from_str(&body))
-            }
-            _ => None,
+        let abort_controller =
+            web_sys::AbortController::new().expect("abort controller creation failed");
+        if let Some(abort_signal) = &options.abort_signal {
+            if abort_signal.aborted() {
+                abort_controller.abort();
+            } else {
+                let abort = Closure::once_into_js({
+                    let abort_controller = abort_controller.to_owned();
+                    move || abort_controller.abort()
+                });
+                abort_signal
+                    .add_event_listener_with_callback("abort", abort.unchecked_ref())
+                    .expect("add abort listener failed");
+            };
         };
+        let timeout_handle = options.timeout.map(|timeout| {
+            let abort = Closure::once_into_js({
+                let abort_controller = abort_controller.to_owned();
+                move || abort_controller.abort()
+            });
+            web_sys::window()
+                .expect("window is not available")
+                .set_timeout_with_callback_and_timeout_and_arguments_0(
+                    abort.unchecked_ref(),
+                    timeout as i32,
+                )
+                .expect("set timeout failed")
+        });
         let mut request_options = web_sys::RequestInit::new();
         request_options
             .method(method)
             .headers(&headers)
-            .body(body.as_ref());
-        let request = web_sys::Request::new_with_str_and_init(&url, &request_options)
+            .body(body.as_ref())
+            .signal(Some(&abort_controller.signal()));
+        let endpoint = addon_mirrors::resolve(&url);
+        let request_url = url.to_owned();
+        let url = endpoint
+            .as_ref()
+            .map(|endpoint| endpoint.url.to_owned())
+            .unwrap_or(url);
+        let started_at = js_sys::Date::now();
+        let is_api_request = request_url.starts_with(API_URL.as_str());
+        // Only addon requests are limited, the API stays responsive while catalogs load
+        let is_limited = !is_api_request;
+        // Only requests that can be repeated without side effects are retried
+        let prefetch = options.prefetch;
+        let retryable = !prefetch
+            && matches!(
+                parts.method,
+                Method::GET | Method::HEAD | Method::OPTIONS | Method::PUT | Method::DELETE
+            );
+        let retry_budget = FETCH_RETRY_BUDGET.with(|budget| budget.borrow().to_owned());
+        let fetch_request = FetchRequest {
+            url,
+            request_options,
+            is_limited,
+            retryable,
+            retry_budget,
+        };
+        fetch_with_retry(fetch_request, 1)
+            .inspect(move |result| {
+                if let Some(endpoint) = endpoint {
+                    match result {
+                        Ok(_) => addon_mirrors::record_result(&endpoint.transport_url, true),
+                        Err(error) if error.is_endpoint_failure() => {
+                            addon_mirrors::record_result(&endpoint.transport_url, false)
+                        }
+                        Err(_) => {}
+                    };
+                };
+            })
+            .inspect(move |result| {
+                if let (true, Ok((resp, _))) = (is_api_request, result) {
+                    sync_clock(&resp.headers());
+                };
+            })
+            .and_then(|(resp, slot)| {
+                JsFuture::from(resp.text().unwrap())
+                    .map(move |result| {
+                        drop(slot);
+                        result
+                    })
+                    .map_ok(|text| text.as_string().unwrap_or_default())
+                    .map_err(FetchError::from_js_error)
+            })
+            .inspect(move |result| {
+                if prefetch {
+                    return;
+                };
+                if let Err(error) = result {
+                    if !request_url.starts_with(API_URL.as_str()) {
+                        errors_feed::record_addon_error(&request_url, error.kind(), error.hint());
+                    };
+                };
+                addon_stats::record(
+                    request_url,
+                    js_sys::Date::now() - started_at,
+                    result
+                        .as_ref()
+                        .map(|text| text.len())
+                        .map_err(FetchError::kind),
+                );
+            })
+            .inspect(move |_| {
+                if let Some(timeout_handle) = timeout_handle {
+                    web_sys::window()
+                        .expect("window is not available")
+                        .clear_timeout_with_handle(timeout_handle);
+                };
+            })
+            .map_err(EnvError::from)
+            .and_then(|text| future::ready(serde_json::from_str(&text).map_err(EnvError::from)))
+            .boxed_local()
+    }
+    pub fn diagnostics() -> Diagnostics {
+        Diagnostics {
+            fetch_retry_budget: FETCH_RETRY_BUDGET
+                .with(|budget| budget.borrow().borrow().to_owned()),
+            clock_skew: *CLOCK_SKEW.read().expect("clock skew read failed"),
+            storage_writes: storage_writes::stats(),
+        }
+    }
+    // Requests made from now on spend the retry budget of this route load
+    pub fn start_route_load(route: &'static str) {
+        FETCH_RETRY_BUDGET.with(|budget| {
+            *budget.borrow_mut() = Rc::new(RefCell::new(FetchRetryBudget::new(Some(route))))
+        });
+    }
+    pub fn cancel_effects() {
+        EFFECTS_CANCELLATION
+            .with(|cancellation| *cancellation.borrow_mut() = EffectsCancellation::new());
+    }
+    // Serializers ask on every state read, so the value is kept until the user
+    // or the browser changes it
+    pub fn data_saver() -> DataSaver {
+        if let Some(data_saver) = DATA_SAVER.with(|data_saver| data_saver.borrow().to_owned()) {
+            return data_saver;
+        };
+        watch_reduced_data();
+        let data_saver = match get_storage_sync::<bool>(DATA_SAVER_STORAGE_KEY) {
+            Ok(Some(enabled)) => DataSaver {
+                enabled,
+                auto_activated: false,
+            },
+            _ => {
+                let enabled = browser_requests_reduced_data();
+                DataSaver {
+                    enabled,
+                    auto_activated: enabled,
+                }
+            }
+        };
+        DATA_SAVER.with(|current| *current.borrow_mut() = Some(data_saver.to_owned()));
+        data_saver
+    }
+    pub fn set_data_saver(enabled: Option<bool>) -> Result<(), EnvError> {
+        set_storage_sync(DATA_SAVER_STORAGE_KEY, enabled.as_ref())?;
+        DATA_SAVER.with(|data_saver| data_saver.borrow_mut().take());
+        Ok(())
+    }
+    // Deployments sharing an origin keep their data apart by prefixing every storage key
+    pub fn set_storage_key_prefix(prefix: String) {
+        *STORAGE_KEY_PREFIX
+            .write()
+            .expect("storage key prefix write failed") = prefix;
+        DATA_SAVER.with(|data_saver| data_saver.borrow_mut().take());
+    }
+    pub fn storage_sizes() -> Result<HashMap<String, usize>, EnvError> {
+        let storage = local_storage()?;
+        let length = storage.length().map_err(|_| EnvError::StorageUnavailable)?;
+        let prefix = STORAGE_KEY_PREFIX
+            .read()
+            .expect("storage key prefix read failed")
+            .to_owned();
+        let mut sizes = HashMap::new();
+        for index in 0..length {
+            if let Ok(Some(key)) = storage.key(index) {
+                if !key.starts_with(&prefix) {
+                    continue;
+                };
+                let size = storage
+                    .get_item(&key)
+                    .ok()
+                    .flatten()
+                    .map(|value| value.len())
+                    .unwrap_or_default();
+                sizes.insert(key[prefix.len()..].to_owned(), size);
+            };
+        }
+        Ok(sizes)
+    }
+    pub fn timezone_offset() -> FixedOffset {
+        let minutes = js_sys::Date::new_0().get_timezone_offset() as i32;
+        FixedOffset::west(minutes * 60)
+    }
+}
+
+struct FetchRequest {
+    url: String,
+    request_options: web_sys::RequestInit,
+    is_limited: bool,
+    retryable: bool,
+    retry_budget: Rc<RefCell<FetchRetryBudget>>,
+}
+
+// Each attempt takes its own request slot, and gives it back before waiting to retry
+fn fetch_with_retry(
+    fetch_request: FetchRequest,
+    attempt: u32,
+) -> LocalBoxFuture<'static, Result<(web_sys::Response, Option<Slot>), FetchError>> {
+    if network_error::is_mixed_content(&fetch_request.url) {
+        return future::err(FetchError::Network(
+            NetworkErrorKind::MixedContent,
+            format!("Insecure request to {} blocked", fetch_request.url),
+        ))
+        .boxed_local();
+    };
+    let slot = if fetch_request.is_limited {
+        request_limiter::acquire().map(Some).boxed_local()
+    } else {
+        future::ready(None).boxed_local()
+    };
+    let request =
+        web_sys::Request::new_with_str_and_init(&fetch_request.url, &fetch_request.request_options)
             .expect("request builder failed");
+    slot.then(move |slot| {
         let promise = web_sys::window()
             .expect("window is not available")
             .fetch_with_request(&request);
         JsFuture::from(promise)
-            .map_err(|error| EnvError::Fetch(js_error_message(error)))
-            .and_then(|resp| {
+            .map_err(FetchError::from_js_error)
+            .and_then(move |resp| {
                 let resp = resp.dyn_into::<web_sys::Response>().unwrap();
                 if resp.status() != 200 {
-                    Either::Right(future::err(EnvError::Fetch(format!(
-                        "Unexpected HTTP status code {}",
-                        resp.status(),
-                    ))))
+                    future::err(FetchError::Status(resp.status()))
                 } else {
-                    Either::Left(
-                        JsFuture::from(resp.json().unwrap())
-                            .map_err(|error| EnvError::Fetch(js_error_message(error))),
-                    )
+                    future::ok((resp, slot))
                 }
             })
-            .and_then(|resp| future::ready(resp.into_serde().map_err(EnvError::from)))
-            .boxed_local()
-    }
-    fn get_storage<T>(key: &str) -> EnvFuture<Option<T>>
-    where
-        for<'de> T: Deserialize<'de> + 'static,
-    {
-        future::ready(get_storage_sync(key)).boxed_local()
-    }
-    fn set_storage<T: Serialize>(key: &str, value: Option<&T>) -> EnvFuture<()> {
-        future::ready(set_storage_sync(key, value)).boxed_local()
-    }
-    fn exec<F>(future: F)
-    where
-        F: Future<Output = ()> + 'static,
+    })
+    .then(move |result| match result {
+        Err(error)
+            if fetch_request.retryable
+                && error.is_retryable()
+                && attempt < FETCH_MAX_ATTEMPTS
+                && fetch_request
+                    .retry_budget
+                    .borrow_mut()
+                    .try_spend(&fetch_request.url) =>
+        {
+            let backoff = FETCH_RETRY_BACKOFF << (attempt - 1);
+            retry_delay(backoff)
+                .then(move |_| fetch_with_retry(fetch_request, attempt + 1))
+                .boxed_local()
+        }
+        Err(FetchError::Network(NetworkErrorKind::Unknown, message)) => {
+            network_error::classify(&fetch_request.url)
+                .map(|kind| Err(FetchError::Network(kind, message)))
+                .boxed_local()
+        }
+        result => future::ready(result).boxed_local(),
+    })
+    .boxed_local()
+}
+
+fn retry_delay(delay: i32) -> JsFuture {
+    JsFuture::from(js_sys::Promise::new(&mut |resolve, _| {
+        web_sys::window()
+            .expect("window is not available")
+            .set_timeout_with_callback_and_timeout_and_arguments_0(&resolve, delay)
+            .expect("set timeout failed");
+    }))
+}
+
+// The API is the closest thing to a trusted clock we talk to. The clock is left alone when
+// the Date header is not exposed to this origin.
+fn sync_clock(headers: &web_sys::Headers) {
+    let clock_skew = headers
+        .get("date")
+        .ok()
+        .flatten()
+        .and_then(|date| clock_skew(&date, js_sys::Date::now() as i64));
+    if let Some(clock_skew) = clock_skew {
+        *CLOCK_SKEW.write().expect("clock skew write failed") = Some(clock_skew);
+    };
+}
+
+fn skewed_now(now: i64, clock_skew: Option<i64>) -> DateTime<Utc> {
+    let millis = now
+        + clock_skew
+            .filter(|clock_skew| clock_skew.abs() >= CLOCK_SKEW_THRESHOLD)
+            .unwrap_or_default();
+    let (secs, millis) = (millis / 1000, millis % 1000);
+    Utc.timestamp(secs, millis as u32 * 1_000_000)
+}
+
+// The Date header only has whole seconds, which is well below the skew threshold
+fn clock_skew(date: &str, now: i64) -> Option<i64> {
+    DateTime::parse_from_rfc2822(date)
+        .ok()
+        .map(|server_time| server_time.timestamp_millis() - now)
+}
+
+// Either browser signal can change while the page is open, the cached value is dropped when it does
+fn watch_reduced_data() {
+    if DATA_SAVER_WATCHED.with(|watched| watched.replace(true)) {
+        return;
+    };
+    let window = web_sys::window().expect("window is not available");
+    let on_change = Closure::wrap(Box::new(|| {
+        DATA_SAVER.with(|data_saver| data_saver.borrow_mut().take());
+    }) as Box<dyn FnMut()>);
+    if let Ok(Some(media_query_list)) = window.match_media("(prefers-reduced-data: reduce)") {
+        let _ = media_query_list
+            .add_event_listener_with_callback("change", on_change.as_ref().unchecked_ref());
+    };
+    if let Ok(connection) =
+        js_sys::Reflect::get(&window.navigator(), &JsValue::from_str("connection"))
     {
-        spawn_local(future)
-    }
-    fn now() -> DateTime<Utc> {
-        let millis = js_sys::Date::now() as i64;
-        let (secs, millis) = (millis / 1000, millis % 1000);
-        Utc.timestamp(secs, millis as u32 * 1_000_000)
-    }
-    #[cfg(debug_assertions)]
-    fn log(message: String) {
-        web_sys::console::log_1(&JsValue::from(message));
-    }
+        if connection.is_object() {
+            let _ = connection
+                .unchecked_ref::<web_sys::EventTarget>()
+                .add_event_listener_with_callback("change", on_change.as_ref().unchecked_ref());
+        };
+    };
+    on_change.forget();
+}
+
+fn browser_requests_reduced_data() -> bool {
+    let window = web_sys::window().expect("window is not available");
+    let save_data = js_sys::Reflect::get(&window.navigator(), &JsValue::from_str("connection"))
+        .ok()
+        .filter(|connection| connection.is_object())
+        .and_then(|connection| {
+            js_sys::Reflect::get(&connection, &JsValue::from_str("saveData")).ok()
+        })
+        .and_then(|save_data| save_data.as_bool())
+        .unwrap_or_default();
+    let prefers_reduced_data = window
+        .match_media("(prefers-reduced-data: reduce)")
+        .ok()
+        .flatten()
+        .map(|media_query_list| media_query_list.matches())
+        .unwrap_or_default();
+    save_data || prefers_reduced_data
 }
 
 fn local_storage() -> Result<web_sys::Storage, EnvError> {
@@ -102,35 +642,111 @@ fn get_storage_sync<T>(key: &str) -> Result<Option<T>, EnvError>
 where
     for<'de> T: Deserialize<'de> + 'static,
 {
-    let storage = local_storage()?;
-    let value = storage
-        .get_item(key)
-        .map_err(|_| EnvError::StorageUnavailable)?;
+    let key = storage_key(key);
+    let value = match storage_writes::pending(&key) {
+        Some(value) => value,
+        None => local_storage()?
+            .get_item(&key)
+            .map_err(|_| EnvError::StorageUnavailable)?,
+    };
     Ok(match value {
         Some(value) => Some(serde_json::from_str(&value)?),
         None => None,
     })
 }
 
+// Writes are queued and flushed together, failures after that are reported through events
 fn set_storage_sync<T: Serialize>(key: &str, value: Option<&T>) -> Result<(), EnvError> {
-    let storage = local_storage()?;
-    match value {
-        Some(value) => {
-            let serialized_value = serde_json::to_string(value)?;
-            storage
-                .set_item(key, &serialized_value)
-                .map_err(|_| EnvError::StorageUnavailable)?;
-        }
-        None => storage
-            .remove_item(key)
-            .map_err(|_| EnvError::StorageUnavailable)?,
+    local_storage()?;
+    let value = match value {
+        Some(value) => Some(serde_json::to_string(value)?),
+        None => None,
     };
+    storage_writes::schedule(storage_key(key), value);
     Ok(())
 }
 
-fn js_error_message(error: JsValue) -> String {
+fn storage_key(key: &str) -> String {
+    format!(
+        "{}{}",
+        STORAGE_KEY_PREFIX
+            .read()
+            .expect("storage key prefix read failed"),
+        key
+    )
+}
+
+fn set_library_storage_sync(bucket: LibraryBucket) -> Result<(), EnvError> {
+    let split = library_recent::split(
+        bucket,
+        get_storage_sync(LIBRARY_RECENT_STORAGE_KEY).ok().flatten(),
+        get_storage_sync(LIBRARY_STORAGE_KEY).ok().flatten(),
+        WebEnv::now(),
+    );
+    set_storage_sync(LIBRARY_RECENT_STORAGE_KEY, Some(&split.recent))?;
+    set_storage_sync(LIBRARY_STORAGE_KEY, Some(&split.other))?;
+    if !split.rotated_ids.is_empty() {
+        event::emit(&WebEvent::LibraryItemsRotated(split.rotated_ids));
+    };
+    Ok(())
+}
+
+pub fn js_error_message(error: JsValue) -> String {
     error
         .dyn_into::<js_sys::Error>()
         .map(|error| String::from(error.message()))
         .unwrap_or_else(|_| "Unknown Error".to_owned())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn clock_skew_from_the_date_header() {
+        let now = Utc.ymd(2020, 5, 10).and_hms(12, 0, 0).timestamp_millis();
+        assert_eq!(
+            clock_skew("Sun, 10 May 2020 12:02:30 GMT", now),
+            Some(150_000)
+        );
+        assert_eq!(
+            clock_skew("Sun, 10 May 2020 11:59:00 GMT", now),
+            Some(-60_000)
+        );
+        assert_eq!(clock_skew("yesterday", now), None);
+    }
+
+    #[test]
+    fn clock_skew_is_applied_above_the_threshold() {
+        let now = Utc.ymd(2020, 5, 10).and_hms(12, 0, 0);
+        let skew = clock_skew("Sun, 10 May 2020 12:02:30 GMT", now.timestamp_millis());
+        assert_eq!(
+            skewed_now(now.timestamp_millis(), skew),
+            Utc.ymd(2020, 5, 10).and_hms(12, 2, 30)
+        );
+        let skew = clock_skew("Sun, 10 May 2020 12:00:10 GMT", now.timestamp_millis());
+        assert_eq!(skewed_now(now.timestamp_millis(), skew), now);
+    }
+
+    #[test]
+    fn retry_budget_is_shared_by_the_urls_of_a_route_load() {
+        let mut budget = FetchRetryBudget::new(Some("board"));
+        for index in 0..FETCH_RETRY_BUDGET_CAPACITY / FETCH_RETRY_URL_SHARE {
+            let url = format!("https://addon{}.example.com/catalog/movie/top.json", index);
+            for _ in 0..FETCH_RETRY_URL_SHARE {
+                assert!(budget.try_spend(&url));
+            }
+        }
+        assert!(!budget.try_spend("https://other.example.com/catalog/movie/top.json"));
+        assert_eq!(budget.spent, FETCH_RETRY_BUDGET_CAPACITY);
+    }
+
+    #[test]
+    fn failing_url_spends_only_its_share() {
+        let mut budget = FetchRetryBudget::new(Some("board"));
+        let failing_url = "https://failing.example.com/catalog/movie/top.json";
+        while budget.try_spend(failing_url) {}
+        assert_eq!(budget.spent, FETCH_RETRY_URL_SHARE);
+        assert!(budget.try_spend("https://other.example.com/catalog/movie/top.json"));
+    }
+}
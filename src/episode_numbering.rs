@@ -0,0 +1,68 @@
+use serde::{Deserialize, Serialize};
+use std::cell::Cell;
+use std::collections::HashMap;
+use stremio_core::types::resource::{SeriesInfo, Video};
+
+pub const EPISODE_NUMBERING_STORAGE_KEY: &str = "episode_numbering";
+const SPECIALS_SEASON: u32 = 0;
+
+thread_local! {
+    static EPISODE_NUMBERING: Cell<EpisodeNumbering> = Cell::new(EpisodeNumbering::default());
+}
+
+#[derive(Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum EpisodeNumbering {
+    Seasonal,
+    Absolute,
+}
+
+impl Default for EpisodeNumbering {
+    fn default() -> Self {
+        EpisodeNumbering::Seasonal
+    }
+}
+
+pub fn init(numbering: EpisodeNumbering) {
+    EPISODE_NUMBERING.with(|current| current.set(numbering));
+}
+
+pub fn numbering() -> EpisodeNumbering {
+    EPISODE_NUMBERING.with(|numbering| numbering.get())
+}
+
+// Anime addons often split a show into seasons that don't match the numbering fans use,
+// so every regular episode is also numbered by its position across all seasons.
+// Specials are left out and episodes sharing a number share the absolute one as well.
+pub fn absolute_episodes(videos: &[Video]) -> HashMap<&str, u32> {
+    let mut episodes = videos
+        .iter()
+        .filter_map(|video| {
+            video
+                .series_info
+                .as_ref()
+                .filter(|series_info| series_info.season != SPECIALS_SEASON)
+                .map(|series_info| (series_info.season, series_info.episode, video.id.as_str()))
+        })
+        .collect::<Vec<_>>();
+    episodes.sort_by_key(|(season, episode, _)| (*season, *episode));
+    let mut absolute_episode = 0;
+    let mut previous = None;
+    episodes
+        .into_iter()
+        .map(|(season, episode, id)| {
+            if previous != Some((season, episode)) {
+                absolute_episode += 1;
+                previous = Some((season, episode));
+            };
+            (id, absolute_episode)
+        })
+        .collect()
+}
+
+pub fn episode_label(series_info: &SeriesInfo, absolute_episode: Option<u32>) -> String {
+    match (numbering(), absolute_episode) {
+        (EpisodeNumbering::Absolute, Some(absolute_episode)) => format!("#{}", absolute_episode),
+        _ => format!("{}x{}", series_info.season, series_info.episode),
+    }
+}
@@ -0,0 +1,115 @@
+use crate::env::WebEnv;
+use serde::Serialize;
+use std::cell::RefCell;
+use stremio_core::runtime::msg::{CtxError, Event};
+use stremio_core::runtime::{Env, EnvError};
+use url::Url;
+
+const ERROR_EXPIRY: i64 = 5 * 60 * 1000;
+const MAX_ERRORS: usize = 50;
+
+thread_local! {
+    static ERRORS_FEED: RefCell<Vec<ErrorEntry>> = Default::default();
+}
+
+#[derive(Clone, Copy, PartialEq, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ErrorSource {
+    Addon,
+    Sync,
+    Storage,
+    Other,
+}
+
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ErrorEntry {
+    pub id: String,
+    pub source: ErrorSource,
+    pub message: String,
+    pub hint: Option<&'static str>,
+    pub count: u32,
+    pub first_seen: i64,
+    pub last_seen: i64,
+}
+
+pub fn record(source: ErrorSource, key: &str, message: String, hint: Option<&'static str>) {
+    let now = WebEnv::now().timestamp_millis();
+    let id = format!("{:?}:{}", source, key);
+    ERRORS_FEED.with(|errors_feed| {
+        let mut errors_feed = errors_feed.borrow_mut();
+        errors_feed.retain(|entry| now - entry.last_seen <= ERROR_EXPIRY);
+        match errors_feed.iter_mut().find(|entry| entry.id == id) {
+            Some(entry) => {
+                entry.message = message;
+                entry.hint = hint;
+                entry.count += 1;
+                entry.last_seen = now;
+            }
+            None => {
+                if errors_feed.len() >= MAX_ERRORS {
+                    errors_feed.remove(0);
+                };
+                errors_feed.push(ErrorEntry {
+                    id,
+                    source,
+                    message,
+                    hint,
+                    count: 1,
+                    first_seen: now,
+                    last_seen: now,
+                });
+            }
+        };
+    });
+}
+
+pub fn record_event(event: &Event) {
+    if let Event::Error { error, .. } = event {
+        let (source, message) = match error {
+            CtxError::API(message) => (ErrorSource::Sync, message.to_owned()),
+            CtxError::Env(error) => match error {
+                EnvError::StorageUnavailable
+                | EnvError::StorageReadError(_)
+                | EnvError::StorageWriteError(_) => (ErrorSource::Storage, error.message()),
+                _ => (ErrorSource::Other, error.message()),
+            },
+            CtxError::Other(message) => (ErrorSource::Other, message.to_owned()),
+        };
+        let key = message.to_owned();
+        record(source, &key, message, None);
+    };
+}
+
+// Addon failures are keyed by origin so that every failing request of an addon folds into one entry
+pub fn record_addon_error(url: &str, message: String, hint: Option<&'static str>) {
+    let origin = Url::parse(url)
+        .map(|url| url.origin().ascii_serialization())
+        .unwrap_or_else(|_| url.to_owned());
+    record(ErrorSource::Addon, &origin, message, hint);
+}
+
+pub fn dismiss(id: &str) -> bool {
+    ERRORS_FEED.with(|errors_feed| {
+        let mut errors_feed = errors_feed.borrow_mut();
+        let len = errors_feed.len();
+        errors_feed.retain(|entry| entry.id != id);
+        errors_feed.len() != len
+    })
+}
+
+pub fn errors_feed() -> Vec<ErrorEntry> {
+    let now = WebEnv::now().timestamp_millis();
+    ERRORS_FEED.with(|errors_feed| {
+        errors_feed
+            .borrow()
+            .iter()
+            .filter(|entry| now - entry.last_seen <= ERROR_EXPIRY)
+            .cloned()
+            .collect()
+    })
+}
+
+pub fn clear() {
+    ERRORS_FEED.with(|errors_feed| errors_feed.borrow_mut().clear());
+}
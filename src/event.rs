@@ -0,0 +1,137 @@
+use crate::debug_log;
+use crate::env::js_error_message;
+use crate::js_value::to_js_value;
+use crate::library_refresh::LibraryItemRefresh;
+use crate::runtime_pause;
+use crate::session_refresh::APIError;
+use crate::settings_effect::RestartRequired;
+use crate::shuffle_play::ShufflePick;
+use crate::storage_writes::StorageWriteFailure;
+use crate::watch_session::WatchSessionSummary;
+use serde::Serialize;
+use std::cell::{Cell, RefCell};
+use stremio_core::types::resource::Video;
+use wasm_bindgen::JsValue;
+
+#[derive(Serialize)]
+#[serde(tag = "name", content = "args")]
+pub enum WebEvent {
+    WatchSessionEnded(WatchSessionSummary),
+    LibraryItemRefreshed(LibraryItemRefresh),
+    RestartRequired(RestartRequired),
+    VideoDetails(Video),
+    LibraryItemsRotated(Vec<String>),
+    SessionRefreshed,
+    SessionExpired(APIError),
+    EmitFailed(EmitFailure),
+    StorageWriteFailed(StorageWriteFailure),
+    ShufflePlayReady(ShufflePick),
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EmitFailure {
+    pub event: Option<String>,
+    pub message: String,
+}
+
+thread_local! {
+    static EMIT: RefCell<Option<js_sys::Function>> = Default::default();
+    static NEXT_LISTENER_ID: Cell<u32> = Cell::new(0);
+    static LISTENERS: RefCell<Vec<EventListener>> = Default::default();
+}
+
+struct EventListener {
+    id: u32,
+    event_name: String,
+    callback: js_sys::Function,
+}
+
+pub fn set_emit(emit: js_sys::Function) {
+    EMIT.with(|current| *current.borrow_mut() = Some(emit));
+}
+
+pub fn add_event_listener(event_name: String, callback: js_sys::Function) -> u32 {
+    let id = NEXT_LISTENER_ID.with(|next_id| {
+        let id = next_id.get();
+        next_id.set(id.wrapping_add(1));
+        id
+    });
+    LISTENERS.with(|listeners| {
+        listeners.borrow_mut().push(EventListener {
+            id,
+            event_name,
+            callback,
+        })
+    });
+    id
+}
+
+pub fn remove_event_listener(id: u32) -> bool {
+    LISTENERS.with(|listeners| {
+        let mut listeners = listeners.borrow_mut();
+        let len = listeners.len();
+        listeners.retain(|listener| listener.id != id);
+        listeners.len() != len
+    })
+}
+
+pub fn emit<T: Serialize>(event: &T) {
+    let event = serde_json::to_value(event).expect("event serialization failed");
+    if runtime_pause::buffer_event(&event) {
+        return;
+    };
+    emit_value(event);
+}
+
+pub fn replay(events: Vec<serde_json::Value>) {
+    for event in events {
+        emit_value(event);
+    }
+}
+
+fn emit_value(event: serde_json::Value) {
+    let js_event = to_js_value(&event).unwrap();
+    debug_log::record_event(&event);
+    let emit = EMIT.with(|emit| emit.borrow().to_owned());
+    // A throwing callback must not take the runtime down, the failure goes to listeners instead
+    if let Some(emit) = emit {
+        if let Err(error) = emit.call1(&JsValue::NULL, &js_event) {
+            web_sys::console::error_2(&JsValue::from("emit event failed"), &error);
+            let failure = WebEvent::EmitFailed(EmitFailure {
+                event: event
+                    .get("name")
+                    .and_then(|name| name.as_str())
+                    .map(|name| name.to_owned()),
+                message: js_error_message(error),
+            });
+            let failure = serde_json::to_value(&failure).expect("event serialization failed");
+            notify_listeners(&failure, &to_js_value(&failure).unwrap());
+        };
+    };
+    notify_listeners(&event, &js_event);
+}
+
+fn notify_listeners(event: &serde_json::Value, js_event: &JsValue) {
+    // Core events are wrapped in a CoreEvent, listeners can subscribe to either name
+    let event_names = [
+        event.get("name").and_then(|name| name.as_str()),
+        event
+            .get("args")
+            .and_then(|args| args.get("event"))
+            .and_then(|name| name.as_str()),
+    ];
+    let callbacks = LISTENERS.with(|listeners| {
+        listeners
+            .borrow()
+            .iter()
+            .filter(|listener| event_names.contains(&Some(listener.event_name.as_str())))
+            .map(|listener| listener.callback.to_owned())
+            .collect::<Vec<_>>()
+    });
+    for callback in callbacks {
+        if let Err(error) = callback.call1(&JsValue::NULL, js_event) {
+            web_sys::console::error_2(&JsValue::from("event listener failed"), &error);
+        };
+    }
+}
@@ -0,0 +1,131 @@
+use serde::Deserialize;
+use std::cell::RefCell;
+use std::collections::HashMap;
+use stremio_core::types::resource::Subtitles;
+use url::Url;
+
+pub const EXTERNAL_SUBTITLES_STORAGE_KEY: &str = "external_subtitles";
+const MAX_CONTENT_LENGTH: usize = 512 * 1024;
+const MAX_PERSISTED_DATA_LENGTH: usize = 2 * 1024 * 1024;
+const DATA_URL_SCHEME: &str = "data";
+const BLOB_URL_SCHEME: &str = "blob";
+const WEBVTT_SIGNATURE: &str = "WEBVTT";
+
+thread_local! {
+    static EXTERNAL_SUBTITLES: RefCell<HashMap<String, Vec<Subtitles>>> = Default::default();
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExternalSubtitlesSource {
+    pub lang: String,
+    pub url: Option<Url>,
+    pub content: Option<String>,
+}
+
+impl ExternalSubtitlesSource {
+    // Object urls die with the page, so text content is kept as a data url to survive the session
+    pub fn into_subtitles(self) -> Result<Subtitles, String> {
+        let url = match (self.url, self.content) {
+            (_, Some(content)) if content.len() > MAX_CONTENT_LENGTH => {
+                return Err(format!(
+                    "subtitles content exceeds {} bytes",
+                    MAX_CONTENT_LENGTH
+                ))
+            }
+            (_, Some(content)) => {
+                let media_type = if content.trim_start().starts_with(WEBVTT_SIGNATURE) {
+                    "text/vtt"
+                } else {
+                    "application/x-subrip"
+                };
+                Url::parse(&format!(
+                    "data:{};base64,{}",
+                    media_type,
+                    base64::encode(content)
+                ))
+                .map_err(|error| error.to_string())?
+            }
+            (Some(url), None) => url,
+            (None, None) => return Err("subtitles url or content is required".to_owned()),
+        };
+        Ok(Subtitles {
+            lang: self.lang,
+            url,
+        })
+    }
+}
+
+pub fn init(external_subtitles: HashMap<String, Vec<Subtitles>>) {
+    EXTERNAL_SUBTITLES.with(|current| *current.borrow_mut() = persisted(&external_subtitles));
+}
+
+pub fn external_subtitles(video_id: &str) -> Vec<Subtitles> {
+    EXTERNAL_SUBTITLES.with(|external_subtitles| {
+        external_subtitles
+            .borrow()
+            .get(video_id)
+            .cloned()
+            .unwrap_or_default()
+    })
+}
+
+// Object urls are only kept for the session, everything else is persisted
+// as long as the data urls fit in the storage budget
+pub fn add_external_subtitles(
+    video_id: String,
+    subtitles: Subtitles,
+) -> Result<HashMap<String, Vec<Subtitles>>, String> {
+    EXTERNAL_SUBTITLES.with(|external_subtitles| {
+        let mut external_subtitles = external_subtitles.borrow_mut();
+        if subtitles.url.scheme() == DATA_URL_SCHEME {
+            let persisted_length = external_subtitles
+                .iter()
+                .map(|(current_video_id, video_subtitles)| {
+                    video_subtitles
+                        .iter()
+                        .filter(|current| {
+                            *current_video_id != video_id || current.url != subtitles.url
+                        })
+                        .map(data_length)
+                        .sum::<usize>()
+                })
+                .sum::<usize>();
+            if persisted_length + data_length(&subtitles) > MAX_PERSISTED_DATA_LENGTH {
+                return Err(format!(
+                    "external subtitles content exceeds {} bytes",
+                    MAX_PERSISTED_DATA_LENGTH
+                ));
+            };
+        };
+        let video_subtitles = external_subtitles.entry(video_id).or_default();
+        video_subtitles.retain(|current| current.url != subtitles.url);
+        video_subtitles.push(subtitles);
+        Ok(persisted(&external_subtitles))
+    })
+}
+
+fn data_length(subtitles: &Subtitles) -> usize {
+    if subtitles.url.scheme() == DATA_URL_SCHEME {
+        subtitles.url.as_str().len()
+    } else {
+        0
+    }
+}
+
+fn persisted(
+    external_subtitles: &HashMap<String, Vec<Subtitles>>,
+) -> HashMap<String, Vec<Subtitles>> {
+    external_subtitles
+        .iter()
+        .map(|(video_id, video_subtitles)| {
+            let video_subtitles = video_subtitles
+                .iter()
+                .filter(|subtitles| subtitles.url.scheme() != BLOB_URL_SCHEME)
+                .cloned()
+                .collect::<Vec<_>>();
+            (video_id.to_owned(), video_subtitles)
+        })
+        .filter(|(_, video_subtitles)| !video_subtitles.is_empty())
+        .collect()
+}
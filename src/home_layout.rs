@@ -0,0 +1,99 @@
+use serde::{Deserialize, Serialize};
+use std::cell::RefCell;
+use std::collections::HashMap;
+
+pub const HOME_LAYOUTS_STORAGE_KEY: &str = "home_layouts";
+const ANONYMOUS_PROFILE_KEY: &str = "";
+const DEFAULT_CALENDAR_DAYS: u32 = 7;
+const MAX_CALENDAR_DAYS: u32 = 31;
+
+thread_local! {
+    static HOME_LAYOUTS: RefCell<HashMap<String, HomeLayout>> = Default::default();
+}
+
+#[derive(Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum HomeSection {
+    ContinueWatching,
+    // A single board row, identified by its board layout row id
+    Catalog {
+        id: String,
+    },
+    // The board rows that no catalog section picked, in board layout order
+    Board,
+    Calendar {
+        #[serde(default = "default_calendar_days")]
+        days: u32,
+    },
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HomeLayout {
+    pub sections: Vec<HomeSection>,
+}
+
+// Without a layout the home is what the board always was, continue watching followed by every row
+impl Default for HomeLayout {
+    fn default() -> Self {
+        HomeLayout {
+            sections: vec![HomeSection::ContinueWatching, HomeSection::Board],
+        }
+    }
+}
+
+impl HomeLayout {
+    pub fn is_valid(&self) -> bool {
+        self.sections.iter().all(|section| match section {
+            HomeSection::Calendar { days } => *days > 0 && *days <= MAX_CALENDAR_DAYS,
+            _ => true,
+        })
+    }
+    pub fn catalog_ids(&self) -> Vec<&String> {
+        self.sections
+            .iter()
+            .filter_map(|section| match section {
+                HomeSection::Catalog { id } => Some(id),
+                _ => None,
+            })
+            .collect()
+    }
+}
+
+fn default_calendar_days() -> u32 {
+    DEFAULT_CALENDAR_DAYS
+}
+
+pub fn init(home_layouts: HashMap<String, HomeLayout>) {
+    HOME_LAYOUTS.with(|current| *current.borrow_mut() = home_layouts);
+}
+
+pub fn home_layout(uid: &Option<String>) -> HomeLayout {
+    HOME_LAYOUTS.with(|home_layouts| {
+        home_layouts
+            .borrow()
+            .get(profile_key(uid))
+            .cloned()
+            .unwrap_or_default()
+    })
+}
+
+pub fn set(uid: &Option<String>, home_layout: HomeLayout) -> HashMap<String, HomeLayout> {
+    HOME_LAYOUTS.with(|home_layouts| {
+        let mut home_layouts = home_layouts.borrow_mut();
+        home_layouts.insert(profile_key(uid).to_owned(), home_layout);
+        home_layouts.to_owned()
+    })
+}
+
+pub fn reset(uid: &Option<String>) -> HashMap<String, HomeLayout> {
+    HOME_LAYOUTS.with(|home_layouts| {
+        let mut home_layouts = home_layouts.borrow_mut();
+        home_layouts.remove(profile_key(uid));
+        home_layouts.to_owned()
+    })
+}
+
+fn profile_key(uid: &Option<String>) -> &str {
+    uid.as_deref().unwrap_or(ANONYMOUS_PROFILE_KEY)
+}
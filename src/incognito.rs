@@ -0,0 +1,33 @@
+use std::cell::Cell;
+use stremio_core::runtime::msg::{Action, ActionLoad, ActionPlayer, Msg};
+
+thread_local! {
+    static INCOGNITO: Cell<bool> = Cell::new(false);
+}
+
+pub fn is_enabled() -> bool {
+    INCOGNITO.with(|incognito| incognito.get())
+}
+
+pub fn set_enabled(enabled: bool) {
+    INCOGNITO.with(|incognito| incognito.set(enabled));
+}
+
+pub fn is_allowed(action: &Action) -> bool {
+    !is_enabled()
+        || !matches!(
+            action,
+            Action::Player(ActionPlayer::UpdateLibraryItemState { .. })
+                | Action::Player(ActionPlayer::PushToLibrary)
+        )
+}
+
+// The player pushes its library item when it is unloaded or replaced,
+// so it is detached before such actions reach core.
+pub fn is_library_write(msg: &Msg) -> bool {
+    is_enabled()
+        && matches!(
+            msg,
+            Msg::Action(Action::Load(ActionLoad::Player(_))) | Msg::Action(Action::Unload)
+        )
+}
@@ -0,0 +1,18 @@
+use crate::permissions::Permissions;
+use serde::Deserialize;
+use std::collections::HashMap;
+use url::Url;
+
+#[derive(Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct InitOptions {
+    #[serde(default)]
+    pub permissions: Permissions,
+    #[serde(default)]
+    pub media_types: HashMap<String, bool>,
+    pub analytics_url: Option<Url>,
+    pub runtime_buffer_size: Option<usize>,
+    pub new_state_debounce: Option<u32>,
+    pub max_concurrent_addon_requests: Option<usize>,
+    pub storage_key_prefix: Option<String>,
+}
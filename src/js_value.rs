@@ -0,0 +1,12 @@
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use wasm_bindgen::JsValue;
+
+// Values go through JSON, serde-wasm-bindgen needs a newer wasm-bindgen and rustc than CI has
+pub fn to_js_value<T: Serialize + ?Sized>(value: &T) -> Result<JsValue, serde_json::Error> {
+    JsValue::from_serde(value)
+}
+
+pub fn from_js_value<T: DeserializeOwned>(value: JsValue) -> Result<T, serde_json::Error> {
+    value.into_serde()
+}
@@ -1,5 +1,60 @@
 #![allow(clippy::module_inception)]
 
+mod account;
+mod action_error;
+mod action_settlement;
+mod addon_mirrors;
+mod addon_priorities;
+mod addon_stats;
+mod analytics;
+mod analytics_route;
+mod artwork;
+mod board_layout;
+mod debug_log;
+mod dev_addon;
+mod effect_groups;
 mod env;
+mod episode_numbering;
+mod errors_feed;
+mod event;
+mod external_subtitles;
+mod home_layout;
+mod incognito;
+mod init_options;
+mod js_value;
+mod library_backup;
+mod library_recent;
+mod library_refresh;
+mod manifest_validation;
+mod media_support;
+mod meta_prefetch;
+mod middleware;
 mod model;
+mod mounted_fields;
+mod network_error;
+mod panic_hook;
+mod pending_actions;
+mod pending_removal;
+mod permissions;
+mod play_queue;
+mod player_analytics;
+mod ratings;
+mod request_limiter;
+mod route_timing;
+mod runtime_pause;
+mod session_refresh;
+mod settings_effect;
+mod share;
+mod shuffle_play;
+mod state_selector;
+mod storage_writes;
+mod stream_expiry;
+mod stream_wait;
 mod stremio_core_web;
+mod subscriptions;
+mod support_bundle;
+mod torrent_files;
+mod ui_state;
+mod version_info;
+mod watch_limits;
+mod watch_session;
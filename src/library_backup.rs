@@ -0,0 +1,357 @@
+use crate::env::js_error_message;
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use std::cell::{Cell, RefCell};
+use stremio_core::types::library::LibraryBucket;
+use stremio_core::types::profile::Profile;
+use url::Url;
+use wasm_bindgen::closure::Closure;
+use wasm_bindgen::{JsCast, JsValue};
+use wasm_bindgen_futures::JsFuture;
+
+pub const LIBRARY_BACKUP_STORAGE_KEY: &str = "library_backup";
+const BACKUP_CHECK_INTERVAL: i32 = 15 * 60 * 1000;
+pub const BACKUP_SNAPSHOT_VERSION: u32 = 1;
+const KEY_DERIVATION_ITERATIONS: u32 = 100_000;
+const SALT_LENGTH: u32 = 16;
+const IV_LENGTH: u32 = 12;
+const ANONYMOUS_BACKUP_NAME: &str = "anonymous";
+
+thread_local! {
+    static LIBRARY_BACKUP: RefCell<LibraryBackup> = Default::default();
+    static SECRETS: RefCell<Option<BackupSecrets>> = Default::default();
+    static IN_PROGRESS: Cell<bool> = Cell::new(false);
+    static WATCH_HANDLE: Cell<Option<i32>> = Cell::new(None);
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum BackupTarget {
+    #[serde(rename_all = "camelCase")]
+    WebDav { url: Url, username: Option<String> },
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BackupConfig {
+    pub target: BackupTarget,
+    pub interval_hours: u32,
+}
+
+// The passphrase and the target password are only kept in memory,
+// after a reload backups wait until the app provides them again
+#[derive(Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BackupSecrets {
+    pub passphrase: String,
+    pub password: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BackupConfigRequest {
+    pub target: BackupTarget,
+    pub interval_hours: u32,
+    #[serde(flatten)]
+    pub secrets: BackupSecrets,
+}
+
+impl BackupConfigRequest {
+    pub fn is_valid(&self) -> bool {
+        !self.secrets.passphrase.is_empty() && self.interval_hours > 0
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BackupStatus {
+    pub time: DateTime<Utc>,
+    pub error: Option<String>,
+    pub size: usize,
+}
+
+#[derive(Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LibraryBackup {
+    pub config: Option<BackupConfig>,
+    pub last_backup: Option<BackupStatus>,
+    pub last_success: Option<DateTime<Utc>>,
+}
+
+// Credentials and the passphrase stay out of the serialized state
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BackupSummary {
+    pub target: Option<&'static str>,
+    pub interval_hours: Option<u32>,
+    pub unlocked: bool,
+    pub last_backup: Option<BackupStatus>,
+    pub last_success: Option<DateTime<Utc>>,
+    pub in_progress: bool,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BackupSnapshot<'a> {
+    pub version: u32,
+    pub created_at: DateTime<Utc>,
+    pub profile: &'a Profile,
+    pub library: &'a LibraryBucket,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BackupEnvelope {
+    pub version: u32,
+    pub cipher: &'static str,
+    pub kdf: &'static str,
+    pub iterations: u32,
+    pub salt: String,
+    pub iv: String,
+    pub data: String,
+}
+
+pub fn init(library_backup: LibraryBackup) {
+    LIBRARY_BACKUP.with(|current| *current.borrow_mut() = library_backup);
+}
+
+pub fn library_backup() -> LibraryBackup {
+    LIBRARY_BACKUP.with(|library_backup| library_backup.borrow().to_owned())
+}
+
+pub fn set_config(request: Option<BackupConfigRequest>) -> LibraryBackup {
+    let (config, secrets) = match request {
+        Some(request) => (
+            Some(BackupConfig {
+                target: request.target,
+                interval_hours: request.interval_hours,
+            }),
+            Some(request.secrets),
+        ),
+        None => (None, None),
+    };
+    set_secrets(secrets);
+    update(|library_backup| library_backup.config = config)
+}
+
+pub fn set_secrets(secrets: Option<BackupSecrets>) {
+    SECRETS.with(|current| *current.borrow_mut() = secrets);
+}
+
+pub fn secrets() -> Option<BackupSecrets> {
+    SECRETS.with(|secrets| secrets.borrow().to_owned())
+}
+
+pub fn record_status(status: BackupStatus) -> LibraryBackup {
+    update(|library_backup| {
+        if status.error.is_none() {
+            library_backup.last_success = Some(status.time);
+        };
+        library_backup.last_backup = Some(status);
+    })
+}
+
+pub fn summary() -> BackupSummary {
+    let library_backup = library_backup();
+    BackupSummary {
+        target: library_backup
+            .config
+            .as_ref()
+            .map(|config| match config.target {
+                BackupTarget::WebDav { .. } => "webDav",
+            }),
+        interval_hours: library_backup
+            .config
+            .as_ref()
+            .map(|config| config.interval_hours),
+        unlocked: secrets().is_some(),
+        last_backup: library_backup.last_backup,
+        last_success: library_backup.last_success,
+        in_progress: is_in_progress(),
+    }
+}
+
+pub fn is_in_progress() -> bool {
+    IN_PROGRESS.with(|in_progress| in_progress.get())
+}
+
+pub fn set_in_progress(in_progress: bool) {
+    IN_PROGRESS.with(|current| current.set(in_progress));
+}
+
+// Failed attempts count as well, otherwise an unreachable target would be retried on every check
+pub fn is_due(now: DateTime<Utc>) -> bool {
+    if secrets().is_none() {
+        return false;
+    };
+    let library_backup = library_backup();
+    match (&library_backup.config, &library_backup.last_backup) {
+        (Some(config), Some(last_backup)) => {
+            now - last_backup.time >= Duration::hours(config.interval_hours as i64)
+        }
+        (Some(_), None) => true,
+        (None, _) => false,
+    }
+}
+
+// The callback runs periodically, it is up to it to check whether a backup is due
+pub fn watch<F: Fn() + 'static>(on_check: F) {
+    if WATCH_HANDLE.with(|handle| handle.get()).is_some() {
+        return;
+    };
+    let on_check = Closure::wrap(Box::new(on_check) as Box<dyn FnMut()>);
+    let handle = web_sys::window()
+        .expect("window is not available")
+        .set_interval_with_callback_and_timeout_and_arguments_0(
+            on_check.as_ref().unchecked_ref(),
+            BACKUP_CHECK_INTERVAL,
+        )
+        .expect("set interval failed");
+    on_check.forget();
+    WATCH_HANDLE.with(|current| current.set(Some(handle)));
+}
+
+pub fn unwatch() {
+    if let Some(handle) = WATCH_HANDLE.with(|handle| handle.take()) {
+        web_sys::window()
+            .expect("window is not available")
+            .clear_interval_with_handle(handle);
+    };
+}
+
+// AES-GCM with a key derived from the passphrase, nothing readable leaves the browser
+pub async fn encrypt(passphrase: &str, plaintext: &str) -> Result<BackupEnvelope, String> {
+    let crypto = web_sys::window()
+        .expect("window is not available")
+        .crypto()
+        .map_err(js_error_message)?;
+    let subtle = crypto.subtle();
+    let salt = random_bytes(&crypto, SALT_LENGTH)?;
+    let iv = random_bytes(&crypto, IV_LENGTH)?;
+    let usages = js_sys::Array::of1(&JsValue::from_str("deriveKey"));
+    let base_key = subtle
+        .import_key_with_str(
+            "raw",
+            &js_sys::Uint8Array::from(passphrase.as_bytes()),
+            "PBKDF2",
+            false,
+            &usages,
+        )
+        .map_err(js_error_message)?;
+    let base_key = JsFuture::from(base_key)
+        .await
+        .map_err(js_error_message)?
+        .unchecked_into::<web_sys::CryptoKey>();
+    let usages = js_sys::Array::of1(&JsValue::from_str("encrypt"));
+    let key = subtle
+        .derive_key_with_object_and_object(
+            &web_sys::Pbkdf2Params::new(
+                "PBKDF2",
+                &JsValue::from_str("SHA-256"),
+                KEY_DERIVATION_ITERATIONS,
+                &salt,
+            ),
+            &base_key,
+            &web_sys::AesDerivedKeyParams::new("AES-GCM", 256),
+            false,
+            &usages,
+        )
+        .map_err(js_error_message)?;
+    let key = JsFuture::from(key)
+        .await
+        .map_err(js_error_message)?
+        .unchecked_into::<web_sys::CryptoKey>();
+    let data = subtle
+        .encrypt_with_object_and_buffer_source(
+            &web_sys::AesGcmParams::new("AES-GCM", &iv),
+            &key,
+            &js_sys::Uint8Array::from(plaintext.as_bytes()),
+        )
+        .map_err(js_error_message)?;
+    let data = JsFuture::from(data).await.map_err(js_error_message)?;
+    Ok(BackupEnvelope {
+        version: BACKUP_SNAPSHOT_VERSION,
+        cipher: "AES-GCM",
+        kdf: "PBKDF2-SHA256",
+        iterations: KEY_DERIVATION_ITERATIONS,
+        salt: base64::encode(salt.to_vec()),
+        iv: base64::encode(iv.to_vec()),
+        data: base64::encode(js_sys::Uint8Array::new(&data).to_vec()),
+    })
+}
+
+// Upload targets answer with empty bodies and 201/204 statuses, which the env fetch doesn't accept
+pub async fn upload(
+    target: &BackupTarget,
+    password: &Option<String>,
+    uid: &Option<String>,
+    body: &str,
+) -> Result<(), String> {
+    let headers = web_sys::Headers::new().map_err(js_error_message)?;
+    headers
+        .set("Content-Type", "application/json")
+        .map_err(js_error_message)?;
+    let url = match target {
+        BackupTarget::WebDav { url, username } => {
+            if let Some(username) = username {
+                let credentials =
+                    format!("{}:{}", username, password.as_deref().unwrap_or_default());
+                headers
+                    .set(
+                        "Authorization",
+                        &format!("Basic {}", base64::encode(credentials)),
+                    )
+                    .map_err(js_error_message)?;
+            };
+            webdav_file_url(url, uid)?
+        }
+    };
+    let mut request_options = web_sys::RequestInit::new();
+    request_options
+        .method("PUT")
+        .headers(&headers)
+        .body(Some(&JsValue::from_str(body)));
+    let request = web_sys::Request::new_with_str_and_init(url.as_str(), &request_options)
+        .map_err(js_error_message)?;
+    let resp = JsFuture::from(
+        web_sys::window()
+            .expect("window is not available")
+            .fetch_with_request(&request),
+    )
+    .await
+    .map_err(js_error_message)?
+    .unchecked_into::<web_sys::Response>();
+    if resp.ok() {
+        Ok(())
+    } else {
+        Err(format!("Unexpected HTTP status code {}", resp.status()))
+    }
+}
+
+fn webdav_file_url(url: &Url, uid: &Option<String>) -> Result<Url, String> {
+    let mut url = url.to_owned();
+    if !url.path().ends_with('/') {
+        url.set_path(&format!("{}/", url.path()));
+    };
+    url.join(&format!(
+        "stremio-backup-{}.json",
+        uid.as_deref().unwrap_or(ANONYMOUS_BACKUP_NAME)
+    ))
+    .map_err(|error| error.to_string())
+}
+
+fn random_bytes(crypto: &web_sys::Crypto, length: u32) -> Result<js_sys::Uint8Array, String> {
+    let bytes = js_sys::Uint8Array::new_with_length(length);
+    crypto
+        .get_random_values_with_array_buffer_view(&bytes)
+        .map_err(js_error_message)?;
+    Ok(bytes)
+}
+
+fn update<F: FnOnce(&mut LibraryBackup)>(f: F) -> LibraryBackup {
+    LIBRARY_BACKUP.with(|library_backup| {
+        let mut library_backup = library_backup.borrow_mut();
+        f(&mut library_backup);
+        library_backup.to_owned()
+    })
+}
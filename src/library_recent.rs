@@ -0,0 +1,244 @@
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::cell::RefCell;
+use std::cmp::Reverse;
+use std::collections::HashMap;
+use stremio_core::constants::LIBRARY_RECENT_COUNT;
+use stremio_core::types::library::{LibraryBucket, LibraryItem};
+
+pub const LIBRARY_RECENT_POLICY_STORAGE_KEY: &str = "library_recent_policy";
+
+thread_local! {
+    static LIBRARY_RECENT_POLICY: RefCell<RecentPolicy> = Default::default();
+}
+
+#[derive(Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", content = "value")]
+pub enum RecentPolicy {
+    Count(usize),
+    AgeDays(u32),
+}
+
+impl Default for RecentPolicy {
+    fn default() -> Self {
+        RecentPolicy::Count(LIBRARY_RECENT_COUNT)
+    }
+}
+
+impl RecentPolicy {
+    pub fn is_valid(&self) -> bool {
+        match self {
+            RecentPolicy::Count(count) => *count > 0,
+            RecentPolicy::AgeDays(days) => *days > 0,
+        }
+    }
+}
+
+pub struct LibrarySplit {
+    pub recent: LibraryBucket,
+    pub other: LibraryBucket,
+    pub rotated_ids: Vec<String>,
+}
+
+pub fn init(policy: RecentPolicy) {
+    LIBRARY_RECENT_POLICY.with(|current| *current.borrow_mut() = policy);
+}
+
+pub fn policy() -> RecentPolicy {
+    LIBRARY_RECENT_POLICY.with(|policy| *policy.borrow())
+}
+
+// Both stored buckets and the one being written are merged, newest mtime wins,
+// and the result is split again so that the policy holds whatever core decided to write
+pub fn split(
+    written: LibraryBucket,
+    stored_recent: Option<LibraryBucket>,
+    stored_other: Option<LibraryBucket>,
+    now: DateTime<Utc>,
+) -> LibrarySplit {
+    let stored_recent = stored_recent.filter(|bucket| bucket.uid == written.uid);
+    let stored_other = stored_other.filter(|bucket| bucket.uid == written.uid);
+    let previous_recent_ids = stored_recent
+        .as_ref()
+        .map(|bucket| bucket.items.keys().cloned().collect::<Vec<_>>())
+        .unwrap_or_default();
+    let uid = written.uid.to_owned();
+    let mut items = stored_other
+        .into_iter()
+        .chain(stored_recent)
+        .chain(Some(written))
+        .flat_map(|bucket| bucket.items.into_iter().map(|(_, item)| item))
+        .fold(HashMap::<String, LibraryItem>::new(), |mut items, item| {
+            match items.get(&item.id) {
+                Some(current) if current.mtime >= item.mtime => {}
+                _ => {
+                    items.insert(item.id.to_owned(), item);
+                }
+            };
+            items
+        })
+        .into_iter()
+        .map(|(_, item)| item)
+        .collect::<Vec<_>>();
+    items.sort_by_key(|item| Reverse(item.mtime));
+    let recent_count = match policy() {
+        RecentPolicy::Count(count) => count.min(items.len()),
+        RecentPolicy::AgeDays(days) => items
+            .iter()
+            .take_while(|item| now - item.mtime <= Duration::days(days as i64))
+            .count(),
+    };
+    let other_items = items.split_off(recent_count);
+    let rotated_ids = other_items
+        .iter()
+        .filter(|item| previous_recent_ids.contains(&item.id))
+        .map(|item| item.id.to_owned())
+        .collect();
+    LibrarySplit {
+        recent: LibraryBucket::new(uid.to_owned(), items),
+        other: LibraryBucket::new(uid, other_items),
+        rotated_ids,
+    }
+}
+
+// A recent bucket core wrote can be stored as is when it still fits the policy.
+// It is checked on its JSON, so the common single item update isn't deserialized and split.
+pub fn within_policy(bucket: &Value, now: DateTime<Utc>) -> bool {
+    let items = match bucket.get("items").and_then(Value::as_object) {
+        Some(items) => items,
+        None => return false,
+    };
+    match policy() {
+        RecentPolicy::Count(count) => items.len() <= count,
+        RecentPolicy::AgeDays(days) => items.values().all(|item| {
+            item.get("_mtime")
+                .and_then(Value::as_str)
+                .and_then(|mtime| DateTime::parse_from_rfc3339(mtime).ok())
+                .map_or(false, |mtime| {
+                    now - mtime.with_timezone(&Utc) <= Duration::days(days as i64)
+                })
+        }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn now() -> DateTime<Utc> {
+        Utc.ymd(2020, 5, 10).and_hms(12, 0, 0)
+    }
+
+    fn item(id: &str, days_ago: i64) -> LibraryItem {
+        LibraryItem {
+            id: id.to_owned(),
+            name: id.to_owned(),
+            r#type: "movie".to_owned(),
+            poster: None,
+            poster_shape: Default::default(),
+            removed: false,
+            temp: false,
+            ctime: None,
+            mtime: now() - Duration::days(days_ago),
+            state: Default::default(),
+            behavior_hints: Default::default(),
+        }
+    }
+
+    fn bucket(uid: Option<&str>, items: Vec<LibraryItem>) -> LibraryBucket {
+        LibraryBucket::new(uid.map(str::to_owned), items)
+    }
+
+    fn ids(bucket: &LibraryBucket) -> Vec<String> {
+        let mut ids = bucket.items.keys().cloned().collect::<Vec<_>>();
+        ids.sort();
+        ids
+    }
+
+    #[test]
+    fn split_keeps_the_newest_items_recent() {
+        init(RecentPolicy::Count(2));
+        let split = split(
+            bucket(Some("uid"), vec![item("a", 1)]),
+            Some(bucket(Some("uid"), vec![item("b", 2), item("c", 3)])),
+            Some(bucket(Some("uid"), vec![item("d", 4)])),
+            now(),
+        );
+        assert_eq!(ids(&split.recent), vec!["a", "b"]);
+        assert_eq!(ids(&split.other), vec!["c", "d"]);
+        assert_eq!(split.rotated_ids, vec!["c"]);
+        assert_eq!(split.recent.uid, Some("uid".to_owned()));
+    }
+
+    #[test]
+    fn split_prefers_the_newest_copy_of_an_item() {
+        init(RecentPolicy::Count(1));
+        let split = split(
+            bucket(Some("uid"), vec![item("a", 3)]),
+            Some(bucket(Some("uid"), vec![item("a", 1)])),
+            Some(bucket(Some("uid"), vec![item("b", 2)])),
+            now(),
+        );
+        assert_eq!(ids(&split.recent), vec!["a"]);
+        assert_eq!(split.recent.items["a"].mtime, now() - Duration::days(1));
+        assert_eq!(ids(&split.other), vec!["b"]);
+        assert!(split.rotated_ids.is_empty());
+    }
+
+    #[test]
+    fn split_ignores_buckets_of_other_profiles() {
+        init(RecentPolicy::Count(2));
+        let split = split(
+            bucket(Some("uid"), vec![item("a", 1)]),
+            Some(bucket(None, vec![item("b", 2)])),
+            Some(bucket(Some("other"), vec![item("c", 3)])),
+            now(),
+        );
+        assert_eq!(ids(&split.recent), vec!["a"]);
+        assert!(split.other.items.is_empty());
+    }
+
+    #[test]
+    fn split_by_age() {
+        init(RecentPolicy::AgeDays(7));
+        let split = split(
+            bucket(None, vec![item("a", 1), item("b", 7), item("c", 8)]),
+            None,
+            None,
+            now(),
+        );
+        assert_eq!(ids(&split.recent), vec!["a", "b"]);
+        assert_eq!(ids(&split.other), vec!["c"]);
+    }
+
+    #[test]
+    fn within_policy_by_count() {
+        init(RecentPolicy::Count(2));
+        let json = |items: Vec<LibraryItem>| serde_json::to_value(bucket(None, items)).unwrap();
+        assert!(within_policy(
+            &json(vec![item("a", 1), item("b", 20)]),
+            now()
+        ));
+        assert!(!within_policy(
+            &json(vec![item("a", 1), item("b", 2), item("c", 3)]),
+            now()
+        ));
+        assert!(!within_policy(&Value::Null, now()));
+    }
+
+    #[test]
+    fn within_policy_by_age() {
+        init(RecentPolicy::AgeDays(7));
+        let json = |items: Vec<LibraryItem>| serde_json::to_value(bucket(None, items)).unwrap();
+        assert!(within_policy(
+            &json(vec![item("a", 1), item("b", 7)]),
+            now()
+        ));
+        assert!(!within_policy(
+            &json(vec![item("a", 1), item("b", 8)]),
+            now()
+        ));
+    }
+}
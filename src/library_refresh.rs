@@ -0,0 +1,120 @@
+use crate::model::URI_COMPONENT_ENCODE_SET;
+use percent_encoding::utf8_percent_encode;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use stremio_core::types::library::LibraryItem;
+use stremio_core::types::resource::{MetaItem, MetaItemPreview};
+use url::Url;
+
+#[derive(Deserialize)]
+pub struct MetaResponse {
+    pub meta: MetaItem,
+}
+
+#[derive(Serialize)]
+pub struct LibraryItemChange {
+    pub key: &'static str,
+    pub before: Value,
+    pub after: Value,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LibraryItemRefresh {
+    pub id: String,
+    pub changes: Vec<LibraryItemChange>,
+}
+
+// Built the way core's http transport builds resource urls, so prefetched responses are found
+pub fn meta_url(transport_url: &Url, r#type: &str, id: &str) -> Result<Url, url::ParseError> {
+    transport_url.join(&format!(
+        "meta/{}/{}.json",
+        r#type,
+        utf8_percent_encode(id, URI_COMPONENT_ENCODE_SET)
+    ))
+}
+
+pub fn meta_item_preview(meta_item: MetaItem) -> MetaItemPreview {
+    MetaItemPreview {
+        id: meta_item.id,
+        r#type: meta_item.r#type,
+        name: meta_item.name,
+        poster: meta_item.poster,
+        logo: meta_item.logo,
+        description: meta_item.description,
+        release_info: meta_item.release_info,
+        runtime: meta_item.runtime,
+        released: meta_item.released,
+        poster_shape: meta_item.poster_shape,
+        trailer_streams: meta_item.trailer_streams,
+        behavior_hints: meta_item.behavior_hints,
+    }
+}
+
+pub fn library_item_changes(
+    library_item: &LibraryItem,
+    meta_item: &MetaItemPreview,
+) -> Vec<LibraryItemChange> {
+    vec![
+        (
+            "name",
+            serde_json::to_value(&library_item.name),
+            serde_json::to_value(&meta_item.name),
+        ),
+        (
+            "poster",
+            serde_json::to_value(&library_item.poster),
+            serde_json::to_value(&meta_item.poster),
+        ),
+        (
+            "posterShape",
+            serde_json::to_value(&library_item.poster_shape),
+            serde_json::to_value(&meta_item.poster_shape),
+        ),
+    ]
+    .into_iter()
+    .filter_map(|(key, before, after)| match (before, after) {
+        (Ok(before), Ok(after)) if before != after => {
+            Some(LibraryItemChange { key, before, after })
+        }
+        _ => None,
+    })
+    .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use http::Request;
+
+    // Prefetched responses are keyed by the request uri the env fetch sees
+    fn cache_key(r#type: &str, id: &str) -> String {
+        let transport_url = Url::parse("https://addon.example.com/manifest.json").unwrap();
+        let meta_url = meta_url(&transport_url, r#type, id).unwrap();
+        Request::get(meta_url.as_str())
+            .body(())
+            .unwrap()
+            .uri()
+            .to_string()
+    }
+
+    #[test]
+    fn meta_url_matches_core_encoding() {
+        assert_eq!(
+            cache_key("movie", "tt0111161"),
+            "https://addon.example.com/meta/movie/tt0111161.json"
+        );
+        assert_eq!(
+            cache_key("series", "tt0944947:1:2"),
+            "https://addon.example.com/meta/series/tt0944947%3A1%3A2.json"
+        );
+        assert_eq!(
+            cache_key("anime", "kitsu-1.5_(tv)!~*'"),
+            "https://addon.example.com/meta/anime/kitsu-1.5_(tv)!~*'.json"
+        );
+        assert_eq!(
+            cache_key("other", "a/b c?d"),
+            "https://addon.example.com/meta/other/a%2Fb%20c%3Fd.json"
+        );
+    }
+}
@@ -0,0 +1,87 @@
+use crate::js_value::from_js_value;
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::HashSet;
+use stremio_core::types::addon::Manifest;
+use wasm_bindgen::JsValue;
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ManifestError {
+    pub path: String,
+    pub message: String,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ManifestValidation {
+    pub valid: bool,
+    pub errors: Vec<ManifestError>,
+}
+
+impl From<Vec<ManifestError>> for ManifestValidation {
+    fn from(errors: Vec<ManifestError>) -> Self {
+        ManifestValidation {
+            valid: errors.is_empty(),
+            errors,
+        }
+    }
+}
+
+// Pasted manifests may still be JSON text, objects go through the same deserialization as the core does
+pub fn validate(manifest: &JsValue) -> ManifestValidation {
+    let manifest = match manifest.as_string() {
+        Some(text) => serde_json::from_str::<Value>(&text).map_err(|error| ManifestError {
+            path: String::new(),
+            message: error.to_string(),
+        }),
+        None => from_js_value::<Value>(manifest.to_owned()).map_err(|error| ManifestError {
+            path: String::new(),
+            message: error.to_string(),
+        }),
+    };
+    let manifest = manifest.and_then(|manifest| {
+        serde_path_to_error::deserialize::<_, Manifest>(manifest).map_err(|error| ManifestError {
+            path: error.path().to_string(),
+            message: error.into_inner().to_string(),
+        })
+    });
+    match manifest {
+        Ok(manifest) => ManifestValidation::from(manifest_errors(&manifest)),
+        Err(error) => ManifestValidation::from(vec![error]),
+    }
+}
+
+// Deserialization accepts manifests the addon catalog would still refuse to install
+fn manifest_errors(manifest: &Manifest) -> Vec<ManifestError> {
+    let mut errors = vec![];
+    if manifest.id.trim().is_empty() {
+        errors.push(error("id", "id must not be empty"));
+    };
+    if manifest.name.trim().is_empty() {
+        errors.push(error("name", "name must not be empty"));
+    };
+    if manifest.resources.is_empty() {
+        errors.push(error("resources", "at least one resource is required"));
+    };
+    let mut catalogs = HashSet::new();
+    for (index, catalog) in manifest.catalogs.iter().enumerate() {
+        if !catalogs.insert((&catalog.r#type, &catalog.id)) {
+            errors.push(error(
+                &format!("catalogs[{}]", index),
+                &format!(
+                    "catalog {} of type {} is declared more than once",
+                    catalog.id, catalog.r#type
+                ),
+            ));
+        };
+    }
+    errors
+}
+
+fn error(path: &str, message: &str) -> ManifestError {
+    ManifestError {
+        path: path.to_owned(),
+        message: message.to_owned(),
+    }
+}
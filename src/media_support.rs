@@ -0,0 +1,65 @@
+use serde::Serialize;
+use std::cell::RefCell;
+use std::collections::HashMap;
+use stremio_core::types::resource::{Stream, StreamSource};
+
+const WEB_SCHEMES: &[&str] = &["http", "https"];
+const CONTAINER_MEDIA_TYPES: &[(&str, &str)] = &[
+    ("mp4", "video/mp4"),
+    ("m4v", "video/mp4"),
+    ("webm", "video/webm"),
+    ("mkv", "video/x-matroska"),
+    ("avi", "video/x-msvideo"),
+    ("ts", "video/mp2t"),
+    ("m3u8", "application/vnd.apple.mpegurl"),
+];
+
+thread_local! {
+    static MEDIA_TYPES: RefCell<HashMap<String, bool>> = Default::default();
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Playable {
+    Direct,
+    ViaServer,
+    Unsupported,
+}
+
+pub fn init(media_types: HashMap<String, bool>) {
+    MEDIA_TYPES.with(|current| *current.borrow_mut() = media_types);
+}
+
+// Media types the browser wasn't asked about are assumed to be playable
+pub fn playable(stream: &Stream) -> Playable {
+    match &stream.source {
+        StreamSource::Torrent { .. } => Playable::ViaServer,
+        StreamSource::Url { url } => {
+            if !WEB_SCHEMES.contains(&url.scheme()) {
+                return Playable::Unsupported;
+            };
+            if stream.behavior_hints.not_web_ready {
+                return Playable::ViaServer;
+            };
+            let extension = url
+                .path_segments()
+                .and_then(|mut segments| segments.next_back())
+                .and_then(|filename| filename.rsplit('.').next())
+                .map(|extension| extension.to_lowercase());
+            let supported = extension
+                .and_then(|extension| {
+                    CONTAINER_MEDIA_TYPES
+                        .iter()
+                        .find(|(container, _)| *container == extension)
+                })
+                .and_then(|(_, media_type)| {
+                    MEDIA_TYPES.with(|media_types| media_types.borrow().get(*media_type).copied())
+                });
+            match supported {
+                Some(false) => Playable::ViaServer,
+                _ => Playable::Direct,
+            }
+        }
+        _ => Playable::Direct,
+    }
+}
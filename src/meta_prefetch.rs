@@ -0,0 +1,110 @@
+use serde::Deserialize;
+use std::cell::{Cell, RefCell};
+use std::collections::{HashMap, HashSet};
+use wasm_bindgen::closure::Closure;
+use wasm_bindgen::JsCast;
+
+const PREFETCH_TTL: f64 = 5.0 * 60.0 * 1000.0;
+const PREFETCH_SESSION_BUDGET: u32 = 200;
+const MAX_VISIBLE_ITEMS: usize = 20;
+const MAX_PREFETCHED: usize = 100;
+
+thread_local! {
+    static VISIBLE_ITEMS: RefCell<Vec<VisibleItem>> = Default::default();
+    static PREFETCHED: RefCell<HashMap<String, Prefetched>> = Default::default();
+    static IN_FLIGHT: RefCell<HashSet<String>> = Default::default();
+    static BUDGET_SPENT: Cell<u32> = Cell::new(0);
+    static IDLE_SCHEDULED: Cell<bool> = Cell::new(false);
+}
+
+#[derive(Clone, PartialEq, Deserialize)]
+pub struct VisibleItem {
+    pub r#type: String,
+    pub id: String,
+}
+
+struct Prefetched {
+    text: String,
+    fetched_at: f64,
+}
+
+pub fn set_visible_items(mut items: Vec<VisibleItem>) {
+    items.dedup();
+    items.truncate(MAX_VISIBLE_ITEMS);
+    VISIBLE_ITEMS.with(|visible_items| *visible_items.borrow_mut() = items);
+}
+
+pub fn visible_items() -> Vec<VisibleItem> {
+    VISIBLE_ITEMS.with(|visible_items| visible_items.borrow().to_owned())
+}
+
+// Visible items change a lot while scrolling, only the latest ones are prefetched once the browser is idle
+pub fn schedule<F: FnOnce() + 'static>(on_idle: F) {
+    if IDLE_SCHEDULED.with(|scheduled| scheduled.replace(true)) {
+        return;
+    };
+    let on_idle = Closure::once_into_js(move || {
+        IDLE_SCHEDULED.with(|scheduled| scheduled.set(false));
+        on_idle();
+    });
+    if web_sys::window()
+        .expect("window is not available")
+        .request_idle_callback(on_idle.unchecked_ref())
+        .is_err()
+    {
+        IDLE_SCHEDULED.with(|scheduled| scheduled.set(false));
+    };
+}
+
+// Urls already cached or being fetched don't spend the budget, everything else spends one request
+pub fn try_start(url: &str) -> bool {
+    let now = js_sys::Date::now();
+    let cached = PREFETCHED.with(|prefetched| {
+        prefetched.borrow().get(url).map_or(false, |prefetched| {
+            now - prefetched.fetched_at < PREFETCH_TTL
+        })
+    });
+    if cached || IN_FLIGHT.with(|in_flight| in_flight.borrow().contains(url)) {
+        return false;
+    };
+    let budget_left = BUDGET_SPENT.with(|budget_spent| {
+        if budget_spent.get() < PREFETCH_SESSION_BUDGET {
+            budget_spent.set(budget_spent.get() + 1);
+            true
+        } else {
+            false
+        }
+    });
+    if budget_left {
+        IN_FLIGHT.with(|in_flight| in_flight.borrow_mut().insert(url.to_owned()));
+    };
+    budget_left
+}
+
+pub fn finish(url: &str, text: Option<String>) {
+    IN_FLIGHT.with(|in_flight| in_flight.borrow_mut().remove(url));
+    if let Some(text) = text {
+        let now = js_sys::Date::now();
+        PREFETCHED.with(|prefetched| {
+            let mut prefetched = prefetched.borrow_mut();
+            prefetched.retain(|_, prefetched| now - prefetched.fetched_at < PREFETCH_TTL);
+            if prefetched.len() < MAX_PREFETCHED {
+                prefetched.insert(
+                    url.to_owned(),
+                    Prefetched {
+                        text,
+                        fetched_at: now,
+                    },
+                );
+            };
+        });
+    };
+}
+
+// Prefetched responses are handed out once, later loads go to the network as usual
+pub fn take(url: &str) -> Option<String> {
+    PREFETCHED
+        .with(|prefetched| prefetched.borrow_mut().remove(url))
+        .filter(|prefetched| js_sys::Date::now() - prefetched.fetched_at < PREFETCH_TTL)
+        .map(|prefetched| prefetched.text)
+}
@@ -0,0 +1,68 @@
+use std::cell::RefCell;
+use wasm_bindgen::JsValue;
+
+const MIDDLEWARE_TIME_BUDGET: f64 = 5.0;
+const MIDDLEWARE_BYPASS_ACTIONS: &[&[&str]] = &[&["Ctx", "Logout"], &["Player"], &["Unload"]];
+
+thread_local! {
+    static MIDDLEWARES: RefCell<Vec<js_sys::Function>> = Default::default();
+}
+
+pub fn register_middleware(middleware: js_sys::Function) {
+    MIDDLEWARES.with(|middlewares| middlewares.borrow_mut().push(middleware));
+}
+
+// A middleware returns `false` to veto the action, an object to replace it or anything else to pass it.
+// Verdicts of middlewares that throw or exceed the time budget are ignored.
+pub fn apply_middlewares(action: &JsValue, field: &JsValue) -> Option<JsValue> {
+    if is_bypassed(action) {
+        return Some(action.to_owned());
+    };
+    let middlewares = MIDDLEWARES.with(|middlewares| middlewares.borrow().to_owned());
+    middlewares
+        .iter()
+        .try_fold(action.to_owned(), |action, middleware| {
+            let start = js_sys::Date::now();
+            let result = middleware.call2(&JsValue::NULL, &action, field);
+            let elapsed = js_sys::Date::now() - start;
+            match result {
+                _ if elapsed > MIDDLEWARE_TIME_BUDGET => {
+                    web_sys::console::warn_1(&JsValue::from(format!(
+                        "middleware exceeded time budget ({}ms), verdict ignored",
+                        elapsed
+                    )));
+                    Some(action)
+                }
+                Ok(result) if result.as_bool() == Some(false) => None,
+                Ok(result) if result.is_object() => Some(result),
+                Ok(_) => Some(action),
+                Err(error) => {
+                    web_sys::console::error_2(&JsValue::from("middleware failed"), &error);
+                    Some(action)
+                }
+            }
+        })
+}
+
+fn is_bypassed(action: &JsValue) -> bool {
+    let path = action_path(action);
+    MIDDLEWARE_BYPASS_ACTIONS.iter().any(|bypass| {
+        path.len() >= bypass.len() && path.iter().zip(bypass.iter()).all(|(a, b)| a == b)
+    })
+}
+
+pub fn action_path(action: &JsValue) -> Vec<String> {
+    let mut path = vec![];
+    let mut current = action.to_owned();
+    while let Some(name) = js_sys::Reflect::get(&current, &JsValue::from("action"))
+        .ok()
+        .and_then(|name| name.as_string())
+    {
+        path.push(name);
+        current = match js_sys::Reflect::get(&current, &JsValue::from("args")) {
+            Ok(args) if args.is_object() => args,
+            _ => break,
+        };
+    }
+    path
+}
@@ -0,0 +1,69 @@
+use serde::Serialize;
+use stremio_core::constants::SKIP_EXTRA_NAME;
+use stremio_core::types::addon::{Descriptor, ManifestCatalog, ManifestExtra, ResourceRequest};
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DeclaredExtraProp<'a> {
+    pub name: &'a String,
+    pub is_required: bool,
+    pub options: Option<&'a Vec<String>>,
+    pub options_limit: usize,
+    pub default: Option<&'a String>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DeclaredExtra<'a> {
+    pub props: Vec<DeclaredExtraProp<'a>>,
+    pub skip_supported: bool,
+}
+
+// Required extras with options default to the first one, which is what core sends when none is selected
+pub fn declared_extra(catalog: &ManifestCatalog) -> DeclaredExtra {
+    let props = match &catalog.extra {
+        ManifestExtra::Full { props } => props
+            .iter()
+            .map(|prop| DeclaredExtraProp {
+                name: &prop.name,
+                is_required: prop.is_required,
+                options: prop.options.as_ref(),
+                options_limit: prop.options_limit,
+                default: prop
+                    .options
+                    .as_ref()
+                    .filter(|_| prop.is_required)
+                    .and_then(|options| options.first()),
+            })
+            .collect::<Vec<_>>(),
+        ManifestExtra::Short {
+            required,
+            supported,
+        } => supported
+            .iter()
+            .map(|name| DeclaredExtraProp {
+                name,
+                is_required: required.contains(name),
+                options: None,
+                options_limit: 1,
+                default: None,
+            })
+            .collect::<Vec<_>>(),
+    };
+    DeclaredExtra {
+        skip_supported: props.iter().any(|prop| prop.name == SKIP_EXTRA_NAME),
+        props,
+    }
+}
+
+pub fn request_declared_extra<'a>(
+    request: &ResourceRequest,
+    addon: &'a Descriptor,
+) -> Option<DeclaredExtra<'a>> {
+    addon
+        .manifest
+        .catalogs
+        .iter()
+        .find(|catalog| catalog.id == request.path.id && catalog.r#type == request.path.r#type)
+        .map(declared_extra)
+}
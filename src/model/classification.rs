@@ -0,0 +1,119 @@
+pub const GENRE_LINK_CATEGORY: &str = "Genres";
+pub const CERTIFICATION_LINK_CATEGORY: &str = "Certification";
+
+const GENRE_KEYS: &[(&str, &[&str])] = &[
+    (
+        "action",
+        &[
+            "action",
+            "action & adventure",
+            "acción",
+            "azione",
+            "ação",
+            "aktion",
+        ],
+    ),
+    (
+        "adventure",
+        &["adventure", "aventura", "avventura", "abenteuer"],
+    ),
+    (
+        "animation",
+        &[
+            "animation",
+            "animated",
+            "animación",
+            "animazione",
+            "animação",
+        ],
+    ),
+    (
+        "comedy",
+        &[
+            "comedy", "comedia", "commedia", "comédia", "comédie", "komödie",
+        ],
+    ),
+    ("crime", &["crime", "crimen", "crimine", "krimi"]),
+    (
+        "documentary",
+        &[
+            "documentary",
+            "documental",
+            "documentario",
+            "documentário",
+            "documentaire",
+            "dokumentarfilm",
+        ],
+    ),
+    ("drama", &["drama", "dramma", "drame"]),
+    (
+        "family",
+        &["family", "familia", "famiglia", "familie", "famille"],
+    ),
+    (
+        "fantasy",
+        &["fantasy", "fantasia", "fantasía", "fantastique"],
+    ),
+    (
+        "history",
+        &["history", "historia", "storia", "histoire", "historie"],
+    ),
+    ("horror", &["horror", "terror", "orrore", "horreur"]),
+    ("music", &["music", "musica", "música", "musique", "musik"]),
+    ("mystery", &["mystery", "misterio", "mistero", "mystère"]),
+    ("news", &["news", "noticias", "notizie", "nachrichten"]),
+    ("reality-tv", &["reality", "reality-tv", "reality tv"]),
+    (
+        "romance",
+        &["romance", "romántico", "romantico", "romantik"],
+    ),
+    (
+        "science-fiction",
+        &[
+            "science-fiction",
+            "science fiction",
+            "sci-fi",
+            "scifi",
+            "sci-fi & fantasy",
+            "ciencia ficción",
+            "fantascienza",
+            "ficção científica",
+        ],
+    ),
+    ("sport", &["sport", "sports", "deporte", "deportes"]),
+    ("talk-show", &["talk-show", "talk show", "talk"]),
+    ("thriller", &["thriller", "suspense"]),
+    (
+        "war",
+        &["war", "war & politics", "guerra", "guerre", "krieg"],
+    ),
+    ("western", &["western"]),
+];
+
+const CERTIFICATION_AGES: &[(u8, &[&str])] = &[
+    (0, &["g", "tv-g", "tv-y", "u", "0", "fsk 0", "all"]),
+    (7, &["tv-y7", "6", "7", "fsk 6"]),
+    (10, &["pg", "tv-pg"]),
+    (12, &["12", "12a", "pg-12", "fsk 12"]),
+    (13, &["pg-13"]),
+    (14, &["tv-14", "14"]),
+    (15, &["15"]),
+    (17, &["r", "tv-ma", "16", "fsk 16", "m"]),
+    (18, &["nc-17", "18", "r18", "fsk 18"]),
+];
+
+pub fn genre_key(genre: &str) -> Option<&'static str> {
+    let genre = genre.trim().to_lowercase();
+    GENRE_KEYS
+        .iter()
+        .find(|(_, aliases)| aliases.contains(&genre.as_str()))
+        .map(|(key, _)| *key)
+}
+
+pub fn certification_minimum_age(certification: &str) -> Option<u8> {
+    let certification = certification.trim().to_lowercase();
+    CERTIFICATION_AGES
+        .iter()
+        .find(|(_, aliases)| aliases.contains(&certification.as_str()))
+        .map(|(age, _)| *age)
+}
@@ -1,3 +1,4 @@
+use crate::share;
 use flate2::write::ZlibEncoder;
 use flate2::Compression;
 use percent_encoding::{utf8_percent_encode, AsciiSet, NON_ALPHANUMERIC};
@@ -7,12 +8,12 @@ use std::io;
 use std::io::Write;
 use stremio_core::models::installed_addons_with_filters::InstalledAddonsRequest;
 use stremio_core::models::library_with_filters::LibraryRequest;
-use stremio_core::types::addon::{ExtraValue, ResourceRequest};
+use stremio_core::types::addon::{ExtraValue, ResourcePath, ResourceRequest};
 use stremio_core::types::library::LibraryItem;
 use stremio_core::types::resource::{MetaItem, MetaItemPreview, Stream, Video};
 use url::form_urlencoded;
 
-const URI_COMPONENT_ENCODE_SET: &AsciiSet = &NON_ALPHANUMERIC
+pub const URI_COMPONENT_ENCODE_SET: &AsciiSet = &NON_ALPHANUMERIC
     .remove(b'-')
     .remove(b'_')
     .remove(b'.')
@@ -23,6 +24,37 @@ const URI_COMPONENT_ENCODE_SET: &AsciiSet = &NON_ALPHANUMERIC
     .remove(b'(')
     .remove(b')');
 
+pub fn meta_details_share_url(r#type: &str, id: &str) -> String {
+    share::share_url(&format!(
+        "#/metadetails/{}/{}",
+        utf8_percent_encode(r#type, URI_COMPONENT_ENCODE_SET),
+        utf8_percent_encode(id, URI_COMPONENT_ENCODE_SET)
+    ))
+}
+
+pub fn meta_details_url(meta_path: &ResourcePath, stream_path: Option<&ResourcePath>) -> String {
+    match stream_path {
+        Some(stream_path) => format!(
+            "#/metadetails/{}/{}/{}",
+            utf8_percent_encode(&meta_path.r#type, URI_COMPONENT_ENCODE_SET),
+            utf8_percent_encode(&meta_path.id, URI_COMPONENT_ENCODE_SET),
+            utf8_percent_encode(&stream_path.id, URI_COMPONENT_ENCODE_SET)
+        ),
+        None => format!(
+            "#/metadetails/{}/{}",
+            utf8_percent_encode(&meta_path.r#type, URI_COMPONENT_ENCODE_SET),
+            utf8_percent_encode(&meta_path.id, URI_COMPONENT_ENCODE_SET)
+        ),
+    }
+}
+
+pub fn search_url(query: Option<&String>) -> String {
+    match query {
+        Some(query) => format!("#/search?{}", query_params_encode(&[("search", query)])),
+        None => "#/search".to_owned(),
+    }
+}
+
 #[derive(Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct LibraryItemDeepLinks {
@@ -67,6 +99,7 @@ impl From<&LibraryItem> for LibraryItemDeepLinks {
 pub struct MetaItemDeepLinks {
     meta_details_videos: Option<String>,
     meta_details_streams: Option<String>,
+    share: String,
 }
 
 impl From<&MetaItemPreview> for MetaItemDeepLinks {
@@ -94,6 +127,7 @@ impl From<&MetaItemPreview> for MetaItemDeepLinks {
                         utf8_percent_encode(&video_id, URI_COMPONENT_ENCODE_SET)
                     )
                 }),
+            share: meta_details_share_url(&item.r#type, &item.id),
         }
     }
 }
@@ -123,6 +157,7 @@ impl From<&MetaItem> for MetaItemDeepLinks {
                         utf8_percent_encode(&video_id, URI_COMPONENT_ENCODE_SET)
                     )
                 }),
+            share: meta_details_share_url(&item.r#type, &item.id),
         }
     }
 }
@@ -130,6 +165,7 @@ impl From<&MetaItem> for MetaItemDeepLinks {
 #[derive(Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct VideoDeepLinks {
+    meta_details_videos: Option<String>,
     meta_details_streams: String,
     player: Option<String>,
 }
@@ -137,12 +173,37 @@ pub struct VideoDeepLinks {
 impl From<(&Video, &ResourceRequest)> for VideoDeepLinks {
     fn from((video, request): (&Video, &ResourceRequest)) -> Self {
         VideoDeepLinks {
-            meta_details_streams: format!(
-                "#/metadetails/{}/{}/{}",
-                utf8_percent_encode(&request.path.r#type, URI_COMPONENT_ENCODE_SET),
-                utf8_percent_encode(&request.path.id, URI_COMPONENT_ENCODE_SET),
-                utf8_percent_encode(&video.id, URI_COMPONENT_ENCODE_SET)
-            ),
+            // Episodes can also land on the videos list with their season selected, without the stream picker
+            meta_details_videos: video.series_info.as_ref().map(|series_info| {
+                format!(
+                    "#/metadetails/{}/{}?{}",
+                    utf8_percent_encode(&request.path.r#type, URI_COMPONENT_ENCODE_SET),
+                    utf8_percent_encode(&request.path.id, URI_COMPONENT_ENCODE_SET),
+                    query_params_encode(&[
+                        ("season", series_info.season.to_string().as_str()),
+                        ("videoId", &video.id),
+                        ("openStreams", "false"),
+                    ])
+                )
+            }),
+            meta_details_streams: match &video.series_info {
+                Some(series_info) => format!(
+                    "#/metadetails/{}/{}/{}?{}",
+                    utf8_percent_encode(&request.path.r#type, URI_COMPONENT_ENCODE_SET),
+                    utf8_percent_encode(&request.path.id, URI_COMPONENT_ENCODE_SET),
+                    utf8_percent_encode(&video.id, URI_COMPONENT_ENCODE_SET),
+                    query_params_encode(&[
+                        ("season", series_info.season.to_string().as_str()),
+                        ("openStreams", "true"),
+                    ])
+                ),
+                None => format!(
+                    "#/metadetails/{}/{}/{}",
+                    utf8_percent_encode(&request.path.r#type, URI_COMPONENT_ENCODE_SET),
+                    utf8_percent_encode(&request.path.id, URI_COMPONENT_ENCODE_SET),
+                    utf8_percent_encode(&video.id, URI_COMPONENT_ENCODE_SET)
+                ),
+            },
             player: video
                 .streams
                 .first()
@@ -209,7 +270,7 @@ impl From<(&Stream, &ResourceRequest, &ResourceRequest)> for StreamDeepLinks {
 
 #[derive(Serialize)]
 pub struct DiscoverDeepLinks {
-    discover: String,
+    pub discover: String,
 }
 
 impl From<&ResourceRequest> for DiscoverDeepLinks {
@@ -234,7 +295,7 @@ impl From<&ResourceRequest> for DiscoverDeepLinks {
 
 #[derive(Serialize)]
 pub struct AddonsDeepLinks {
-    addons: String,
+    pub addons: String,
 }
 
 impl From<&ResourceRequest> for AddonsDeepLinks {
@@ -269,7 +330,7 @@ impl From<&InstalledAddonsRequest> for AddonsDeepLinks {
 
 #[derive(Serialize)]
 pub struct LibraryDeepLinks {
-    library: String,
+    pub library: String,
 }
 
 impl From<&String> for LibraryDeepLinks {
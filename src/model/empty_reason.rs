@@ -0,0 +1,36 @@
+use serde::Serialize;
+use stremio_core::models::common::ResourceError;
+use stremio_core::runtime::EnvError;
+use stremio_core::types::addon::{Descriptor, ResourceRequest};
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum EmptyReason {
+    EmptyContent,
+    ExtraRequired,
+    TypeNotSupported,
+    UnexpectedResponse,
+}
+
+pub fn empty_reason(
+    error: &ResourceError,
+    request: &ResourceRequest,
+    addon: &Descriptor,
+) -> Option<EmptyReason> {
+    let manifest_catalog = addon
+        .manifest
+        .catalogs
+        .iter()
+        .find(|catalog| catalog.id == request.path.id && catalog.r#type == request.path.r#type);
+    match (manifest_catalog, error) {
+        (None, _) => Some(EmptyReason::TypeNotSupported),
+        (Some(catalog), _) if !catalog.is_extra_supported(&request.path.extra) => {
+            Some(EmptyReason::ExtraRequired)
+        }
+        (_, ResourceError::EmptyContent) => Some(EmptyReason::EmptyContent),
+        (_, ResourceError::UnexpectedResponse(_)) | (_, ResourceError::Env(EnvError::Serde(_))) => {
+            Some(EmptyReason::UnexpectedResponse)
+        }
+        _ => None,
+    }
+}
@@ -0,0 +1,90 @@
+use std::cmp::Ordering;
+use stremio_core::types::resource::Video;
+
+const SPECIALS_SEASON: u32 = 0;
+
+pub struct EpisodeNavigation<'a> {
+    pub season: u32,
+    pub seasons: Vec<u32>,
+    pub first_in_season: bool,
+    pub last_in_season: bool,
+    pub previous: Option<&'a Video>,
+    pub next: Option<&'a Video>,
+    pub next_season: Option<&'a Video>,
+}
+
+// Specials are only navigated between when one of them is playing, otherwise
+// jumping past the last episode of a season would land on season 0
+pub fn episode_navigation<'a>(
+    videos: &'a [Video],
+    video_id: &str,
+    last_watched_video_id: Option<&str>,
+) -> Option<EpisodeNavigation<'a>> {
+    let current = videos.iter().find(|video| video.id == video_id)?;
+    let season = current.series_info.as_ref()?.season;
+    let mut episodes = videos
+        .iter()
+        .filter(|video| {
+            video.series_info.as_ref().map_or(false, |series_info| {
+                (series_info.season == SPECIALS_SEASON) == (season == SPECIALS_SEASON)
+            })
+        })
+        .collect::<Vec<_>>();
+    episodes.sort_by(|a, b| compare_episodes(a, b));
+    let position = episodes.iter().position(|video| video.id == video_id)?;
+    let mut seasons = episodes
+        .iter()
+        .filter_map(|video| video.series_info.as_ref())
+        .map(|series_info| series_info.season)
+        .collect::<Vec<_>>();
+    seasons.dedup();
+    let season_of = |video: &Video| {
+        video
+            .series_info
+            .as_ref()
+            .map(|series_info| series_info.season)
+    };
+    let previous = position
+        .checked_sub(1)
+        .and_then(|position| episodes.get(position))
+        .copied();
+    let next = episodes.get(position + 1).copied();
+    // Progress is linear, anything up to the last watched video counts as watched
+    let watched_until = last_watched_video_id
+        .and_then(|video_id| episodes.iter().position(|video| video.id == video_id));
+    let next_season = seasons
+        .iter()
+        .find(|next_season| **next_season > season)
+        .and_then(|next_season| {
+            episodes
+                .iter()
+                .enumerate()
+                .filter(|(_, video)| season_of(video) == Some(*next_season))
+                .find(|(index, _)| {
+                    watched_until.map_or(true, |watched_until| *index > watched_until)
+                })
+                .or_else(|| {
+                    episodes
+                        .iter()
+                        .enumerate()
+                        .find(|(_, video)| season_of(video) == Some(*next_season))
+                })
+                .map(|(_, video)| *video)
+        });
+    Some(EpisodeNavigation {
+        season,
+        seasons,
+        first_in_season: previous.map_or(true, |previous| season_of(previous) != Some(season)),
+        last_in_season: next.map_or(true, |next| season_of(next) != Some(season)),
+        previous,
+        next,
+        next_season,
+    })
+}
+
+fn compare_episodes(a: &Video, b: &Video) -> Ordering {
+    match (&a.series_info, &b.series_info) {
+        (Some(a), Some(b)) => (a.season, a.episode).cmp(&(b.season, b.episode)),
+        _ => Ordering::Equal,
+    }
+}
@@ -1,14 +1,42 @@
+mod catalog_extra;
+
+mod classification;
+
 mod deep_links;
+pub use deep_links::{StreamDeepLinks, URI_COMPONENT_ENCODE_SET};
+
+mod empty_reason;
+
+mod episode_navigation;
+
+mod release_date;
+
+mod route_context;
+pub use route_context::{route_context, RouteContext};
+
+mod search_relevance;
+
+mod series_status;
+
+mod time_remaining;
+
+mod transport_protocol;
 
 mod serialize_catalogs_with_extra;
 use serialize_catalogs_with_extra::*;
 
+mod serialize_ctx;
+use serialize_ctx::*;
+
 mod serialize_continue_watching_preview;
 use serialize_continue_watching_preview::*;
 
 mod serialize_discover;
 use serialize_discover::*;
 
+mod serialize_home;
+use serialize_home::*;
+
 mod serialize_installed_addons;
 use serialize_installed_addons::*;
 
@@ -1,9 +1,18 @@
+use crate::board_layout::board_layout;
 use crate::env::WebEnv;
+use crate::home_layout::HomeLayout;
+use crate::incognito;
+use crate::js_value::to_js_value;
 use crate::model::{
-    serialize_catalogs_with_extra, serialize_continue_watching_preview, serialize_discover,
-    serialize_installed_addons, serialize_library, serialize_meta_details, serialize_player,
-    serialize_remote_addons,
+    serialize_catalogs_with_extra, serialize_continue_watching_preview, serialize_ctx,
+    serialize_discover, serialize_home, serialize_installed_addons, serialize_library,
+    serialize_meta_details, serialize_player, serialize_remote_addons,
 };
+use crate::mounted_fields;
+use serde::{Serialize, Serializer};
+use std::cell::RefCell;
+use std::mem::{self, Discriminant};
+use std::ops::{Deref, DerefMut};
 use stremio_core::models::addon_details::AddonDetails;
 use stremio_core::models::catalog_with_filters::CatalogWithFilters;
 use stremio_core::models::catalogs_with_extra::CatalogsWithExtra;
@@ -16,7 +25,8 @@ use stremio_core::models::library_with_filters::{
 use stremio_core::models::meta_details::MetaDetails;
 use stremio_core::models::player::Player;
 use stremio_core::models::streaming_server::StreamingServer;
-use stremio_core::runtime::Effects;
+use stremio_core::runtime::msg::{Action, ActionLoad, Msg};
+use stremio_core::runtime::{Effects, UpdateWithCtx};
 use stremio_core::types::addon::DescriptorPreview;
 use stremio_core::types::library::LibraryBucket;
 use stremio_core::types::profile::Profile;
@@ -24,21 +34,116 @@ use stremio_core::types::resource::MetaItemPreview;
 use stremio_derive::Model;
 use wasm_bindgen::JsValue;
 
+thread_local! {
+    static CHANGED_FIELDS: RefCell<Vec<Discriminant<WebModelField>>> = Default::default();
+}
+
+impl WebModelField {
+    pub const ALL: &'static [WebModelField] = &[
+        WebModelField::Ctx,
+        WebModelField::ContinueWatchingPreview,
+        WebModelField::Board,
+        WebModelField::Discover,
+        WebModelField::Library,
+        WebModelField::ContinueWatching,
+        WebModelField::Search,
+        WebModelField::MetaDetails,
+        WebModelField::RemoteAddons,
+        WebModelField::InstalledAddons,
+        WebModelField::AddonDetails,
+        WebModelField::StreamingServer,
+        WebModelField::Player,
+    ];
+}
+
+pub struct FieldState<'a> {
+    model: &'a WebModel,
+    field: &'a WebModelField,
+}
+
+impl<'a> Serialize for FieldState<'a> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.model.serialize_state(self.field, serializer)
+    }
+}
+
+// Wraps a model to record its field as changed whenever one of its updates reports a change.
+// Models of unmounted fields skip their updates and are stale until they are loaded again.
+pub struct Tracked<M> {
+    model: M,
+    field: WebModelField,
+    before_update: fn(&mut M, &Msg),
+    stale: bool,
+}
+
+impl<M> Tracked<M> {
+    pub fn new(field: WebModelField, model: M) -> Self {
+        Tracked {
+            model,
+            field,
+            before_update: |_, _| {},
+            stale: false,
+        }
+    }
+    pub fn with_before_update(self, before_update: fn(&mut M, &Msg)) -> Self {
+        Tracked {
+            before_update,
+            ..self
+        }
+    }
+    fn reload(&self, load: impl FnOnce(&M) -> Option<ActionLoad>) -> Option<ActionLoad> {
+        if self.stale {
+            load(&self.model)
+        } else {
+            None
+        }
+    }
+}
+
+impl<M> Deref for Tracked<M> {
+    type Target = M;
+    fn deref(&self) -> &M {
+        &self.model
+    }
+}
+
+impl<M> DerefMut for Tracked<M> {
+    fn deref_mut(&mut self) -> &mut M {
+        &mut self.model
+    }
+}
+
+impl<M: UpdateWithCtx<WebEnv>> UpdateWithCtx<WebEnv> for Tracked<M> {
+    fn update(&mut self, msg: &Msg, ctx: &Ctx<WebEnv>) -> Effects {
+        if !mounted_fields::is_mounted(&self.field) {
+            self.stale = true;
+            return Effects::none().unchanged();
+        };
+        self.stale = false;
+        (self.before_update)(&mut self.model, msg);
+        let effects = self.model.update(msg, ctx);
+        if effects.has_changed {
+            mark_changed(std::slice::from_ref(&self.field));
+        };
+        effects
+    }
+}
+
 #[derive(Model)]
 pub struct WebModel {
     pub ctx: Ctx<WebEnv>,
-    pub continue_watching_preview: ContinueWatchingPreview,
-    pub board: CatalogsWithExtra,
-    pub discover: CatalogWithFilters<MetaItemPreview>,
-    pub library: LibraryWithFilters<NotRemovedFilter>,
-    pub continue_watching: LibraryWithFilters<ContinueWatchingFilter>,
-    pub search: CatalogsWithExtra,
-    pub meta_details: MetaDetails,
-    pub remote_addons: CatalogWithFilters<DescriptorPreview>,
-    pub installed_addons: InstalledAddonsWithFilters,
-    pub addon_details: AddonDetails,
-    pub streaming_server: StreamingServer,
-    pub player: Player,
+    pub continue_watching_preview: Tracked<ContinueWatchingPreview>,
+    pub board: Tracked<CatalogsWithExtra>,
+    pub discover: Tracked<CatalogWithFilters<MetaItemPreview>>,
+    pub library: Tracked<LibraryWithFilters<NotRemovedFilter>>,
+    pub continue_watching: Tracked<LibraryWithFilters<ContinueWatchingFilter>>,
+    pub search: Tracked<CatalogsWithExtra>,
+    pub meta_details: Tracked<MetaDetails>,
+    pub remote_addons: Tracked<CatalogWithFilters<DescriptorPreview>>,
+    pub installed_addons: Tracked<InstalledAddonsWithFilters>,
+    pub addon_details: Tracked<AddonDetails>,
+    pub streaming_server: Tracked<StreamingServer>,
+    pub player: Tracked<Player>,
 }
 
 impl WebModel {
@@ -56,18 +161,22 @@ impl WebModel {
         let (streaming_server, streaming_server_effects) = StreamingServer::new::<WebEnv>(&profile);
         let model = WebModel {
             ctx: Ctx::new(profile, library),
-            continue_watching_preview,
-            board: Default::default(),
-            discover,
-            library: library_,
-            continue_watching,
-            search: Default::default(),
-            meta_details: Default::default(),
-            remote_addons,
-            installed_addons,
-            addon_details: Default::default(),
-            streaming_server,
-            player: Default::default(),
+            continue_watching_preview: Tracked::new(
+                WebModelField::ContinueWatchingPreview,
+                continue_watching_preview,
+            ),
+            board: Tracked::new(WebModelField::Board, Default::default()),
+            discover: Tracked::new(WebModelField::Discover, discover),
+            library: Tracked::new(WebModelField::Library, library_),
+            continue_watching: Tracked::new(WebModelField::ContinueWatching, continue_watching),
+            search: Tracked::new(WebModelField::Search, Default::default()),
+            meta_details: Tracked::new(WebModelField::MetaDetails, Default::default()),
+            remote_addons: Tracked::new(WebModelField::RemoteAddons, remote_addons),
+            installed_addons: Tracked::new(WebModelField::InstalledAddons, installed_addons),
+            addon_details: Tracked::new(WebModelField::AddonDetails, Default::default()),
+            streaming_server: Tracked::new(WebModelField::StreamingServer, streaming_server),
+            player: Tracked::new(WebModelField::Player, Player::default())
+                .with_before_update(detach_incognito_player),
         };
         (
             model,
@@ -80,25 +189,233 @@ impl WebModel {
                 .join(streaming_server_effects),
         )
     }
+    // Only route models are loaded and unloaded, the rest live as long as the runtime
+    pub fn is_loaded(&self, field: &WebModelField) -> bool {
+        match field {
+            WebModelField::Board => self.board.selected.is_some(),
+            WebModelField::Discover => self.discover.selected.is_some(),
+            WebModelField::Library => self.library.selected.is_some(),
+            WebModelField::ContinueWatching => self.continue_watching.selected.is_some(),
+            WebModelField::Search => self.search.selected.is_some(),
+            WebModelField::MetaDetails => self.meta_details.selected.is_some(),
+            WebModelField::RemoteAddons => self.remote_addons.selected.is_some(),
+            WebModelField::InstalledAddons => self.installed_addons.selected.is_some(),
+            WebModelField::AddonDetails => self.addon_details.selected.is_some(),
+            WebModelField::Player => self.player.selected.is_some(),
+            WebModelField::Ctx
+            | WebModelField::ContinueWatchingPreview
+            | WebModelField::StreamingServer => false,
+        }
+    }
+    // Models that skipped messages while unmounted are loaded again from their selection
+    pub fn reload_action(&self, field: &WebModelField) -> Option<Action> {
+        let load = match field {
+            WebModelField::Board => self
+                .board
+                .reload(|board| board.selected.to_owned().map(ActionLoad::CatalogsWithExtra)),
+            WebModelField::Discover => self.discover.reload(|discover| {
+                discover
+                    .selected
+                    .to_owned()
+                    .map(|selected| ActionLoad::CatalogWithFilters(Some(selected)))
+            }),
+            WebModelField::Library => self.library.reload(|library| {
+                library
+                    .selected
+                    .to_owned()
+                    .map(ActionLoad::LibraryWithFilters)
+            }),
+            WebModelField::ContinueWatching => self.continue_watching.reload(|continue_watching| {
+                continue_watching
+                    .selected
+                    .to_owned()
+                    .map(ActionLoad::LibraryWithFilters)
+            }),
+            WebModelField::Search => self.search.reload(|search| {
+                search
+                    .selected
+                    .to_owned()
+                    .map(ActionLoad::CatalogsWithExtra)
+            }),
+            WebModelField::MetaDetails => self.meta_details.reload(|meta_details| {
+                meta_details
+                    .selected
+                    .to_owned()
+                    .map(ActionLoad::MetaDetails)
+            }),
+            WebModelField::RemoteAddons => self.remote_addons.reload(|remote_addons| {
+                remote_addons
+                    .selected
+                    .to_owned()
+                    .map(|selected| ActionLoad::CatalogWithFilters(Some(selected)))
+            }),
+            WebModelField::InstalledAddons => self.installed_addons.reload(|installed_addons| {
+                installed_addons
+                    .selected
+                    .to_owned()
+                    .map(ActionLoad::InstalledAddonsWithFilters)
+            }),
+            WebModelField::AddonDetails => self.addon_details.reload(|addon_details| {
+                addon_details
+                    .selected
+                    .to_owned()
+                    .map(ActionLoad::AddonDetails)
+            }),
+            WebModelField::Ctx
+            | WebModelField::ContinueWatchingPreview
+            | WebModelField::StreamingServer
+            | WebModelField::Player => None,
+        };
+        load.map(Action::Load)
+    }
+    pub fn get_home(&self, home_layout: &HomeLayout) -> JsValue {
+        serialize_home(
+            &self.board,
+            &self.continue_watching_preview,
+            &self.ctx,
+            home_layout,
+        )
+    }
     pub fn get_state(&self, field: &WebModelField) -> JsValue {
+        to_js_value(&self.get_state_value(field)).unwrap()
+    }
+    pub fn field_state<'a>(&'a self, field: &'a WebModelField) -> FieldState<'a> {
+        FieldState { model: self, field }
+    }
+    pub fn get_state_value(&self, field: &WebModelField) -> serde_json::Value {
+        self.serialize_state(field, serde_json::value::Serializer)
+            .unwrap()
+    }
+    pub fn serialize_state<S: Serializer>(
+        &self,
+        field: &WebModelField,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
         match field {
-            WebModelField::Ctx => JsValue::from_serde(&self.ctx).unwrap(),
+            WebModelField::Ctx => serialize_ctx(&self.ctx, serializer),
             WebModelField::ContinueWatchingPreview => {
-                serialize_continue_watching_preview(&self.continue_watching_preview)
+                serialize_continue_watching_preview(&self.continue_watching_preview, serializer)
+            }
+            WebModelField::Board => serialize_catalogs_with_extra(
+                &self.board,
+                &self.ctx,
+                Some(&board_layout(&self.ctx.profile.uid())),
+                serializer,
+            ),
+            WebModelField::Discover => serialize_discover(&self.discover, &self.ctx, serializer),
+            WebModelField::Library => {
+                serialize_library(&self.library, "library".to_owned(), serializer)
+            }
+            WebModelField::ContinueWatching => serialize_library(
+                &self.continue_watching,
+                "continuewatching".to_owned(),
+                serializer,
+            ),
+            WebModelField::Search => {
+                serialize_catalogs_with_extra(&self.search, &self.ctx, None, serializer)
+            }
+            WebModelField::MetaDetails => {
+                serialize_meta_details(&self.meta_details, &self.ctx, serializer)
             }
-            WebModelField::Board => serialize_catalogs_with_extra(&self.board, &self.ctx),
-            WebModelField::Discover => serialize_discover(&self.discover, &self.ctx),
-            WebModelField::Library => serialize_library(&self.library, "library".to_owned()),
-            WebModelField::ContinueWatching => {
-                serialize_library(&self.continue_watching, "continuewatching".to_owned())
+            WebModelField::RemoteAddons => {
+                serialize_remote_addons(&self.remote_addons, &self.ctx, serializer)
             }
-            WebModelField::Search => serialize_catalogs_with_extra(&self.search, &self.ctx),
-            WebModelField::MetaDetails => serialize_meta_details(&self.meta_details, &self.ctx),
-            WebModelField::RemoteAddons => serialize_remote_addons(&self.remote_addons, &self.ctx),
-            WebModelField::InstalledAddons => serialize_installed_addons(&self.installed_addons),
-            WebModelField::AddonDetails => JsValue::from_serde(&self.addon_details).unwrap(),
-            WebModelField::StreamingServer => JsValue::from_serde(&self.streaming_server).unwrap(),
-            WebModelField::Player => serialize_player(&self.player, &self.ctx),
+            WebModelField::InstalledAddons => {
+                serialize_installed_addons(&self.installed_addons, serializer)
+            }
+            WebModelField::AddonDetails => self.addon_details.serialize(serializer),
+            WebModelField::StreamingServer => self.streaming_server.serialize(serializer),
+            WebModelField::Player => serialize_player(&self.player, &self.ctx, serializer),
         }
     }
 }
+
+// Library writes of incognito playback are dropped by leaving the player without a library item
+fn detach_incognito_player(player: &mut Player, msg: &Msg) {
+    if incognito::is_library_write(msg) {
+        player.library_item = None;
+    };
+}
+
+pub fn mark_changed(fields: &[WebModelField]) {
+    CHANGED_FIELDS.with(|changed_fields| {
+        let mut changed_fields = changed_fields.borrow_mut();
+        for field in fields {
+            let field = mem::discriminant(field);
+            if !changed_fields.contains(&field) {
+                changed_fields.push(field);
+            };
+        }
+    });
+}
+
+// Ctx is updated by the derive itself, with nothing to report its changes, so it always counts
+pub fn take_changed_fields() -> Vec<Discriminant<WebModelField>> {
+    mark_changed(&[WebModelField::Ctx]);
+    CHANGED_FIELDS.with(|changed_fields| changed_fields.borrow_mut().drain(..).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct TestModel {
+        changes: bool,
+    }
+
+    impl UpdateWithCtx<WebEnv> for TestModel {
+        fn update(&mut self, _msg: &Msg, _ctx: &Ctx<WebEnv>) -> Effects {
+            if self.changes {
+                Effects::none()
+            } else {
+                Effects::none().unchanged()
+            }
+        }
+    }
+
+    fn update(model: &mut Tracked<TestModel>) {
+        let ctx = Ctx::<WebEnv>::new(Profile::default(), LibraryBucket::default());
+        UpdateWithCtx::<WebEnv>::update(model, &Msg::Action(Action::Unload), &ctx);
+    }
+
+    #[test]
+    fn only_changed_models_record_their_field() {
+        let mut board = Tracked::new(WebModelField::Board, TestModel { changes: true });
+        let mut search = Tracked::new(WebModelField::Search, TestModel { changes: false });
+        update(&mut board);
+        update(&mut search);
+        let changed_fields = take_changed_fields();
+        assert!(changed_fields.contains(&mem::discriminant(&WebModelField::Board)));
+        assert!(!changed_fields.contains(&mem::discriminant(&WebModelField::Search)));
+        assert!(!take_changed_fields().contains(&mem::discriminant(&WebModelField::Board)));
+    }
+
+    #[test]
+    fn ctx_always_counts_as_changed() {
+        assert_eq!(
+            take_changed_fields(),
+            vec![mem::discriminant(&WebModelField::Ctx)]
+        );
+    }
+
+    #[test]
+    fn unmounted_models_skip_their_updates_until_mounted() {
+        mounted_fields::mount(&WebModelField::Search);
+        let mut board = Tracked::new(WebModelField::Board, TestModel { changes: true });
+        update(&mut board);
+        assert!(board.stale);
+        assert!(!take_changed_fields().contains(&mem::discriminant(&WebModelField::Board)));
+        mounted_fields::mount(&WebModelField::Board);
+        update(&mut board);
+        assert!(!board.stale);
+        assert!(take_changed_fields().contains(&mem::discriminant(&WebModelField::Board)));
+    }
+
+    #[test]
+    fn before_update_runs_ahead_of_the_update() {
+        let mut player = Tracked::new(WebModelField::Player, TestModel { changes: false })
+            .with_before_update(|model, _| model.changes = true);
+        update(&mut player);
+        assert!(take_changed_fields().contains(&mem::discriminant(&WebModelField::Player)));
+    }
+}
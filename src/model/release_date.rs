@@ -0,0 +1,73 @@
+use crate::env::WebEnv;
+use chrono::{DateTime, NaiveDate, NaiveTime, Utc};
+use serde::Serialize;
+use stremio_core::runtime::Env;
+
+#[derive(Debug, Serialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub enum ReleasePrecision {
+    Date,
+    DateTime,
+}
+
+impl From<&DateTime<Utc>> for ReleasePrecision {
+    fn from(released: &DateTime<Utc>) -> Self {
+        // Addons that only know the air date send it as midnight UTC
+        if released.time() == NaiveTime::from_hms(0, 0, 0) {
+            ReleasePrecision::Date
+        } else {
+            ReleasePrecision::DateTime
+        }
+    }
+}
+
+pub fn is_upcoming(released: &DateTime<Utc>) -> bool {
+    match ReleasePrecision::from(released) {
+        ReleasePrecision::Date => released.naive_utc().date() > local_today(),
+        ReleasePrecision::DateTime => *released > WebEnv::now(),
+    }
+}
+
+pub fn is_released_today(released: &DateTime<Utc>) -> bool {
+    let released_date = match ReleasePrecision::from(released) {
+        ReleasePrecision::Date => released.naive_utc().date(),
+        ReleasePrecision::DateTime => released
+            .with_timezone(&WebEnv::timezone_offset())
+            .naive_local()
+            .date(),
+    };
+    released_date == local_today()
+}
+
+fn local_today() -> NaiveDate {
+    WebEnv::now()
+        .with_timezone(&WebEnv::timezone_offset())
+        .naive_local()
+        .date()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn midnight_utc_is_a_date() {
+        assert_eq!(
+            ReleasePrecision::from(&Utc.ymd(2020, 5, 1).and_hms(0, 0, 0)),
+            ReleasePrecision::Date
+        );
+    }
+
+    #[test]
+    fn any_other_time_is_a_date_time() {
+        assert_eq!(
+            ReleasePrecision::from(&Utc.ymd(2020, 5, 1).and_hms(20, 0, 0)),
+            ReleasePrecision::DateTime
+        );
+        assert_eq!(
+            ReleasePrecision::from(&Utc.ymd(2020, 5, 1).and_hms_milli(0, 0, 0, 1)),
+            ReleasePrecision::DateTime
+        );
+    }
+}
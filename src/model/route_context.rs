@@ -0,0 +1,87 @@
+use crate::model::deep_links::{
+    meta_details_url, search_url, AddonsDeepLinks, DiscoverDeepLinks, LibraryDeepLinks,
+};
+use crate::model::WebModel;
+use serde::Serialize;
+use serde_json::Value;
+use stremio_core::models::library_with_filters::Selected as LibrarySelected;
+
+const SEARCH_EXTRA_NAME: &str = "search";
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RouteContext {
+    pub route: &'static str,
+    pub url: String,
+    pub state: Value,
+}
+
+fn state<T: Serialize>(selected: &Option<T>) -> Value {
+    serde_json::to_value(selected).unwrap_or(Value::Null)
+}
+
+fn library_url(root: &str, selected: &Option<LibrarySelected>) -> String {
+    let root = root.to_owned();
+    match selected {
+        Some(selected) => LibraryDeepLinks::from((&root, &selected.request)).library,
+        None => LibraryDeepLinks::from(&root).library,
+    }
+}
+
+// Urls are the deep links of the route's current selection, so they match what the app navigated to
+pub fn route_context(model: &WebModel, route: &'static str) -> RouteContext {
+    let (url, state) = match route {
+        "board" => ("#/".to_owned(), Value::Null),
+        "discover" => (
+            model
+                .discover
+                .selected
+                .as_ref()
+                .map(|selected| DiscoverDeepLinks::from(&selected.request).discover)
+                .unwrap_or_else(|| "#/discover".to_owned()),
+            state(&model.discover.selected),
+        ),
+        "library" => (
+            library_url("library", &model.library.selected),
+            state(&model.library.selected),
+        ),
+        "continueWatching" => (
+            library_url("continuewatching", &model.continue_watching.selected),
+            state(&model.continue_watching.selected),
+        ),
+        "search" => (
+            search_url(model.search.selected.as_ref().and_then(|selected| {
+                selected
+                    .extra
+                    .iter()
+                    .find(|extra| extra.name == SEARCH_EXTRA_NAME)
+                    .map(|extra| &extra.value)
+            })),
+            state(&model.search.selected),
+        ),
+        "metaDetails" => (
+            model
+                .meta_details
+                .selected
+                .as_ref()
+                .map(|selected| {
+                    meta_details_url(&selected.meta_path, selected.stream_path.as_ref())
+                })
+                .unwrap_or_else(|| "#/metadetails".to_owned()),
+            state(&model.meta_details.selected),
+        ),
+        "remoteAddons" => (
+            model
+                .remote_addons
+                .selected
+                .as_ref()
+                .map(|selected| AddonsDeepLinks::from(&selected.request).addons)
+                .unwrap_or_else(|| "#/addons".to_owned()),
+            state(&model.remote_addons.selected),
+        ),
+        "addonDetails" => ("#/addons".to_owned(), state(&model.addon_details.selected)),
+        "player" => ("#/player".to_owned(), state(&model.player.selected)),
+        _ => (format!("#/{}", route), Value::Null),
+    };
+    RouteContext { route, url, state }
+}
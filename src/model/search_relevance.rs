@@ -0,0 +1,66 @@
+use serde::Serialize;
+use stremio_core::models::catalogs_with_extra::Selected;
+use stremio_core::types::resource::MetaItemPreview;
+
+const SEARCH_EXTRA_NAME: &str = "search";
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum MatchedField {
+    Title,
+    Description,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Relevance {
+    pub score: f64,
+    pub matched_field: Option<MatchedField>,
+}
+
+pub fn search_query(selected: &Option<Selected>) -> Option<String> {
+    selected
+        .as_ref()?
+        .extra
+        .iter()
+        .find(|extra_value| extra_value.name == SEARCH_EXTRA_NAME)
+        .map(|extra_value| extra_value.value.trim().to_lowercase())
+        .filter(|query| !query.is_empty())
+}
+
+// Previews carry no aliases or cast, so only the title and the description can be matched
+pub fn relevance(query: &str, meta_item: &MetaItemPreview) -> Relevance {
+    let title = meta_item.name.to_lowercase();
+    let words = query.split_whitespace().collect::<Vec<_>>();
+    let title_score = if title == query {
+        1.0
+    } else if title.starts_with(query) {
+        0.9
+    } else if title.contains(query) {
+        0.75
+    } else {
+        let matched_words = words.iter().filter(|word| title.contains(*word)).count();
+        0.6 * matched_words as f64 / words.len() as f64
+    };
+    if title_score > 0.0 {
+        return Relevance {
+            score: title_score,
+            matched_field: Some(MatchedField::Title),
+        };
+    };
+    let description_matched = meta_item.description.as_ref().map_or(false, |description| {
+        let description = description.to_lowercase();
+        words.iter().all(|word| description.contains(*word))
+    });
+    if description_matched {
+        Relevance {
+            score: 0.25,
+            matched_field: Some(MatchedField::Description),
+        }
+    } else {
+        Relevance {
+            score: 0.0,
+            matched_field: None,
+        }
+    }
+}
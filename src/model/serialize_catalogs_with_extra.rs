@@ -1,11 +1,18 @@
+use crate::addon_priorities;
+use crate::board_layout::{self, BoardLayout};
 use crate::env::WebEnv;
+use crate::model::catalog_extra::{request_declared_extra, DeclaredExtra};
 use crate::model::deep_links::{DiscoverDeepLinks, MetaItemDeepLinks};
-use serde::Serialize;
+use crate::model::empty_reason::{empty_reason, EmptyReason};
+use crate::model::search_relevance::{relevance, search_query, Relevance};
+use crate::model::series_status::{series_status, SeriesStatus};
+use itertools::Itertools;
+use serde::{Serialize, Serializer};
+use std::cmp::Reverse;
 use stremio_core::models::catalogs_with_extra::{CatalogsWithExtra, Selected};
 use stremio_core::models::common::{Loadable, ResourceError};
 use stremio_core::models::ctx::Ctx;
 use stremio_core::types::resource::PosterShape;
-use wasm_bindgen::JsValue;
 
 mod model {
     use super::*;
@@ -16,13 +23,19 @@ mod model {
         pub name: &'a String,
         pub poster: &'a Option<String>,
         pub poster_shape: &'a PosterShape,
+        pub series_status: Option<SeriesStatus>,
+        pub relevance: Option<Relevance>,
         pub deep_links: MetaItemDeepLinks,
     }
     #[derive(Serialize)]
     #[serde(rename_all = "camelCase")]
     pub struct ResourceLoadable<'a> {
+        pub id: String,
         pub title: String,
+        pub pinned: bool,
+        pub declared_extra: Option<DeclaredExtra<'a>>,
         pub content: Loadable<Vec<MetaItemPreview<'a>>, String>,
+        pub empty_reason: Option<EmptyReason>,
         pub deep_links: DiscoverDeepLinks,
     }
     #[derive(Serialize)]
@@ -33,11 +46,14 @@ mod model {
     }
 }
 
-pub fn serialize_catalogs_with_extra(
+pub fn serialize_catalogs_with_extra<S: Serializer>(
     catalogs_with_extra: &CatalogsWithExtra,
     ctx: &Ctx<WebEnv>,
-) -> JsValue {
-    JsValue::from_serde(&model::CatalogsWithExtra {
+    board_layout: Option<&BoardLayout>,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    let search_query = search_query(&catalogs_with_extra.selected);
+    model::CatalogsWithExtra {
         selected: &catalogs_with_extra.selected,
         catalogs: catalogs_with_extra
             .catalogs
@@ -52,11 +68,21 @@ pub fn serialize_catalogs_with_extra(
                     .find(|addon| addon.transport_url == catalog.request.base)
                     .map(|addon| (addon, catalog))
             })
-            .map(|(addon, catalog)| model::ResourceLoadable {
+            .map(|(addon, catalog)| (board_layout::row_id(&catalog.request), addon, catalog))
+            .sorted_by_key(|(row_id, addon, _)| {
+                (
+                    board_layout.map(|board_layout| board_layout.position(row_id)),
+                    Reverse(addon_priorities::priority(&addon.transport_url)),
+                )
+            })
+            .map(|(row_id, addon, catalog)| model::ResourceLoadable {
+                pinned: board_layout.map_or(false, |board_layout| board_layout.is_pinned(&row_id)),
+                id: row_id,
                 title: format!(
                     "{} - {} {}",
                     &addon.manifest.name, &catalog.request.path.id, &catalog.request.path.r#type
                 ),
+                declared_extra: request_declared_extra(&catalog.request, addon),
                 content: match &catalog.content {
                     Loadable::Ready(meta_items) => Loadable::Ready(
                         meta_items
@@ -66,6 +92,15 @@ pub fn serialize_catalogs_with_extra(
                                 name: &meta_item.name,
                                 poster: &meta_item.poster,
                                 poster_shape: &meta_items.first().unwrap().poster_shape,
+                                series_status: ctx
+                                    .library
+                                    .items
+                                    .get(&meta_item.id)
+                                    .filter(|library_item| !library_item.removed)
+                                    .and_then(series_status),
+                                relevance: search_query
+                                    .as_ref()
+                                    .map(|search_query| relevance(search_query, meta_item)),
                                 deep_links: MetaItemDeepLinks::from(meta_item),
                             })
                             .collect::<Vec<_>>(),
@@ -73,9 +108,13 @@ pub fn serialize_catalogs_with_extra(
                     Loadable::Loading => Loadable::Loading,
                     Loadable::Err(error) => Loadable::Err(error.to_string()),
                 },
+                empty_reason: match &catalog.content {
+                    Loadable::Err(error) => empty_reason(error, &catalog.request, addon),
+                    _ => None,
+                },
                 deep_links: DiscoverDeepLinks::from(&catalog.request),
             })
             .collect::<Vec<_>>(),
-    })
-    .unwrap()
+    }
+    .serialize(serializer)
 }
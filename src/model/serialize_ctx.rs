@@ -0,0 +1,48 @@
+use crate::account::{self, Account};
+use crate::analytics;
+use crate::env::WebEnv;
+use crate::episode_numbering::{self, EpisodeNumbering};
+use crate::errors_feed::{errors_feed, ErrorEntry};
+use crate::incognito;
+use crate::library_backup::{self, BackupSummary};
+use crate::library_recent::{self, RecentPolicy};
+use crate::settings_effect::{settings_effects, SettingEffect};
+use crate::watch_limits::{self, WatchLimitsSummary};
+use serde::{Serialize, Serializer};
+use std::collections::BTreeMap;
+use stremio_core::models::ctx::Ctx;
+
+mod model {
+    use super::*;
+    #[derive(Serialize)]
+    #[serde(rename_all = "camelCase")]
+    pub struct Ctx<'a> {
+        #[serde(flatten)]
+        pub ctx: &'a stremio_core::models::ctx::Ctx<WebEnv>,
+        pub account: Option<Account>,
+        pub incognito: bool,
+        pub analytics_enabled: bool,
+        pub settings_effects: BTreeMap<String, SettingEffect>,
+        pub errors_feed: Vec<ErrorEntry>,
+        pub library_recent_policy: RecentPolicy,
+        pub episode_numbering: EpisodeNumbering,
+        pub library_backup: BackupSummary,
+        pub watch_limits: Option<WatchLimitsSummary>,
+    }
+}
+
+pub fn serialize_ctx<S: Serializer>(ctx: &Ctx<WebEnv>, serializer: S) -> Result<S::Ok, S::Error> {
+    model::Ctx {
+        ctx,
+        account: account::account(&ctx.profile.uid()),
+        incognito: incognito::is_enabled(),
+        analytics_enabled: analytics::is_allowed(),
+        settings_effects: settings_effects(&ctx.profile.settings),
+        errors_feed: errors_feed(),
+        library_recent_policy: library_recent::policy(),
+        episode_numbering: episode_numbering::numbering(),
+        library_backup: library_backup::summary(),
+        watch_limits: watch_limits::summary(),
+    }
+    .serialize(serializer)
+}
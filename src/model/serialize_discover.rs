@@ -1,6 +1,10 @@
 use crate::env::WebEnv;
+use crate::model::catalog_extra::{request_declared_extra, DeclaredExtra};
 use crate::model::deep_links::{DiscoverDeepLinks, MetaItemDeepLinks, StreamDeepLinks};
-use serde::Serialize;
+use crate::model::empty_reason::{empty_reason, EmptyReason};
+use itertools::Itertools;
+use serde::{Serialize, Serializer};
+use std::collections::HashMap;
 use stremio_core::constants::{CATALOG_PAGE_SIZE, SKIP_EXTRA_NAME};
 use stremio_core::models::catalog_with_filters::{
     CatalogWithFilters, Selected as CatalogWithFiltersSelected,
@@ -9,7 +13,6 @@ use stremio_core::models::common::Loadable;
 use stremio_core::models::ctx::Ctx;
 use stremio_core::types::addon::ResourceRequest;
 use stremio_core::types::resource::MetaItemPreview;
-use wasm_bindgen::JsValue;
 
 mod model {
     use super::*;
@@ -43,6 +46,7 @@ mod model {
         pub id: &'a String,
         pub name: &'a String,
         pub addon: DescriptorPreview<'a>,
+        pub declared_extra: Option<DeclaredExtra<'a>>,
         pub selected: &'a bool,
         pub deep_links: DiscoverDeepLinks,
     }
@@ -51,6 +55,7 @@ mod model {
     pub struct SelectableType<'a> {
         pub r#type: &'a String,
         pub selected: &'a bool,
+        pub addons_count: usize,
         pub deep_links: DiscoverDeepLinks,
     }
     #[derive(Serialize)]
@@ -87,6 +92,7 @@ mod model {
     #[serde(rename_all = "camelCase")]
     pub struct ResourceLoadable<'a> {
         pub content: Loadable<Vec<MetaItemPreview<'a>>, String>,
+        pub empty_reason: Option<EmptyReason>,
         pub installed: bool,
     }
     #[derive(Serialize)]
@@ -100,11 +106,29 @@ mod model {
     }
 }
 
-pub fn serialize_discover(
+pub fn serialize_discover<S: Serializer>(
     discover: &CatalogWithFilters<MetaItemPreview>,
     ctx: &Ctx<WebEnv>,
-) -> JsValue {
-    JsValue::from_serde(&model::CatalogWithFilters {
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    let data_saver = WebEnv::data_saver();
+    let addons_count_by_type = ctx
+        .profile
+        .addons
+        .iter()
+        .flat_map(|addon| {
+            addon
+                .manifest
+                .catalogs
+                .iter()
+                .map(|catalog| &catalog.r#type)
+                .unique()
+        })
+        .fold(HashMap::new(), |mut addons_count_by_type, r#type| {
+            *addons_count_by_type.entry(r#type).or_insert(0) += 1;
+            addons_count_by_type
+        });
+    model::CatalogWithFilters {
         selected: &discover.selected,
         selectable: model::Selectable {
             types: discover
@@ -114,6 +138,10 @@ pub fn serialize_discover(
                 .map(|selectable_type| model::SelectableType {
                     r#type: &selectable_type.r#type,
                     selected: &selectable_type.selected,
+                    addons_count: addons_count_by_type
+                        .get(&selectable_type.r#type)
+                        .copied()
+                        .unwrap_or_default(),
                     deep_links: DiscoverDeepLinks::from(&selectable_type.request),
                 })
                 .collect(),
@@ -136,6 +164,7 @@ pub fn serialize_discover(
                             name: &addon.manifest.name,
                         },
                     },
+                    declared_extra: request_declared_extra(&selectable_catalog.request, addon),
                     selected: &selectable_catalog.selected,
                     deep_links: DiscoverDeepLinks::from(&selectable_catalog.request),
                 })
@@ -182,6 +211,7 @@ pub fn serialize_discover(
                                 trailer_streams: meta_item
                                     .trailer_streams
                                     .iter()
+                                    .filter(|_| !data_saver.enabled)
                                     .map(|stream| model::Stream {
                                         stream,
                                         deep_links: StreamDeepLinks::from(stream),
@@ -200,6 +230,15 @@ pub fn serialize_discover(
                     Loadable::Loading => Loadable::Loading,
                     Loadable::Err(error) => Loadable::Err(error.to_string()),
                 },
+                empty_reason: match &catalog.content {
+                    Loadable::Err(error) => ctx
+                        .profile
+                        .addons
+                        .iter()
+                        .find(|addon| addon.transport_url == catalog.request.base)
+                        .and_then(|addon| empty_reason(error, &catalog.request, addon)),
+                    _ => None,
+                },
                 installed: ctx
                     .profile
                     .addons
@@ -223,6 +262,6 @@ pub fn serialize_discover(
                     .map(|skip| 1 + skip / CATALOG_PAGE_SIZE as u32)
             })
             .unwrap_or(1),
-    })
-    .unwrap()
+    }
+    .serialize(serializer)
 }
@@ -0,0 +1,138 @@
+use crate::board_layout::board_layout;
+use crate::env::WebEnv;
+use crate::home_layout::{HomeLayout, HomeSection};
+use crate::js_value::to_js_value;
+use crate::model::deep_links::LibraryItemDeepLinks;
+use crate::model::{serialize_catalogs_with_extra, serialize_continue_watching_preview};
+use chrono::{DateTime, Duration, Utc};
+use serde::Serialize;
+use stremio_core::models::catalogs_with_extra::CatalogsWithExtra;
+use stremio_core::models::continue_watching_preview::ContinueWatchingPreview;
+use stremio_core::models::ctx::Ctx;
+use stremio_core::runtime::Env;
+use stremio_core::types::resource::PosterShape;
+use wasm_bindgen::JsValue;
+
+mod model {
+    use super::*;
+    #[derive(Serialize)]
+    #[serde(rename_all = "camelCase")]
+    pub struct CalendarItem<'a> {
+        #[serde(rename = "_id")]
+        pub id: &'a String,
+        pub name: &'a String,
+        pub r#type: &'a String,
+        pub poster: &'a Option<String>,
+        pub poster_shape: &'a PosterShape,
+        pub released: &'a DateTime<Utc>,
+        pub deep_links: LibraryItemDeepLinks,
+    }
+}
+
+// Sections reuse the board and continue watching serializers, so they look the same as on their own
+pub fn serialize_home(
+    board: &CatalogsWithExtra,
+    continue_watching_preview: &ContinueWatchingPreview,
+    ctx: &Ctx<WebEnv>,
+    home_layout: &HomeLayout,
+) -> JsValue {
+    let board = to_js_value(
+        &serialize_catalogs_with_extra(
+            board,
+            ctx,
+            Some(&board_layout(&ctx.profile.uid())),
+            serde_json::value::Serializer,
+        )
+        .unwrap(),
+    )
+    .unwrap();
+    let board_rows = js_sys::Reflect::get(&board, &JsValue::from_str("catalogs"))
+        .map(|catalogs| js_sys::Array::from(&catalogs).iter().collect::<Vec<_>>())
+        .unwrap_or_default();
+    let row_id = |row: &JsValue| {
+        js_sys::Reflect::get(row, &JsValue::from_str("id"))
+            .ok()
+            .and_then(|id| id.as_string())
+    };
+    let catalog_ids = home_layout.catalog_ids();
+    let sections = js_sys::Array::new();
+    for section in home_layout.sections.iter() {
+        let content = match section {
+            HomeSection::ContinueWatching => Some(
+                to_js_value(
+                    &serialize_continue_watching_preview(
+                        continue_watching_preview,
+                        serde_json::value::Serializer,
+                    )
+                    .unwrap(),
+                )
+                .unwrap(),
+            ),
+            HomeSection::Catalog { id } => board_rows
+                .iter()
+                .find(|row| row_id(row).as_ref() == Some(id))
+                .cloned(),
+            HomeSection::Board => Some(
+                board_rows
+                    .iter()
+                    .filter(|row| row_id(row).map_or(true, |id| !catalog_ids.contains(&&id)))
+                    .collect::<js_sys::Array>()
+                    .into(),
+            ),
+            HomeSection::Calendar { days } => {
+                Some(serialize_calendar(ctx, Duration::days(*days as i64)))
+            }
+        };
+        // Rows of uninstalled addons are left out instead of showing up empty
+        if let Some(content) = content {
+            let home_section = js_sys::Object::new();
+            js_sys::Reflect::set(
+                &home_section,
+                &JsValue::from_str("section"),
+                &to_js_value(section).unwrap(),
+            )
+            .expect("home section build failed");
+            js_sys::Reflect::set(&home_section, &JsValue::from_str("content"), &content)
+                .expect("home section build failed");
+            sections.push(&home_section);
+        };
+    }
+    sections.into()
+}
+
+// Series in the library with an episode out since yesterday or due within the window
+fn serialize_calendar(ctx: &Ctx<WebEnv>, window: Duration) -> JsValue {
+    let now = WebEnv::now();
+    let mut calendar_items = ctx
+        .library
+        .items
+        .values()
+        .filter(|library_item| !library_item.removed)
+        .filter_map(|library_item| {
+            library_item
+                .state
+                .last_vid_released
+                .as_ref()
+                .filter(|released| {
+                    **released >= now - Duration::days(1) && **released <= now + window
+                })
+                .map(|released| (library_item, released))
+        })
+        .collect::<Vec<_>>();
+    calendar_items.sort_by_key(|(_, released)| **released);
+    to_js_value(
+        &calendar_items
+            .into_iter()
+            .map(|(library_item, released)| model::CalendarItem {
+                id: &library_item.id,
+                name: &library_item.name,
+                r#type: &library_item.r#type,
+                poster: &library_item.poster,
+                poster_shape: &library_item.poster_shape,
+                released,
+                deep_links: LibraryItemDeepLinks::from(library_item),
+            })
+            .collect::<Vec<_>>(),
+    )
+    .unwrap()
+}
@@ -1,9 +1,12 @@
+use crate::addon_mirrors;
+use crate::addon_priorities;
 use crate::model::deep_links::AddonsDeepLinks;
-use serde::Serialize;
+use crate::model::transport_protocol::Transport;
+use serde::{Serialize, Serializer};
 use stremio_core::models::installed_addons_with_filters::{
     InstalledAddonsRequest, InstalledAddonsWithFilters, Selected,
 };
-use wasm_bindgen::JsValue;
+use url::Url;
 
 mod model {
     use super::*;
@@ -13,6 +16,10 @@ mod model {
         #[serde(flatten)]
         pub addon: &'a stremio_core::types::addon::DescriptorPreview,
         pub installed: bool,
+        pub transport: Transport,
+        pub mirrors: Vec<Url>,
+        pub active_transport_url: Url,
+        pub priority: i32,
     }
     #[derive(Serialize)]
     #[serde(rename_all = "camelCase")]
@@ -43,8 +50,11 @@ mod model {
     }
 }
 
-pub fn serialize_installed_addons(installed_addons: &InstalledAddonsWithFilters) -> JsValue {
-    JsValue::from_serde(&model::InstalledAddonsWithFilters {
+pub fn serialize_installed_addons<S: Serializer>(
+    installed_addons: &InstalledAddonsWithFilters,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    model::InstalledAddonsWithFilters {
         selected: &installed_addons.selected,
         selectable: model::Selectable {
             types: installed_addons
@@ -68,9 +78,13 @@ pub fn serialize_installed_addons(installed_addons: &InstalledAddonsWithFilters)
             .iter()
             .map(|addon| model::DescriptorPreview {
                 addon,
+                transport: Transport::from(&addon.transport_url),
                 installed: true,
+                mirrors: addon_mirrors::mirrors(&addon.transport_url),
+                active_transport_url: addon_mirrors::active_transport_url(&addon.transport_url),
+                priority: addon_priorities::priority(&addon.transport_url),
             })
             .collect(),
-    })
-    .unwrap()
+    }
+    .serialize(serializer)
 }
@@ -1,8 +1,12 @@
 use crate::model::deep_links::{LibraryDeepLinks, LibraryItemDeepLinks};
-use serde::Serialize;
+use crate::model::series_status::{series_status, SeriesStatus};
+use crate::model::time_remaining::minutes_remaining;
+use crate::pending_removal;
+use crate::ui_state;
+use serde::{Serialize, Serializer};
+use std::collections::HashMap;
 use stremio_core::models::library_with_filters::{LibraryWithFilters, Selected, Sort};
 use stremio_core::types::resource::PosterShape;
-use wasm_bindgen::JsValue;
 
 mod model {
     use super::*;
@@ -16,6 +20,10 @@ mod model {
         pub poster: &'a Option<String>,
         pub poster_shape: &'a PosterShape,
         pub progress: f64,
+        pub minutes_remaining: Option<u64>,
+        pub series_status: Option<SeriesStatus>,
+        pub pending_removal: bool,
+        pub ui_state: HashMap<String, serde_json::Value>,
         pub deep_links: LibraryItemDeepLinks,
     }
     #[derive(Serialize)]
@@ -53,8 +61,12 @@ mod model {
     }
 }
 
-pub fn serialize_library<F>(library: &LibraryWithFilters<F>, root: String) -> JsValue {
-    JsValue::from_serde(&model::LibraryWithFilters {
+pub fn serialize_library<F, S: Serializer>(
+    library: &LibraryWithFilters<F>,
+    root: String,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    model::LibraryWithFilters {
         selected: &library.selected,
         selectable: model::Selectable {
             types: library
@@ -106,9 +118,13 @@ pub fn serialize_library<F>(library: &LibraryWithFilters<F>, root: String) -> Js
                 } else {
                     0.0
                 },
+                minutes_remaining: minutes_remaining(&library_item.state),
+                series_status: series_status(library_item),
+                pending_removal: pending_removal::is_pending(&library_item.id),
+                ui_state: ui_state::get(&library_item.id),
                 deep_links: LibraryItemDeepLinks::from(library_item),
             })
             .collect(),
-    })
-    .unwrap()
+    }
+    .serialize(serializer)
 }
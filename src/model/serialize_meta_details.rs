@@ -1,16 +1,34 @@
+use crate::addon_priorities;
+use crate::artwork::{self, Artwork};
 use crate::env::WebEnv;
-use crate::model::deep_links::{MetaItemDeepLinks, StreamDeepLinks, VideoDeepLinks};
+use crate::episode_numbering::{absolute_episodes, episode_label};
+use crate::media_support::{self, Playable};
+use crate::model::classification::{
+    certification_minimum_age, genre_key, CERTIFICATION_LINK_CATEGORY, GENRE_LINK_CATEGORY,
+};
+use crate::model::deep_links::{
+    meta_details_share_url, MetaItemDeepLinks, StreamDeepLinks, VideoDeepLinks,
+};
+use crate::model::release_date::{self, ReleasePrecision};
+use crate::model::time_remaining::minutes_remaining;
+use crate::pending_removal;
+use crate::ratings::{self, Rating};
+use crate::stream_expiry;
+use crate::stream_wait::{self, WaitTier};
+use crate::ui_state;
+use chrono::{DateTime, Utc};
 use either::Either;
 use itertools::Itertools;
-use serde::Serialize;
+use serde::{Serialize, Serializer};
+use std::cmp::Reverse;
+use std::collections::HashMap;
 use std::iter;
-use stremio_core::constants::META_RESOURCE_NAME;
+use stremio_core::constants::{META_RESOURCE_NAME, OFFICIAL_ADDONS};
 use stremio_core::models::common::{Loadable, ResourceError, ResourceLoadable};
 use stremio_core::models::ctx::Ctx;
 use stremio_core::models::meta_details::{MetaDetails, Selected as MetaDetailsSelected};
-use stremio_core::runtime::Env;
+use stremio_core::types::resource::SeriesInfo;
 use url::Url;
-use wasm_bindgen::JsValue;
 
 mod model {
     use super::*;
@@ -32,27 +50,72 @@ mod model {
     pub struct Stream<'a> {
         #[serde(flatten)]
         pub stream: &'a stremio_core::types::resource::Stream,
+        pub expires_at: Option<DateTime<Utc>>,
+        pub expired: bool,
+        pub playable: Playable,
+        pub wait_tier: Option<WaitTier>,
         pub deep_links: StreamDeepLinks,
     }
     #[derive(Serialize)]
     #[serde(rename_all = "camelCase")]
+    pub struct VideoPreview<'a> {
+        pub id: &'a String,
+        pub title: &'a String,
+        pub released: &'a Option<DateTime<Utc>>,
+        #[serde(flatten)]
+        pub series_info: &'a Option<SeriesInfo>,
+        pub absolute_episode: Option<u32>,
+    }
+    #[derive(Serialize)]
+    #[serde(rename_all = "camelCase")]
     pub struct Video<'a> {
         #[serde(flatten)]
-        pub video: &'a stremio_core::types::resource::Video,
+        pub video: VideoPreview<'a>,
         pub upcomming: bool,
+        pub airs_today: bool,
+        pub release_precision: Option<ReleasePrecision>,
         pub watched: bool,
         pub progress: Option<u32>,
+        pub minutes_remaining: Option<u64>,
         pub scheduled: bool,
         pub deep_links: VideoDeepLinks,
     }
     #[derive(Serialize)]
     #[serde(rename_all = "camelCase")]
+    pub struct Genre<'a> {
+        pub name: &'a String,
+        pub key: Option<&'static str>,
+    }
+    #[derive(Serialize)]
+    #[serde(rename_all = "camelCase")]
+    pub struct Certification<'a> {
+        pub name: &'a String,
+        pub minimum_age: Option<u8>,
+    }
+    #[derive(Serialize)]
+    #[serde(rename_all = "camelCase")]
+    pub struct Share<'a> {
+        pub url: String,
+        pub title: &'a String,
+        pub description: &'a Option<String>,
+        pub poster: &'a Option<String>,
+    }
+    #[derive(Serialize)]
+    #[serde(rename_all = "camelCase")]
     pub struct MetaItem<'a> {
         #[serde(flatten)]
         pub meta_item: &'a stremio_core::types::resource::MetaItem,
         pub videos: Vec<Video<'a>>,
         pub trailer_streams: Vec<Stream<'a>>,
+        pub genres: Vec<Genre<'a>>,
+        pub certification: Option<Certification<'a>>,
         pub in_library: bool,
+        pub pending_removal: bool,
+        pub rating: Option<Rating>,
+        pub ui_state: HashMap<String, serde_json::Value>,
+        pub artwork: Artwork,
+        pub minutes_remaining: Option<u64>,
+        pub share: Share<'a>,
         pub deep_links: MetaItemDeepLinks,
     }
     #[derive(Serialize)]
@@ -63,6 +126,13 @@ mod model {
     }
     #[derive(Serialize)]
     #[serde(rename_all = "camelCase")]
+    pub struct StreamsSummary<'a> {
+        pub count: usize,
+        pub top_stream: Option<Stream<'a>>,
+        pub addon: DescriptorPreview<'a>,
+    }
+    #[derive(Serialize)]
+    #[serde(rename_all = "camelCase")]
     pub struct MetaExtension<'a> {
         pub url: &'a Url,
         pub name: &'a String,
@@ -74,16 +144,23 @@ mod model {
         pub selected: &'a Option<MetaDetailsSelected>,
         pub meta_item: Option<ResourceLoadable<'a, MetaItem<'a>>>,
         pub streams: Vec<ResourceLoadable<'a, Vec<Stream<'a>>>>,
+        pub streams_summaries: Vec<StreamsSummary<'a>>,
+        pub addon_suggestions: Vec<DescriptorPreview<'a>>,
         pub meta_extensions: Vec<MetaExtension<'a>>,
         pub title: Option<String>,
     }
 }
 
-pub fn serialize_meta_details(meta_details: &MetaDetails, ctx: &Ctx<WebEnv>) -> JsValue {
+pub fn serialize_meta_details<S: Serializer>(
+    meta_details: &MetaDetails,
+    ctx: &Ctx<WebEnv>,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
     let meta_item = meta_details
         .meta_items
         .iter()
-        .find(|meta_item| meta_item.content.is_ready())
+        .filter(|meta_item| meta_item.content.is_ready())
+        .min_by_key(|meta_item| Reverse(addon_priorities::priority(&meta_item.request.base)))
         .or_else(|| {
             if meta_details
                 .meta_items
@@ -98,7 +175,8 @@ pub fn serialize_meta_details(meta_details: &MetaDetails, ctx: &Ctx<WebEnv>) ->
                     .find(|catalog| catalog.content.is_loading())
             }
         });
-    JsValue::from_serde(&model::MetaDetails {
+    let data_saver = WebEnv::data_saver();
+    model::MetaDetails {
         selected: &meta_details.selected,
         meta_item: meta_item
             .and_then(|meta_item| {
@@ -115,36 +193,104 @@ pub fn serialize_meta_details(meta_details: &MetaDetails, ctx: &Ctx<WebEnv>) ->
                         content: Loadable::Ready(meta_item),
                     } => Loadable::Ready(model::MetaItem {
                         meta_item,
-                        videos: meta_item
-                            .videos
-                            .iter()
-                            .map(|video| model::Video {
-                                video,
-                                upcomming: meta_item.behavior_hints.has_scheduled_videos
-                                    && meta_item
+                        videos: {
+                            let absolute_episodes = absolute_episodes(&meta_item.videos);
+                            meta_item
+                                .videos
+                                .iter()
+                                .map(|video| model::Video {
+                                    video: model::VideoPreview {
+                                        id: &video.id,
+                                        title: &video.title,
+                                        released: &video.released,
+                                        series_info: &video.series_info,
+                                        absolute_episode: absolute_episodes
+                                            .get(video.id.as_str())
+                                            .copied(),
+                                    },
+                                    upcomming: meta_item.behavior_hints.has_scheduled_videos
+                                        && meta_item
+                                            .released
+                                            .as_ref()
+                                            .map(release_date::is_upcoming)
+                                            .unwrap_or(true),
+                                    airs_today: video
                                         .released
-                                        .map(|released| released > WebEnv::now())
-                                        .unwrap_or(true),
-                                watched: false, // TODO use library
-                                progress: None, // TODO use library,
-                                scheduled: meta_item.behavior_hints.has_scheduled_videos,
-                                deep_links: VideoDeepLinks::from((video, request)),
-                            })
-                            .collect::<Vec<_>>(),
+                                        .as_ref()
+                                        .map(release_date::is_released_today)
+                                        .unwrap_or_default(),
+                                    release_precision: video
+                                        .released
+                                        .as_ref()
+                                        .map(ReleasePrecision::from),
+                                    watched: false, // TODO use library
+                                    progress: None, // TODO use library,
+                                    minutes_remaining: ctx
+                                        .library
+                                        .items
+                                        .get(&meta_item.id)
+                                        .filter(|library_item| {
+                                            library_item.state.video_id.as_ref() == Some(&video.id)
+                                        })
+                                        .and_then(|library_item| {
+                                            minutes_remaining(&library_item.state)
+                                        }),
+                                    scheduled: meta_item.behavior_hints.has_scheduled_videos,
+                                    deep_links: VideoDeepLinks::from((video, request)),
+                                })
+                                .collect::<Vec<_>>()
+                        },
                         trailer_streams: meta_item
                             .trailer_streams
                             .iter()
+                            .filter(|_| !data_saver.enabled)
                             .map(|stream| model::Stream {
                                 stream,
+                                expires_at: stream_expiry::expires_at(stream),
+                                expired: stream_expiry::is_expired(stream),
+                                playable: media_support::playable(stream),
+                                wait_tier: stream_wait::wait_tier(stream, None),
                                 deep_links: StreamDeepLinks::from(stream),
                             })
                             .collect::<Vec<_>>(),
+                        genres: meta_item
+                            .links
+                            .iter()
+                            .filter(|link| link.category == GENRE_LINK_CATEGORY)
+                            .map(|link| model::Genre {
+                                name: &link.name,
+                                key: genre_key(&link.name),
+                            })
+                            .collect::<Vec<_>>(),
+                        certification: meta_item
+                            .links
+                            .iter()
+                            .find(|link| link.category == CERTIFICATION_LINK_CATEGORY)
+                            .map(|link| model::Certification {
+                                name: &link.name,
+                                minimum_age: certification_minimum_age(&link.name),
+                            }),
                         in_library: ctx
                             .library
                             .items
                             .get(&meta_item.id)
                             .map(|library_item| !library_item.removed)
                             .unwrap_or_default(),
+                        pending_removal: pending_removal::is_pending(&meta_item.id),
+                        rating: ratings::rating(&ctx.profile.uid(), &meta_item.id),
+                        ui_state: ui_state::get(&meta_item.id),
+                        artwork: artwork::artwork(meta_item),
+                        minutes_remaining: ctx
+                            .library
+                            .items
+                            .get(&meta_item.id)
+                            .and_then(|library_item| minutes_remaining(&library_item.state)),
+                        share: model::Share {
+                            url: meta_details_share_url(&meta_item.r#type, &meta_item.id),
+                            title: &meta_item.name,
+                            description: &meta_item.description,
+                            poster: &meta_item.poster,
+                        },
                         deep_links: MetaItemDeepLinks::from(meta_item),
                     }),
                     ResourceLoadable {
@@ -175,6 +321,7 @@ pub fn serialize_meta_details(meta_details: &MetaDetails, ctx: &Ctx<WebEnv>) ->
                     .find(|addon| addon.transport_url == streams.request.base)
                     .map(|addon| (streams, addon))
             })
+            .sorted_by_key(|(_, addon)| Reverse(addon_priorities::priority(&addon.transport_url)))
             .map(|(streams, addon)| model::ResourceLoadable {
                 content: match streams {
                     ResourceLoadable {
@@ -185,6 +332,10 @@ pub fn serialize_meta_details(meta_details: &MetaDetails, ctx: &Ctx<WebEnv>) ->
                             .iter()
                             .map(|stream| model::Stream {
                                 stream,
+                                expires_at: stream_expiry::expires_at(stream),
+                                expired: stream_expiry::is_expired(stream),
+                                playable: media_support::playable(stream),
+                                wait_tier: stream_wait::wait_tier(stream, Some(&request.base)),
                                 deep_links: meta_item.map_or_else(
                                     || StreamDeepLinks::from(stream),
                                     |meta_item| {
@@ -213,6 +364,85 @@ pub fn serialize_meta_details(meta_details: &MetaDetails, ctx: &Ctx<WebEnv>) ->
                 },
             })
             .collect::<Vec<_>>(),
+        streams_summaries: meta_details
+            .streams
+            .iter()
+            .filter_map(|streams| {
+                ctx.profile
+                    .addons
+                    .iter()
+                    .find(|addon| addon.transport_url == streams.request.base)
+                    .map(|addon| (streams, addon))
+            })
+            .sorted_by_key(|(_, addon)| Reverse(addon_priorities::priority(&addon.transport_url)))
+            .filter_map(|(streams, addon)| match streams {
+                ResourceLoadable {
+                    request,
+                    content: Loadable::Ready(streams),
+                } => Some(model::StreamsSummary {
+                    count: streams.len(),
+                    top_stream: streams.first().map(|stream| model::Stream {
+                        stream,
+                        expires_at: stream_expiry::expires_at(stream),
+                        expired: stream_expiry::is_expired(stream),
+                        playable: media_support::playable(stream),
+                        wait_tier: stream_wait::wait_tier(stream, Some(&request.base)),
+                        deep_links: meta_item.map_or_else(
+                            || StreamDeepLinks::from(stream),
+                            |meta_item| {
+                                StreamDeepLinks::from((stream, request, &meta_item.request))
+                            },
+                        ),
+                    }),
+                    addon: model::DescriptorPreview {
+                        transport_url: &addon.transport_url,
+                        manifest: model::ManifestPreview {
+                            id: &addon.manifest.id,
+                            name: &addon.manifest.name,
+                            logo: &addon.manifest.logo,
+                        },
+                    },
+                }),
+                _ => None,
+            })
+            .collect::<Vec<_>>(),
+        addon_suggestions: meta_details
+            .selected
+            .as_ref()
+            .and_then(|selected| selected.stream_path.as_ref())
+            .filter(|_| {
+                meta_item.map_or(false, |meta_item| meta_item.content.is_ready())
+                    && meta_details
+                        .streams
+                        .iter()
+                        .all(|streams| match &streams.content {
+                            Loadable::Ready(streams) => streams.is_empty(),
+                            Loadable::Loading => false,
+                            Loadable::Err(_) => true,
+                        })
+            })
+            .map(|stream_path| {
+                OFFICIAL_ADDONS
+                    .iter()
+                    .filter(|addon| {
+                        addon.manifest.is_resource_supported(stream_path)
+                            && ctx
+                                .profile
+                                .addons
+                                .iter()
+                                .all(|installed| installed.transport_url != addon.transport_url)
+                    })
+                    .map(|addon| model::DescriptorPreview {
+                        transport_url: &addon.transport_url,
+                        manifest: model::ManifestPreview {
+                            id: &addon.manifest.id,
+                            name: &addon.manifest.name,
+                            logo: &addon.manifest.logo,
+                        },
+                    })
+                    .collect::<Vec<_>>()
+            })
+            .unwrap_or_default(),
         meta_extensions: meta_details
             .meta_items
             .iter()
@@ -273,11 +503,15 @@ pub fn serialize_meta_details(meta_details: &MetaDetails, ctx: &Ctx<WebEnv>) ->
                             Some(video) if meta_item.behavior_hints.default_video_id.is_none() => {
                                 match &video.series_info {
                                     Some(series_info) => Some(format!(
-                                        "{} - {} ({}x{})",
+                                        "{} - {} ({})",
                                         &meta_item.name,
                                         &video.title,
-                                        &series_info.season,
-                                        &series_info.episode
+                                        episode_label(
+                                            series_info,
+                                            absolute_episodes(&meta_item.videos)
+                                                .get(video.id.as_str())
+                                                .copied()
+                                        )
                                     )),
                                     _ => Some(format!("{} - {}", &meta_item.name, &video.title)),
                                 }
@@ -287,6 +521,6 @@ pub fn serialize_meta_details(meta_details: &MetaDetails, ctx: &Ctx<WebEnv>) ->
                     })
                     .unwrap_or_else(|| meta_item.name.to_owned())
             }),
-    })
-    .unwrap()
+    }
+    .serialize(serializer)
 }
@@ -1,13 +1,28 @@
 use crate::env::WebEnv;
+use crate::episode_numbering::{absolute_episodes, episode_label};
+use crate::external_subtitles::external_subtitles;
 use crate::model::deep_links::VideoDeepLinks;
+use crate::model::episode_navigation::episode_navigation;
+use crate::model::release_date::{self, ReleasePrecision};
+use crate::model::time_remaining::is_resumable;
+use crate::play_queue;
+use crate::stream_expiry;
+use crate::torrent_files::{self, FileResolution};
+use crate::watch_limits::{self, LimitReason};
+use itertools::Itertools;
 use semver::Version;
-use serde::Serialize;
+use serde::{Serialize, Serializer};
+use std::collections::HashMap;
 use stremio_core::models::common::{Loadable, ResourceLoadable};
 use stremio_core::models::ctx::Ctx;
 use stremio_core::models::player::{Player, Selected};
-use stremio_core::runtime::Env;
+use stremio_core::types::addon::ResourceRequest;
 use url::Url;
-use wasm_bindgen::JsValue;
+
+const STREAM_SUBTITLES_ORIGIN: &str = "Stream";
+const EXTERNAL_SUBTITLES_ORIGIN: &str = "External";
+// Remote progress within a minute of the local one isn't worth interrupting playback for
+const PROGRESS_CONFLICT_THRESHOLD: u64 = 60_000;
 
 mod model {
     use super::*;
@@ -34,6 +49,8 @@ mod model {
         #[serde(flatten)]
         pub video: &'a stremio_core::types::resource::Video,
         pub upcomming: bool,
+        pub airs_today: bool,
+        pub release_precision: Option<ReleasePrecision>,
         pub watched: bool,
         pub progress: Option<u32>,
         pub scheduled: bool,
@@ -51,7 +68,7 @@ mod model {
     pub struct Subtitles<'a> {
         #[serde(flatten)]
         pub subtitles: &'a stremio_core::types::resource::Subtitles,
-        pub origin: &'a String,
+        pub origin: &'a str,
     }
     #[derive(Serialize)]
     #[serde(rename_all = "camelCase")]
@@ -66,23 +83,108 @@ mod model {
         #[serde(rename = "_id")]
         pub id: &'a String,
         pub state: LibraryItemState<'a>,
+        pub resumable: bool,
+    }
+    #[derive(Serialize)]
+    #[serde(rename_all = "camelCase")]
+    pub struct PlayQueueItem<'a> {
+        pub id: &'a String,
+        pub title: &'a String,
+        pub thumbnail: &'a Option<String>,
+        pub deep_links: VideoDeepLinks,
+    }
+    #[derive(Serialize)]
+    #[serde(rename_all = "camelCase")]
+    pub struct PlayQueue<'a> {
+        pub videos: Vec<PlayQueueItem<'a>>,
+        pub current_index: Option<usize>,
+        pub auto_advance: bool,
+    }
+    #[derive(Serialize)]
+    #[serde(rename_all = "camelCase")]
+    pub struct EpisodeLink<'a> {
+        pub id: &'a String,
+        pub title: &'a String,
+        pub season: Option<u32>,
+        pub episode: Option<u32>,
+        pub absolute_episode: Option<u32>,
+        pub deep_links: VideoDeepLinks,
+    }
+    #[derive(Serialize)]
+    #[serde(rename_all = "camelCase")]
+    pub struct EpisodeNavigation<'a> {
+        pub season: u32,
+        pub seasons: Vec<u32>,
+        pub first_in_season: bool,
+        pub last_in_season: bool,
+        pub previous: Option<EpisodeLink<'a>>,
+        pub next: Option<EpisodeLink<'a>>,
+        pub next_season: Option<EpisodeLink<'a>>,
+    }
+    #[derive(Serialize)]
+    #[serde(rename_all = "camelCase")]
+    pub struct ProgressConflict<'a> {
+        pub local_time: &'a u64,
+        pub remote_time: &'a u64,
+    }
+    #[derive(Serialize)]
+    #[serde(rename_all = "camelCase")]
+    pub struct MediaSessionArtwork<'a> {
+        pub src: &'a String,
+    }
+    #[derive(Serialize)]
+    #[serde(rename_all = "camelCase")]
+    pub struct MediaSession<'a> {
+        pub title: Option<&'a String>,
+        pub artist: Option<&'a String>,
+        pub album: Option<String>,
+        pub artwork: Vec<MediaSessionArtwork<'a>>,
+        pub actions: Vec<&'static str>,
     }
     #[derive(Serialize)]
     #[serde(rename_all = "camelCase")]
     pub struct Player<'a> {
         pub selected: &'a Option<Selected>,
+        pub stream_expired: bool,
+        pub file_resolution: Option<FileResolution>,
+        pub limit_reached: Option<LimitReason>,
         pub meta_item: Option<model::MetaItem<'a>>,
         pub subtitles: Vec<model::Subtitles<'a>>,
         pub next_video: Option<Video<'a>>,
+        pub play_queue: Option<PlayQueue<'a>>,
+        pub episode_navigation: Option<EpisodeNavigation<'a>>,
         pub library_item: Option<LibraryItem<'a>>,
+        pub progress_conflict: Option<ProgressConflict<'a>>,
         pub title: Option<String>,
         pub addon: Option<model::DescriptorPreview<'a>>,
+        pub media_session: Option<MediaSession<'a>>,
     }
 }
 
-pub fn serialize_player(player: &Player, ctx: &Ctx<WebEnv>) -> JsValue {
-    JsValue::from_serde(&model::Player {
+pub fn serialize_player<S: Serializer>(
+    player: &Player,
+    ctx: &Ctx<WebEnv>,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    let external_subtitles = player
+        .selected
+        .as_ref()
+        .and_then(|selected| selected.stream_request.as_ref())
+        .map(|stream_request| external_subtitles(&stream_request.path.id))
+        .unwrap_or_default();
+    model::Player {
         selected: &player.selected,
+        stream_expired: player.selected.as_ref().map_or(false, |selected| {
+            stream_expiry::is_expired(&selected.stream)
+        }),
+        file_resolution: player
+            .selected
+            .as_ref()
+            .and_then(|selected| torrent_files::unresolved_info_hash(&selected.stream))
+            .map(|info_hash| {
+                torrent_files::resolution(&info_hash).unwrap_or(FileResolution::Pending)
+            }),
+        limit_reached: watch_limits::limit_reached(),
         meta_item: player
             .meta_item
             .as_ref()
@@ -103,8 +205,15 @@ pub fn serialize_player(player: &Player, ctx: &Ctx<WebEnv>) -> JsValue {
                         upcomming: meta_item.behavior_hints.has_scheduled_videos
                             && meta_item
                                 .released
-                                .map(|released| released > WebEnv::now())
+                                .as_ref()
+                                .map(release_date::is_upcoming)
                                 .unwrap_or(true),
+                        airs_today: video
+                            .released
+                            .as_ref()
+                            .map(release_date::is_released_today)
+                            .unwrap_or_default(),
+                        release_precision: video.released.as_ref().map(ReleasePrecision::from),
                         watched: false, // TODO use library
                         progress: None, // TODO use library,
                         scheduled: meta_item.behavior_hints.has_scheduled_videos,
@@ -113,29 +222,59 @@ pub fn serialize_player(player: &Player, ctx: &Ctx<WebEnv>) -> JsValue {
                     .collect(),
             }),
         subtitles: player
-            .subtitles
-            .iter()
-            .filter_map(|subtitles| {
-                ctx.profile
-                    .addons
+            .selected
+            .as_ref()
+            .map(|selected| {
+                let origin = selected
+                    .stream_request
+                    .as_ref()
+                    .and_then(|stream_request| {
+                        ctx.profile
+                            .addons
+                            .iter()
+                            .find(|addon| addon.transport_url == stream_request.base)
+                    })
+                    .map(|addon| addon.manifest.name.as_str())
+                    .unwrap_or(STREAM_SUBTITLES_ORIGIN);
+                selected
+                    .stream
+                    .subtitles
                     .iter()
-                    .find(|addon| addon.transport_url == subtitles.request.base)
-                    .map(|addon| (addon, subtitles))
-            })
-            .filter_map(|(addon, subtitles)| match subtitles {
-                ResourceLoadable {
-                    content: Loadable::Ready(subtitles),
-                    ..
-                } => Some((addon, subtitles)),
-                _ => None,
-            })
-            .flat_map(|(addon, subtitles)| {
-                subtitles.iter().map(move |subtitles| (addon, subtitles))
-            })
-            .map(|(addon, subtitles)| model::Subtitles {
-                subtitles,
-                origin: &addon.manifest.name,
+                    .map(move |subtitles| (origin, subtitles))
             })
+            .into_iter()
+            .flatten()
+            .chain(
+                external_subtitles
+                    .iter()
+                    .map(|subtitles| (EXTERNAL_SUBTITLES_ORIGIN, subtitles)),
+            )
+            .chain(
+                player
+                    .subtitles
+                    .iter()
+                    .filter_map(|subtitles| {
+                        ctx.profile
+                            .addons
+                            .iter()
+                            .find(|addon| addon.transport_url == subtitles.request.base)
+                            .map(|addon| (addon, subtitles))
+                    })
+                    .filter_map(|(addon, subtitles)| match subtitles {
+                        ResourceLoadable {
+                            content: Loadable::Ready(subtitles),
+                            ..
+                        } => Some((addon, subtitles)),
+                        _ => None,
+                    })
+                    .flat_map(|(addon, subtitles)| {
+                        subtitles
+                            .iter()
+                            .map(move |subtitles| (addon.manifest.name.as_str(), subtitles))
+                    }),
+            )
+            .unique_by(|(_, subtitles)| (&subtitles.lang, &subtitles.url))
+            .map(|(origin, subtitles)| model::Subtitles { subtitles, origin })
             .collect(),
         next_video: player
             .selected
@@ -158,10 +297,17 @@ pub fn serialize_player(player: &Player, ctx: &Ctx<WebEnv>) -> JsValue {
                         meta_item.behavior_hints.has_scheduled_videos
                             && meta_item
                                 .released
-                                .map(|released| released > WebEnv::now())
+                                .as_ref()
+                                .map(release_date::is_upcoming)
                                 .unwrap_or(true)
                     })
                     .unwrap_or_default(),
+                airs_today: video
+                    .released
+                    .as_ref()
+                    .map(release_date::is_released_today)
+                    .unwrap_or_default(),
+                release_precision: video.released.as_ref().map(ReleasePrecision::from),
                 watched: false, // TODO use library
                 progress: None, // TODO use library,
                 scheduled: player
@@ -177,6 +323,99 @@ pub fn serialize_player(player: &Player, ctx: &Ctx<WebEnv>) -> JsValue {
                     .unwrap_or_default(),
                 deep_links: VideoDeepLinks::from((video, request)),
             }),
+        play_queue: player
+            .selected
+            .as_ref()
+            .and_then(|selected| selected.stream_request.as_ref())
+            .zip(
+                player
+                    .meta_item
+                    .as_ref()
+                    .and_then(|meta_item| match meta_item {
+                        ResourceLoadable {
+                            request,
+                            content: Loadable::Ready(meta_item),
+                        } => Some((request, meta_item)),
+                        _ => None,
+                    }),
+            )
+            .filter(|(_, (_, meta_item))| {
+                meta_item.behavior_hints.default_video_id.is_none()
+                    && !meta_item.videos.is_empty()
+                    && meta_item
+                        .videos
+                        .iter()
+                        .all(|video| video.series_info.is_none())
+            })
+            .map(|(stream_request, (request, meta_item))| {
+                let videos = play_queue::videos(&meta_item.id, &meta_item.videos);
+                let current_index = videos
+                    .iter()
+                    .position(|video| video.id == stream_request.path.id);
+                model::PlayQueue {
+                    auto_advance: current_index.map_or(false, |index| index + 1 < videos.len()),
+                    current_index,
+                    videos: videos
+                        .into_iter()
+                        .map(|video| model::PlayQueueItem {
+                            id: &video.id,
+                            title: &video.title,
+                            thumbnail: &video.thumbnail,
+                            deep_links: VideoDeepLinks::from((video, request)),
+                        })
+                        .collect(),
+                }
+            }),
+        episode_navigation: player
+            .selected
+            .as_ref()
+            .and_then(|selected| selected.stream_request.as_ref())
+            .zip(
+                player
+                    .meta_item
+                    .as_ref()
+                    .and_then(|meta_item| match meta_item {
+                        ResourceLoadable {
+                            request,
+                            content: Loadable::Ready(meta_item),
+                        } => Some((request, meta_item)),
+                        _ => None,
+                    }),
+            )
+            .and_then(|(stream_request, (request, meta_item))| {
+                episode_navigation(
+                    &meta_item.videos,
+                    &stream_request.path.id,
+                    player
+                        .library_item
+                        .as_ref()
+                        .and_then(|library_item| library_item.state.video_id.as_deref()),
+                )
+                .map(|episode_navigation| {
+                    (
+                        request,
+                        absolute_episodes(&meta_item.videos),
+                        episode_navigation,
+                    )
+                })
+            })
+            .map(
+                |(request, absolute_episodes, episode_navigation)| model::EpisodeNavigation {
+                    season: episode_navigation.season,
+                    seasons: episode_navigation.seasons,
+                    first_in_season: episode_navigation.first_in_season,
+                    last_in_season: episode_navigation.last_in_season,
+                    previous: episode_navigation
+                        .previous
+                        .map(|video| episode_link(video, request, &absolute_episodes)),
+                    next: episode_navigation
+                        .next
+                        .map(|video| episode_link(video, request, &absolute_episodes)),
+                    next_season: episode_navigation
+                        .next_season
+                        .map(|video| episode_link(video, request, &absolute_episodes)),
+                },
+            ),
         library_item: player
             .library_item
             .as_ref()
@@ -186,6 +425,26 @@ pub fn serialize_player(player: &Player, ctx: &Ctx<WebEnv>) -> JsValue {
                     time_offset: &library_item.state.time_offset,
                     video_id: &library_item.state.video_id,
                 },
+                resumable: is_resumable(library_item),
+            }),
+        progress_conflict: player
+            .library_item
+            .as_ref()
+            .and_then(|local_item| {
+                ctx.library
+                    .items
+                    .get(&local_item.id)
+                    .map(|remote_item| (local_item, remote_item))
+            })
+            .filter(|(local_item, remote_item)| {
+                remote_item.mtime > local_item.mtime
+                    && remote_item.state.video_id == local_item.state.video_id
+                    && remote_item.state.time_offset
+                        > local_item.state.time_offset + PROGRESS_CONFLICT_THRESHOLD
+            })
+            .map(|(local_item, remote_item)| model::ProgressConflict {
+                local_time: &local_item.state.time_offset,
+                remote_time: &remote_item.state.time_offset,
             }),
         title: player.selected.as_ref().and_then(|selected| {
             player
@@ -208,11 +467,15 @@ pub fn serialize_player(player: &Player, ctx: &Ctx<WebEnv>) -> JsValue {
                         Some(video) if meta_item.behavior_hints.default_video_id.is_none() => {
                             match &video.series_info {
                                 Some(series_info) => format!(
-                                    "{} - {} ({}x{})",
+                                    "{} - {} ({})",
                                     &meta_item.name,
                                     &video.title,
-                                    &series_info.season,
-                                    &series_info.episode
+                                    episode_label(
+                                        series_info,
+                                        absolute_episodes(&meta_item.videos)
+                                            .get(video.id.as_str())
+                                            .copied()
+                                    )
                                 ),
                                 _ => format!("{} - {}", &meta_item.name, &video.title),
                             }
@@ -244,6 +507,72 @@ pub fn serialize_player(player: &Player, ctx: &Ctx<WebEnv>) -> JsValue {
                     types: &addon.manifest.types,
                 },
             }),
-    })
-    .unwrap()
+        media_session: player.selected.as_ref().map(|selected| {
+            let meta_item = player
+                .meta_item
+                .as_ref()
+                .and_then(|meta_item| match meta_item {
+                    ResourceLoadable {
+                        content: Loadable::Ready(meta_item),
+                        ..
+                    } => Some(meta_item),
+                    _ => None,
+                });
+            let video = meta_item
+                .filter(|meta_item| meta_item.behavior_hints.default_video_id.is_none())
+                .zip(selected.stream_request.as_ref())
+                .and_then(|(meta_item, stream_request)| {
+                    meta_item
+                        .videos
+                        .iter()
+                        .find(|video| video.id == stream_request.path.id)
+                });
+            model::MediaSession {
+                title: video
+                    .map(|video| &video.title)
+                    .or_else(|| meta_item.map(|meta_item| &meta_item.name))
+                    .or_else(|| selected.stream.title.as_ref()),
+                artist: video.and(meta_item).map(|meta_item| &meta_item.name),
+                album: video
+                    .and_then(|video| video.series_info.as_ref())
+                    .map(|series_info| format!("Season {}", series_info.season)),
+                artwork: meta_item
+                    .map(|meta_item| {
+                        vec![&meta_item.poster, &meta_item.background, &meta_item.logo]
+                            .into_iter()
+                            .filter_map(|image| image.as_ref())
+                            .map(|src| model::MediaSessionArtwork { src })
+                            .collect()
+                    })
+                    .unwrap_or_default(),
+                actions: if player.next_video.is_some() {
+                    vec!["play", "pause", "seekto", "nexttrack"]
+                } else {
+                    vec!["play", "pause", "seekto"]
+                },
+            }
+        }),
+    }
+    .serialize(serializer)
+}
+
+fn episode_link<'a>(
+    video: &'a stremio_core::types::resource::Video,
+    request: &ResourceRequest,
+    absolute_episodes: &HashMap<&str, u32>,
+) -> model::EpisodeLink<'a> {
+    model::EpisodeLink {
+        id: &video.id,
+        title: &video.title,
+        season: video
+            .series_info
+            .as_ref()
+            .map(|series_info| series_info.season),
+        episode: video
+            .series_info
+            .as_ref()
+            .map(|series_info| series_info.episode),
+        absolute_episode: absolute_episodes.get(video.id.as_str()).copied(),
+        deep_links: VideoDeepLinks::from((video, request)),
+    }
 }
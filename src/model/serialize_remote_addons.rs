@@ -1,11 +1,11 @@
 use crate::env::WebEnv;
 use crate::model::deep_links::AddonsDeepLinks;
-use serde::Serialize;
+use crate::model::transport_protocol::Transport;
+use serde::{Serialize, Serializer};
 use stremio_core::models::catalog_with_filters::{CatalogWithFilters, Selected};
 use stremio_core::models::common::Loadable;
 use stremio_core::models::ctx::Ctx;
 use stremio_core::types::addon::DescriptorPreview;
-use wasm_bindgen::JsValue;
 
 mod model {
     use super::*;
@@ -36,6 +36,7 @@ mod model {
         #[serde(flatten)]
         pub addon: &'a stremio_core::types::addon::DescriptorPreview,
         pub installed: bool,
+        pub transport: Transport,
     }
     #[derive(Serialize)]
     #[serde(rename_all = "camelCase")]
@@ -51,11 +52,12 @@ mod model {
     }
 }
 
-pub fn serialize_remote_addons(
+pub fn serialize_remote_addons<S: Serializer>(
     remote_addons: &CatalogWithFilters<DescriptorPreview>,
     ctx: &Ctx<WebEnv>,
-) -> JsValue {
-    JsValue::from_serde(&model::CatalogWithFilters {
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    model::CatalogWithFilters {
         selected: &remote_addons.selected,
         selectable: model::Selectable {
             catalogs: remote_addons
@@ -90,6 +92,7 @@ pub fn serialize_remote_addons(
                             .iter()
                             .map(|addon| model::DescriptorPreview {
                                 addon,
+                                transport: Transport::from(&addon.transport_url),
                                 installed: ctx
                                     .profile
                                     .addons
@@ -103,6 +106,6 @@ pub fn serialize_remote_addons(
                     Loadable::Err(error) => Loadable::Err(error.to_string()),
                 },
             }),
-    })
-    .unwrap()
+    }
+    .serialize(serializer)
 }
@@ -0,0 +1,44 @@
+use crate::env::WebEnv;
+use crate::model::release_date::is_upcoming;
+use chrono::Duration;
+use serde::Serialize;
+use stremio_core::runtime::Env;
+use stremio_core::types::library::LibraryItem;
+
+const SERIES_TYPE: &str = "series";
+const RETURNING_SOON_DAYS: i64 = 7;
+const AIRING_DAYS: i64 = 14;
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum SeriesStatus {
+    NewEpisodeAvailable,
+    ReturningSoon,
+    Airing,
+    Ended,
+}
+
+pub fn series_status(library_item: &LibraryItem) -> Option<SeriesStatus> {
+    if library_item.r#type != SERIES_TYPE {
+        return None;
+    };
+    let last_vid_released = library_item.state.last_vid_released.as_ref()?;
+    let now = WebEnv::now();
+    if is_upcoming(last_vid_released) {
+        if *last_vid_released - now <= Duration::days(RETURNING_SOON_DAYS) {
+            Some(SeriesStatus::ReturningSoon)
+        } else {
+            None
+        }
+    } else if library_item
+        .state
+        .last_watched
+        .map_or(false, |last_watched| *last_vid_released > last_watched)
+    {
+        Some(SeriesStatus::NewEpisodeAvailable)
+    } else if now - *last_vid_released <= Duration::days(AIRING_DAYS) {
+        Some(SeriesStatus::Airing)
+    } else {
+        Some(SeriesStatus::Ended)
+    }
+}
@@ -0,0 +1,17 @@
+use stremio_core::types::library::{LibraryItem, LibraryItemState};
+
+// Live channels have no fixed timeline, so a stored offset can't be resumed from
+const LIVE_TYPE: &str = "tv";
+
+pub fn minutes_remaining(state: &LibraryItemState) -> Option<u64> {
+    if state.time_offset > 0 && state.duration > state.time_offset {
+        let remaining = state.duration - state.time_offset;
+        Some((remaining + 59_999) / 60_000)
+    } else {
+        None
+    }
+}
+
+pub fn is_resumable(library_item: &LibraryItem) -> bool {
+    library_item.r#type != LIVE_TYPE && library_item.state.duration > 0
+}
@@ -0,0 +1,43 @@
+use serde::Serialize;
+use url::Url;
+
+const MANIFEST_PATH: &str = "/manifest.json";
+const LEGACY_PATH: &str = "/stremio/v1";
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum TransportProtocol {
+    V3,
+    Legacy,
+    Unknown,
+}
+
+impl From<&Url> for TransportProtocol {
+    fn from(transport_url: &Url) -> Self {
+        let path = transport_url.path();
+        if path.ends_with(MANIFEST_PATH) {
+            TransportProtocol::V3
+        } else if path.ends_with(LEGACY_PATH) {
+            TransportProtocol::Legacy
+        } else {
+            TransportProtocol::Unknown
+        }
+    }
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Transport {
+    pub protocol: TransportProtocol,
+    pub deprecated: bool,
+}
+
+impl From<&Url> for Transport {
+    fn from(transport_url: &Url) -> Self {
+        let protocol = TransportProtocol::from(transport_url);
+        Transport {
+            deprecated: !matches!(protocol, TransportProtocol::V3),
+            protocol,
+        }
+    }
+}
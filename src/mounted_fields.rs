@@ -0,0 +1,91 @@
+use crate::model::WebModelField;
+use std::cell::RefCell;
+use std::mem::{self, Discriminant};
+
+thread_local! {
+    static MOUNTED_FIELDS: RefCell<Option<Vec<Discriminant<WebModelField>>>> = Default::default();
+}
+
+// Ctx backs every screen and the rest keep playback and the home screen running in the background
+fn is_always_mounted(field: &WebModelField) -> bool {
+    matches!(
+        field,
+        WebModelField::Ctx
+            | WebModelField::ContinueWatchingPreview
+            | WebModelField::StreamingServer
+            | WebModelField::Player
+    )
+}
+
+// Returns false when the field was already mounted
+pub fn mount(field: &WebModelField) -> bool {
+    if is_always_mounted(field) {
+        return false;
+    };
+    MOUNTED_FIELDS.with(|mounted_fields| {
+        let mut mounted_fields = mounted_fields.borrow_mut();
+        let mounted_fields = mounted_fields.get_or_insert_with(Vec::new);
+        let field = mem::discriminant(field);
+        if mounted_fields.contains(&field) {
+            return false;
+        };
+        mounted_fields.push(field);
+        true
+    })
+}
+
+pub fn unmount(field: &WebModelField) -> bool {
+    if is_always_mounted(field) {
+        return false;
+    };
+    MOUNTED_FIELDS.with(|mounted_fields| {
+        let mut mounted_fields = mounted_fields.borrow_mut();
+        let mounted_fields = mounted_fields.get_or_insert_with(Vec::new);
+        let field = mem::discriminant(field);
+        let len = mounted_fields.len();
+        mounted_fields.retain(|mounted_field| *mounted_field != field);
+        mounted_fields.len() != len
+    })
+}
+
+// Until the app mounts or unmounts a field every field counts as mounted,
+// so apps that don't track what is on screen keep getting updates for all of them
+pub fn is_mounted(field: &WebModelField) -> bool {
+    is_always_mounted(field)
+        || MOUNTED_FIELDS.with(|mounted_fields| {
+            mounted_fields
+                .borrow()
+                .as_ref()
+                .map_or(true, |mounted_fields| {
+                    mounted_fields.contains(&mem::discriminant(field))
+                })
+        })
+}
+
+pub fn clear() {
+    MOUNTED_FIELDS.with(|mounted_fields| *mounted_fields.borrow_mut() = None);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn every_field_is_mounted_until_one_is_mounted() {
+        assert!(is_mounted(&WebModelField::Discover));
+        assert!(mount(&WebModelField::MetaDetails));
+        assert!(!mount(&WebModelField::MetaDetails));
+        assert!(is_mounted(&WebModelField::MetaDetails));
+        assert!(!is_mounted(&WebModelField::Discover));
+    }
+
+    #[test]
+    fn background_fields_cannot_be_unmounted() {
+        assert!(!unmount(&WebModelField::Player));
+        assert!(!unmount(&WebModelField::Ctx));
+        assert!(!unmount(&WebModelField::Board));
+        assert!(is_mounted(&WebModelField::Player));
+        assert!(is_mounted(&WebModelField::Ctx));
+        assert!(!is_mounted(&WebModelField::Board));
+    }
+}
@@ -0,0 +1,87 @@
+use futures::future::LocalBoxFuture;
+use futures::{future, FutureExt};
+use url::Url;
+use wasm_bindgen_futures::JsFuture;
+
+// Loopback addresses are potentially trustworthy, so the browser lets HTTPS pages reach them
+const LOOPBACK_HOSTS: &[&str] = &["localhost", "127.0.0.1", "[::1]"];
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum NetworkErrorKind {
+    MixedContent,
+    Cors,
+    Unreachable,
+    Offline,
+    Unknown,
+}
+
+impl NetworkErrorKind {
+    pub fn description(&self) -> &'static str {
+        match self {
+            NetworkErrorKind::MixedContent => "mixed content",
+            NetworkErrorKind::Cors => "cors blocked",
+            NetworkErrorKind::Unreachable => "unreachable",
+            NetworkErrorKind::Offline => "offline",
+            NetworkErrorKind::Unknown => "network",
+        }
+    }
+    pub fn hint(&self) -> Option<&'static str> {
+        match self {
+            NetworkErrorKind::MixedContent => Some(
+                "The addon is HTTP-only and the browser blocks it on HTTPS pages, enable the streaming server proxy or use an HTTPS addon URL",
+            ),
+            NetworkErrorKind::Cors => Some(
+                "The server doesn't allow cross-origin requests, enable the streaming server proxy or ask the addon author to send CORS headers",
+            ),
+            NetworkErrorKind::Unreachable => Some(
+                "The server could not be reached, check the address and its TLS certificate",
+            ),
+            NetworkErrorKind::Offline => Some("The device is offline"),
+            NetworkErrorKind::Unknown => None,
+        }
+    }
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            NetworkErrorKind::MixedContent | NetworkErrorKind::Cors => false,
+            NetworkErrorKind::Unreachable
+            | NetworkErrorKind::Offline
+            | NetworkErrorKind::Unknown => true,
+        }
+    }
+}
+
+// The browser would block the request without a reason visible to us, so it is not sent at all
+pub fn is_mixed_content(url: &str) -> bool {
+    let page_protocol = web_sys::window()
+        .expect("window is not available")
+        .location()
+        .protocol()
+        .unwrap_or_default();
+    page_protocol == "https:"
+        && Url::parse(url).map_or(false, |url| {
+            url.scheme() == "http"
+                && !url
+                    .host_str()
+                    .map_or(false, |host| LOOPBACK_HOSTS.contains(&host))
+        })
+}
+
+// Fetch rejects with the same TypeError for CORS, DNS and TLS failures. An opaque no-cors request
+// only succeeds when the server is reachable, which singles out CORS. DNS and TLS failures stay
+// indistinguishable and are both reported as unreachable.
+pub fn classify(url: &str) -> LocalBoxFuture<'static, NetworkErrorKind> {
+    let window = web_sys::window().expect("window is not available");
+    if !window.navigator().on_line() {
+        return future::ready(NetworkErrorKind::Offline).boxed_local();
+    };
+    let mut request_options = web_sys::RequestInit::new();
+    request_options
+        .method("GET")
+        .mode(web_sys::RequestMode::NoCors);
+    JsFuture::from(window.fetch_with_str_and_init(url, &request_options))
+        .map(|result| match result {
+            Ok(_) => NetworkErrorKind::Cors,
+            Err(_) => NetworkErrorKind::Unreachable,
+        })
+        .boxed_local()
+}
@@ -0,0 +1,57 @@
+use crate::js_value::to_js_value;
+use serde::Serialize;
+use std::cell::RefCell;
+use std::panic::PanicInfo;
+use wasm_bindgen::JsValue;
+
+thread_local! {
+    static PANIC_HANDLER: RefCell<Option<js_sys::Function>> = Default::default();
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PanicReport {
+    pub message: String,
+    pub location: Option<String>,
+    pub stack: Option<String>,
+}
+
+pub fn set_handler(handler: Option<js_sys::Function>) {
+    PANIC_HANDLER.with(|current| *current.borrow_mut() = handler);
+}
+
+// Nothing in here may panic again, a failing handler is only logged
+pub fn hook(info: &PanicInfo) {
+    console_error_panic_hook::hook(info);
+    let handler = PANIC_HANDLER
+        .try_with(|handler| {
+            handler
+                .try_borrow()
+                .ok()
+                .and_then(|handler| handler.to_owned())
+        })
+        .ok()
+        .flatten();
+    if let Some(handler) = handler {
+        let report = PanicReport {
+            message: info.to_string(),
+            location: info.location().map(|location| {
+                format!(
+                    "{}:{}:{}",
+                    location.file(),
+                    location.line(),
+                    location.column()
+                )
+            }),
+            // The stack of a fresh error includes the wasm frames that led to the panic
+            stack: js_sys::Reflect::get(&js_sys::Error::new(""), &JsValue::from_str("stack"))
+                .ok()
+                .and_then(|stack| stack.as_string()),
+        };
+        if let Ok(report) = to_js_value(&report) {
+            if let Err(error) = handler.call1(&JsValue::NULL, &report) {
+                web_sys::console::error_2(&JsValue::from("panic handler failed"), &error);
+            };
+        };
+    };
+}
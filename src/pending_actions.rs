@@ -0,0 +1,68 @@
+use crate::action_settlement::Settlement;
+use std::cell::{Cell, RefCell};
+use std::collections::VecDeque;
+use wasm_bindgen::JsValue;
+
+const MAX_PENDING_ACTIONS: usize = 100;
+
+thread_local! {
+    static FLUSH_PENDING_ACTIONS: Cell<bool> = Cell::new(true);
+    static PENDING_ACTIONS: RefCell<VecDeque<PendingAction>> = Default::default();
+}
+
+pub struct PendingAction {
+    pub action: JsValue,
+    pub field: JsValue,
+    pub settlement: Option<Settlement>,
+}
+
+pub fn set_flush(flush: bool) {
+    FLUSH_PENDING_ACTIONS.with(|flush_pending_actions| flush_pending_actions.set(flush));
+}
+
+pub fn push(action: JsValue, field: JsValue, settlement: Option<Settlement>) {
+    let dropped = PENDING_ACTIONS.with(|pending_actions| {
+        let mut pending_actions = pending_actions.borrow_mut();
+        let dropped = if pending_actions.len() >= MAX_PENDING_ACTIONS {
+            pending_actions.pop_front()
+        } else {
+            None
+        };
+        pending_actions.push_back(PendingAction {
+            action,
+            field,
+            settlement,
+        });
+        dropped
+    });
+    if let Some(dropped) = dropped {
+        reject(dropped, "action was dropped from the queue");
+    };
+}
+
+pub fn take() -> Vec<PendingAction> {
+    let pending_actions = PENDING_ACTIONS
+        .with(|pending_actions| pending_actions.borrow_mut().drain(..).collect::<Vec<_>>());
+    if FLUSH_PENDING_ACTIONS.with(|flush_pending_actions| flush_pending_actions.get()) {
+        pending_actions
+    } else {
+        for pending_action in pending_actions {
+            reject(pending_action, "action was dropped from the queue");
+        }
+        vec![]
+    }
+}
+
+pub fn clear() {
+    let pending_actions = PENDING_ACTIONS
+        .with(|pending_actions| pending_actions.borrow_mut().drain(..).collect::<Vec<_>>());
+    for pending_action in pending_actions {
+        reject(pending_action, "runtime was destroyed");
+    }
+}
+
+fn reject(pending_action: PendingAction, reason: &str) {
+    if let Some(settlement) = pending_action.settlement {
+        settlement.reject(&JsValue::from_str(reason));
+    };
+}
@@ -0,0 +1,104 @@
+use crate::action_settlement::Settlement;
+use std::cell::{Cell, RefCell};
+use std::collections::HashMap;
+use wasm_bindgen::closure::Closure;
+use wasm_bindgen::{JsCast, JsValue};
+
+const DEFAULT_UNDO_WINDOW: u32 = 5000;
+
+thread_local! {
+    static UNDO_WINDOW: Cell<u32> = Cell::new(DEFAULT_UNDO_WINDOW);
+    static PENDING_REMOVALS: RefCell<HashMap<String, PendingRemoval>> = Default::default();
+}
+
+// Callers waiting for the removal are settled once it is committed or undone
+struct PendingRemoval {
+    timeout_handle: i32,
+    settlements: Vec<Settlement>,
+}
+
+pub fn set_undo_window(undo_window: u32) {
+    UNDO_WINDOW.with(|current| current.set(undo_window));
+}
+
+pub fn is_pending(id: &str) -> bool {
+    PENDING_REMOVALS.with(|pending_removals| pending_removals.borrow().contains_key(id))
+}
+
+pub fn schedule<F: FnOnce(String, Vec<Settlement>) + 'static>(
+    id: String,
+    settlement: Option<Settlement>,
+    commit: F,
+) {
+    let scheduled = PENDING_REMOVALS.with(|pending_removals| {
+        match pending_removals.borrow_mut().get_mut(&id) {
+            Some(pending_removal) => {
+                pending_removal.settlements.extend(settlement);
+                None
+            }
+            None => Some(settlement),
+        }
+    });
+    let settlement = match scheduled {
+        Some(settlement) => settlement,
+        None => return,
+    };
+    let callback = Closure::once_into_js({
+        let id = id.to_owned();
+        move || {
+            let pending_removal =
+                PENDING_REMOVALS.with(|pending_removals| pending_removals.borrow_mut().remove(&id));
+            if let Some(pending_removal) = pending_removal {
+                commit(id, pending_removal.settlements);
+            };
+        }
+    });
+    let timeout_handle = web_sys::window()
+        .expect("window is not available")
+        .set_timeout_with_callback_and_timeout_and_arguments_0(
+            callback.unchecked_ref(),
+            UNDO_WINDOW.with(|undo_window| undo_window.get()) as i32,
+        )
+        .expect("set timeout failed");
+    PENDING_REMOVALS.with(|pending_removals| {
+        pending_removals.borrow_mut().insert(
+            id,
+            PendingRemoval {
+                timeout_handle,
+                settlements: settlement.into_iter().collect(),
+            },
+        )
+    });
+}
+
+pub fn undo(id: &str) -> bool {
+    match PENDING_REMOVALS.with(|pending_removals| pending_removals.borrow_mut().remove(id)) {
+        Some(pending_removal) => {
+            clear_timeout(pending_removal.timeout_handle);
+            for settlement in pending_removal.settlements {
+                settlement.reject(&JsValue::from_str("library removal was undone"));
+            }
+            true
+        }
+        None => false,
+    }
+}
+
+pub fn flush() -> Vec<(String, Vec<Settlement>)> {
+    PENDING_REMOVALS.with(|pending_removals| {
+        pending_removals
+            .borrow_mut()
+            .drain()
+            .map(|(id, pending_removal)| {
+                clear_timeout(pending_removal.timeout_handle);
+                (id, pending_removal.settlements)
+            })
+            .collect()
+    })
+}
+
+fn clear_timeout(handle: i32) {
+    web_sys::window()
+        .expect("window is not available")
+        .clear_timeout_with_handle(handle);
+}
@@ -0,0 +1,80 @@
+use serde::{Deserialize, Serialize};
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+
+const RESTRICTABLE_ACTIONS: &[&[&str]] = &[
+    &["Ctx", "Authenticate"],
+    &["Ctx", "Logout"],
+    &["Ctx", "InstallAddon"],
+    &["Ctx", "UninstallAddon"],
+    &["Ctx", "UpdateSettings"],
+    &["Ctx", "AddToLibrary"],
+    &["Ctx", "RemoveFromLibrary"],
+    &["Ctx", "RewindLibraryItem"],
+    &["Load"],
+    &["Player"],
+];
+
+thread_local! {
+    static PERMISSIONS: RefCell<Permissions> = Default::default();
+}
+
+#[derive(Default, Deserialize)]
+pub struct Permissions {
+    pub allow: Option<Vec<Vec<String>>>,
+    #[serde(default)]
+    pub deny: Vec<Vec<String>>,
+}
+
+impl Permissions {
+    fn is_allowed<T: AsRef<str>>(&self, path: &[T]) -> bool {
+        let matches = |rule: &Vec<String>| {
+            path.len() >= rule.len()
+                && path
+                    .iter()
+                    .zip(rule.iter())
+                    .all(|(a, b)| a.as_ref() == b.as_str())
+        };
+        !self.deny.iter().any(matches)
+            && self
+                .allow
+                .as_ref()
+                .map_or(true, |allow| allow.iter().any(matches))
+    }
+}
+
+#[derive(Serialize)]
+#[serde(tag = "type")]
+pub enum PermissionError {
+    PermissionDenied { action: Vec<String> },
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Capabilities {
+    pub actions: BTreeMap<String, bool>,
+}
+
+pub fn init(permissions: Permissions) {
+    PERMISSIONS.with(|current| *current.borrow_mut() = permissions);
+}
+
+pub fn check(path: Vec<String>) -> Result<(), PermissionError> {
+    if PERMISSIONS.with(|permissions| permissions.borrow().is_allowed(&path)) {
+        Ok(())
+    } else {
+        Err(PermissionError::PermissionDenied { action: path })
+    }
+}
+
+pub fn capabilities() -> Capabilities {
+    PERMISSIONS.with(|permissions| {
+        let permissions = permissions.borrow();
+        Capabilities {
+            actions: RESTRICTABLE_ACTIONS
+                .iter()
+                .map(|path| (path.join("."), permissions.is_allowed(path)))
+                .collect(),
+        }
+    })
+}
@@ -0,0 +1,61 @@
+use std::cell::RefCell;
+use std::collections::HashSet;
+use stremio_core::types::resource::Video;
+
+thread_local! {
+    static PLAY_QUEUE: RefCell<Option<PlayQueue>> = Default::default();
+}
+
+struct PlayQueue {
+    meta_id: String,
+    order: Vec<String>,
+    skipped: HashSet<String>,
+}
+
+pub fn reorder(meta_id: String, order: Vec<String>) {
+    update(meta_id, |play_queue| play_queue.order = order);
+}
+
+pub fn skip(meta_id: String, video_id: String) {
+    update(meta_id, |play_queue| {
+        play_queue.skipped.insert(video_id);
+    });
+}
+
+pub fn videos<'a>(meta_id: &str, videos: &'a [Video]) -> Vec<&'a Video> {
+    PLAY_QUEUE.with(|play_queue| match &*play_queue.borrow() {
+        Some(play_queue) if play_queue.meta_id == meta_id => {
+            let mut videos = videos
+                .iter()
+                .filter(|video| !play_queue.skipped.contains(&video.id))
+                .collect::<Vec<_>>();
+            videos.sort_by_key(|video| {
+                play_queue
+                    .order
+                    .iter()
+                    .position(|id| *id == video.id)
+                    .unwrap_or_else(|| play_queue.order.len())
+            });
+            videos
+        }
+        _ => videos.iter().collect(),
+    })
+}
+
+fn update<F: FnOnce(&mut PlayQueue)>(meta_id: String, f: F) {
+    PLAY_QUEUE.with(|play_queue| {
+        let mut play_queue = play_queue.borrow_mut();
+        match &mut *play_queue {
+            Some(play_queue) if play_queue.meta_id == meta_id => f(play_queue),
+            _ => {
+                let mut new_play_queue = PlayQueue {
+                    meta_id,
+                    order: vec![],
+                    skipped: HashSet::new(),
+                };
+                f(&mut new_play_queue);
+                *play_queue = Some(new_play_queue);
+            }
+        };
+    });
+}
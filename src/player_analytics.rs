@@ -0,0 +1,52 @@
+use crate::analytics;
+use crate::watch_session::{Seek, WatchSessionChange};
+use stremio_core::types::resource::StreamSource;
+
+fn source_type(source: &StreamSource) -> &'static str {
+    match source {
+        StreamSource::Url { .. } => "url",
+        StreamSource::YouTube { .. } => "youTube",
+        StreamSource::Torrent { .. } => "torrent",
+        StreamSource::External { .. } => "external",
+        StreamSource::PlayerFrame { .. } => "playerFrame",
+    }
+}
+
+// Mirrors the player events the desktop app reports, stream urls are left out on purpose
+pub fn track(change: &WatchSessionChange) {
+    if !analytics::is_enabled() {
+        return;
+    };
+    match change {
+        WatchSessionChange::Started {
+            stream,
+            addon_transport_url,
+        } => analytics::track(
+            "playerStart",
+            serde_json::json!({
+                "streamSourceType": source_type(&stream.source),
+                "addonTransportUrl": addon_transport_url,
+            }),
+        ),
+        WatchSessionChange::Playing { .. } => {}
+        WatchSessionChange::Seeked(Seek { from, to }) => analytics::track(
+            "playerSeek",
+            serde_json::json!({
+                "from": from,
+                "to": to,
+            }),
+        ),
+        WatchSessionChange::Ended(summary) => analytics::track(
+            "playerStop",
+            serde_json::json!({
+                "streamSourceType": source_type(&summary.stream.source),
+                "addonTransportUrl": summary.addon_transport_url,
+                "watchTime": summary.watch_time,
+                "completion": summary.completion,
+                "seeks": summary.seeks,
+                "pauses": summary.pauses,
+                "errors": summary.errors,
+            }),
+        ),
+    };
+}
@@ -0,0 +1,68 @@
+use serde::{Deserialize, Serialize};
+use std::cell::RefCell;
+use std::collections::HashMap;
+
+pub const RATINGS_STORAGE_KEY: &str = "profile_ratings";
+const ANONYMOUS_PROFILE_KEY: &str = "";
+
+thread_local! {
+    static RATINGS: RefCell<HashMap<String, HashMap<String, Rating>>> = Default::default();
+}
+
+#[derive(Clone, Copy, Serialize, Deserialize)]
+#[serde(tag = "type", content = "value")]
+pub enum Rating {
+    Like,
+    Dislike,
+    Stars(u8),
+}
+
+impl Rating {
+    pub fn is_valid(&self) -> bool {
+        match self {
+            Rating::Stars(stars) => (1..=5).contains(stars),
+            _ => true,
+        }
+    }
+}
+
+pub fn init(ratings: HashMap<String, HashMap<String, Rating>>) {
+    RATINGS.with(|current| *current.borrow_mut() = ratings);
+}
+
+pub fn rating(uid: &Option<String>, id: &str) -> Option<Rating> {
+    RATINGS.with(|ratings| {
+        ratings
+            .borrow()
+            .get(profile_key(uid))
+            .and_then(|ratings| ratings.get(id))
+            .copied()
+    })
+}
+
+pub fn set_rating(
+    uid: &Option<String>,
+    id: String,
+    rating: Option<Rating>,
+) -> HashMap<String, HashMap<String, Rating>> {
+    RATINGS.with(|ratings| {
+        let mut ratings = ratings.borrow_mut();
+        let profile_ratings = ratings.entry(profile_key(uid).to_owned()).or_default();
+        match rating {
+            Some(rating) => profile_ratings.insert(id, rating),
+            None => profile_ratings.remove(&id),
+        };
+        if profile_ratings.is_empty() {
+            ratings.remove(profile_key(uid));
+        };
+        ratings.to_owned()
+    })
+}
+
+pub fn clear() {
+    RATINGS.with(|ratings| ratings.borrow_mut().clear());
+}
+
+fn profile_key(uid: &Option<String>) -> &str {
+    uid.as_deref().unwrap_or(ANONYMOUS_PROFILE_KEY)
+}
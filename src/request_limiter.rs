@@ -0,0 +1,105 @@
+use futures::channel::oneshot;
+use futures::future::LocalBoxFuture;
+use futures::{future, FutureExt};
+use std::cell::{Cell, RefCell};
+use std::collections::VecDeque;
+
+thread_local! {
+    static MAX_CONCURRENT: Cell<Option<usize>> = Cell::new(None);
+    static ACTIVE: Cell<usize> = Cell::new(0);
+    static WAITING: RefCell<VecDeque<oneshot::Sender<Slot>>> = Default::default();
+}
+
+// A request may run while it holds a slot. Dropping the slot, even with a cancelled request,
+// hands it straight to the next waiting request.
+pub struct Slot {
+    _private: (),
+}
+
+impl Drop for Slot {
+    fn drop(&mut self) {
+        loop {
+            match WAITING.with(|waiting| waiting.borrow_mut().pop_front()) {
+                Some(sender) => match sender.send(Slot { _private: () }) {
+                    Ok(()) => return,
+                    // The request stopped waiting, the slot stays with this loop
+                    Err(slot) => std::mem::forget(slot),
+                },
+                None => {
+                    ACTIVE.with(|active| active.set(active.get().saturating_sub(1)));
+                    return;
+                }
+            };
+        }
+    }
+}
+
+pub fn init(max_concurrent: Option<usize>) {
+    MAX_CONCURRENT.with(|current| current.set(max_concurrent.filter(|max| *max > 0)));
+}
+
+pub fn acquire() -> LocalBoxFuture<'static, Slot> {
+    let max_concurrent = MAX_CONCURRENT.with(|max_concurrent| max_concurrent.get());
+    let active = ACTIVE.with(|active| active.get());
+    match max_concurrent {
+        Some(max_concurrent) if active >= max_concurrent => {
+            let (sender, receiver) = oneshot::channel();
+            WAITING.with(|waiting| waiting.borrow_mut().push_back(sender));
+            // Senders are only dropped once their receiver is gone
+            receiver
+                .map(|slot| slot.expect("request slot sender dropped"))
+                .boxed_local()
+        }
+        _ => {
+            ACTIVE.with(|active| active.set(active.get() + 1));
+            future::ready(Slot { _private: () }).boxed_local()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn active() -> usize {
+        ACTIVE.with(|active| active.get())
+    }
+
+    #[test]
+    fn dropped_slot_goes_to_the_next_waiting_request() {
+        init(Some(1));
+        let slot = acquire().now_or_never().unwrap();
+        let mut waiting = acquire();
+        assert!((&mut waiting).now_or_never().is_none());
+        drop(slot);
+        let slot = waiting.now_or_never().unwrap();
+        assert_eq!(active(), 1);
+        drop(slot);
+        assert_eq!(active(), 0);
+    }
+
+    #[test]
+    fn cancelled_waiting_requests_leave_no_slot_behind() {
+        init(Some(1));
+        let slot = acquire().now_or_never().unwrap();
+        let cancelled = acquire();
+        let mut waiting = acquire();
+        assert!((&mut waiting).now_or_never().is_none());
+        drop(cancelled);
+        drop(slot);
+        let slot = waiting.now_or_never().unwrap();
+        drop(slot);
+        assert_eq!(active(), 0);
+    }
+
+    #[test]
+    fn slot_handed_to_a_cancelled_request_is_released() {
+        init(Some(1));
+        let slot = acquire().now_or_never().unwrap();
+        let cancelled = acquire();
+        drop(slot);
+        assert_eq!(active(), 1);
+        drop(cancelled);
+        assert_eq!(active(), 0);
+    }
+}
@@ -0,0 +1,133 @@
+use crate::analytics;
+use crate::model::{WebModel, WebModelField};
+use std::cell::RefCell;
+use std::collections::HashMap;
+use stremio_core::models::common::{Loadable, ResourceLoadable};
+use stremio_core::runtime::msg::Action;
+use url::Url;
+
+// Routes that never get a ready resource, e.g. because every addon failed, are not reported
+const ROUTE_TIMEOUT: f64 = 60_000.0;
+
+thread_local! {
+    static PENDING_ROUTES: RefCell<HashMap<&'static str, f64>> = Default::default();
+}
+
+enum Interactive {
+    Pending,
+    Ready(Option<Url>),
+}
+
+pub fn route_name(field: &WebModelField) -> Option<&'static str> {
+    match field {
+        WebModelField::Board => Some("board"),
+        WebModelField::Discover => Some("discover"),
+        WebModelField::Library => Some("library"),
+        WebModelField::ContinueWatching => Some("continueWatching"),
+        WebModelField::Search => Some("search"),
+        WebModelField::MetaDetails => Some("metaDetails"),
+        WebModelField::RemoteAddons => Some("remoteAddons"),
+        WebModelField::AddonDetails => Some("addonDetails"),
+        WebModelField::Player => Some("player"),
+        _ => None,
+    }
+}
+
+fn first_ready<T>(resources: &[ResourceLoadable<T>]) -> Interactive {
+    match resources
+        .iter()
+        .find(|resource| matches!(resource.content, Loadable::Ready(_)))
+    {
+        Some(resource) => Interactive::Ready(Some(resource.request.base.to_owned())),
+        None => Interactive::Pending,
+    }
+}
+
+// A route is interactive once it has its first ready resource, local routes are right away
+fn interactive(model: &WebModel, route: &str) -> Interactive {
+    match route {
+        "board" => first_ready(&model.board.catalogs),
+        "search" => first_ready(&model.search.catalogs),
+        "discover" => first_ready(
+            model
+                .discover
+                .catalog
+                .as_ref()
+                .map_or(&[], std::slice::from_ref),
+        ),
+        "remoteAddons" => first_ready(
+            model
+                .remote_addons
+                .catalog
+                .as_ref()
+                .map_or(&[], std::slice::from_ref),
+        ),
+        "metaDetails" => first_ready(&model.meta_details.meta_items),
+        "player" => match &model.player.meta_item {
+            Some(meta_item) => first_ready(std::slice::from_ref(meta_item)),
+            None if model.player.selected.is_some() => Interactive::Ready(None),
+            None => Interactive::Pending,
+        },
+        "addonDetails" => match (
+            &model.addon_details.selected,
+            &model.addon_details.remote_addon,
+        ) {
+            (Some(selected), Some(Loadable::Ready(_))) => {
+                Interactive::Ready(Some(selected.transport_url.to_owned()))
+            }
+            _ => Interactive::Pending,
+        },
+        _ => Interactive::Ready(None),
+    }
+}
+
+pub fn start(action: &Action, field: Option<&WebModelField>, now: f64) {
+    if let (Action::Load(_), Some(route)) = (action, field.and_then(route_name)) {
+        PENDING_ROUTES.with(|pending_routes| pending_routes.borrow_mut().insert(route, now));
+    };
+}
+
+pub fn cancel(action: &Action, field: Option<&WebModelField>) {
+    if let Action::Unload = action {
+        PENDING_ROUTES.with(|pending_routes| {
+            let mut pending_routes = pending_routes.borrow_mut();
+            match field.and_then(route_name) {
+                Some(route) => {
+                    pending_routes.remove(route);
+                }
+                None => pending_routes.clear(),
+            };
+        });
+    };
+}
+
+// Reports every pending route that became interactive with the latest state
+pub fn check(model: &WebModel, now: f64) {
+    let interactive_routes = PENDING_ROUTES.with(|pending_routes| {
+        let mut pending_routes = pending_routes.borrow_mut();
+        pending_routes.retain(|_, started_at| now - *started_at <= ROUTE_TIMEOUT);
+        let interactive_routes = pending_routes
+            .iter()
+            .filter_map(|(route, started_at)| match interactive(model, route) {
+                Interactive::Ready(addon_transport_url) => {
+                    Some((*route, now - *started_at, addon_transport_url))
+                }
+                Interactive::Pending => None,
+            })
+            .collect::<Vec<_>>();
+        for (route, _, _) in interactive_routes.iter() {
+            pending_routes.remove(route);
+        }
+        interactive_routes
+    });
+    for (route, duration, addon_transport_url) in interactive_routes {
+        analytics::track(
+            "routeInteractive",
+            serde_json::json!({
+                "route": route,
+                "duration": duration,
+                "addonTransportUrl": addon_transport_url,
+            }),
+        );
+    }
+}
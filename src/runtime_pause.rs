@@ -0,0 +1,66 @@
+use futures::future::LocalBoxFuture;
+use serde_json::Value;
+use std::cell::{Cell, RefCell};
+
+const MAX_BUFFERED_EVENTS: usize = 500;
+const NEW_STATE_EVENT_NAME: &str = "NewState";
+
+thread_local! {
+    static PAUSED: Cell<bool> = Cell::new(false);
+    static BUFFERED_EVENTS: RefCell<Vec<Value>> = Default::default();
+    static DEFERRED_EFFECTS: RefCell<Vec<LocalBoxFuture<'static, ()>>> = Default::default();
+}
+
+pub struct Resumed {
+    pub events: Vec<Value>,
+    pub effects: Vec<LocalBoxFuture<'static, ()>>,
+}
+
+pub fn is_paused() -> bool {
+    PAUSED.with(|paused| paused.get())
+}
+
+// Returns false when the runtime was already paused
+pub fn pause() -> bool {
+    !PAUSED.with(|paused| paused.replace(true))
+}
+
+pub fn resume() -> Option<Resumed> {
+    if !PAUSED.with(|paused| paused.replace(false)) {
+        return None;
+    };
+    Some(Resumed {
+        events: BUFFERED_EVENTS.with(|events| events.borrow_mut().drain(..).collect()),
+        effects: DEFERRED_EFFECTS.with(|effects| effects.borrow_mut().drain(..).collect()),
+    })
+}
+
+// NewState carries no payload, so a single one stands for all of the state changes
+// made while paused. Other events are kept in order, the oldest ones go first when full.
+pub fn buffer_event(event: &Value) -> bool {
+    if !is_paused() {
+        return false;
+    };
+    BUFFERED_EVENTS.with(|events| {
+        let mut events = events.borrow_mut();
+        let is_new_state =
+            event.get("name").and_then(|name| name.as_str()) == Some(NEW_STATE_EVENT_NAME);
+        if is_new_state {
+            events.retain(|buffered| *buffered != *event);
+        } else if events.len() >= MAX_BUFFERED_EVENTS {
+            events.remove(0);
+        };
+        events.push(event.to_owned());
+    });
+    true
+}
+
+pub fn defer_effect(effect: LocalBoxFuture<'static, ()>) {
+    DEFERRED_EFFECTS.with(|effects| effects.borrow_mut().push(effect));
+}
+
+pub fn clear() {
+    PAUSED.with(|paused| paused.set(false));
+    BUFFERED_EVENTS.with(|events| events.borrow_mut().clear());
+    DEFERRED_EFFECTS.with(|effects| effects.borrow_mut().clear());
+}
@@ -0,0 +1,150 @@
+use crate::account::AccountDetails;
+use serde::{Deserialize, Serialize};
+use std::cell::{Cell, RefCell};
+use stremio_core::constants::API_URL;
+use stremio_core::runtime::msg::Event;
+use url::Url;
+use wasm_bindgen::closure::Closure;
+use wasm_bindgen::JsCast;
+
+const STALE_SESSION_THRESHOLD: f64 = 60.0 * 60.0 * 1000.0;
+const GET_USER_API_METHOD: &str = "getUser";
+pub const GET_USER_TIMEOUT: u32 = 10_000;
+const PULL_TIMEOUT: i32 = 30_000;
+
+thread_local! {
+    static BLURRED_AT: Cell<Option<f64>> = Cell::new(None);
+    static REFRESHING: Cell<bool> = Cell::new(false);
+    static WATCHING: Cell<bool> = Cell::new(false);
+    static PENDING_PULLS: RefCell<Vec<Pull>> = Default::default();
+    static PULL_TIMEOUT_HANDLE: Cell<Option<i32>> = Cell::new(None);
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Pull {
+    User,
+    Addons,
+    Library,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GetUserRequest<'a> {
+    pub r#type: &'static str,
+    pub auth_key: &'a str,
+}
+
+// A successful result means the auth key is still valid, it also carries the account details
+#[derive(Deserialize)]
+pub struct GetUserResponse {
+    pub error: Option<APIError>,
+    pub result: Option<AccountDetails>,
+}
+
+#[derive(Deserialize, Serialize)]
+pub struct APIError {
+    pub message: String,
+    pub code: u64,
+}
+
+pub fn get_user_request(auth_key: &str) -> GetUserRequest {
+    GetUserRequest {
+        r#type: "GetUser",
+        auth_key,
+    }
+}
+
+pub fn get_user_url() -> Url {
+    API_URL
+        .join(&format!("api/{}", GET_USER_API_METHOD))
+        .expect("api url builder failed")
+}
+
+pub fn is_refreshing() -> bool {
+    REFRESHING.with(|refreshing| refreshing.get())
+}
+
+pub fn set_refreshing(refreshing: bool) {
+    REFRESHING.with(|current| current.set(refreshing));
+}
+
+// A failed pull completes it as well, core reports it as an error caused by the same event
+fn pull(event: &Event) -> Option<Pull> {
+    match event {
+        Event::UserPulledFromAPI { .. } => Some(Pull::User),
+        Event::AddonsPulledFromAPI { .. } => Some(Pull::Addons),
+        Event::LibrarySyncWithAPIPlanned { .. } => Some(Pull::Library),
+        Event::Error { source, .. } => pull(source),
+        _ => None,
+    }
+}
+
+// The refresh stays in progress until core finished the pulls, or gives up after the timeout
+pub fn await_pulls(on_timeout: fn()) {
+    PENDING_PULLS.with(|pending_pulls| {
+        *pending_pulls.borrow_mut() = vec![Pull::User, Pull::Addons, Pull::Library]
+    });
+    let timeout_callback = Closure::once_into_js(move || {
+        PULL_TIMEOUT_HANDLE.with(|handle| handle.set(None));
+        on_timeout();
+    });
+    let timeout_handle = web_sys::window()
+        .expect("window is not available")
+        .set_timeout_with_callback_and_timeout_and_arguments_0(
+            timeout_callback.unchecked_ref(),
+            PULL_TIMEOUT,
+        )
+        .expect("set timeout failed");
+    PULL_TIMEOUT_HANDLE.with(|handle| handle.set(Some(timeout_handle)));
+}
+
+// Returns true once the last awaited pull completed
+pub fn complete_pull(event: &Event) -> bool {
+    let pull = match pull(event) {
+        Some(pull) => pull,
+        None => return false,
+    };
+    PENDING_PULLS.with(|pending_pulls| {
+        let mut pending_pulls = pending_pulls.borrow_mut();
+        let len = pending_pulls.len();
+        pending_pulls.retain(|pending_pull| *pending_pull != pull);
+        len > 0 && pending_pulls.is_empty()
+    })
+}
+
+pub fn finish() {
+    PENDING_PULLS.with(|pending_pulls| pending_pulls.borrow_mut().clear());
+    if let Some(timeout_handle) = PULL_TIMEOUT_HANDLE.with(|handle| handle.take()) {
+        web_sys::window()
+            .expect("window is not available")
+            .clear_timeout_with_handle(timeout_handle);
+    };
+    set_refreshing(false);
+}
+
+// The callback runs when the window regains focus after being idle for longer than the threshold
+pub fn watch<F: Fn() + 'static>(on_stale_focus: F) {
+    if WATCHING.with(|watching| watching.replace(true)) {
+        return;
+    };
+    let window = web_sys::window().expect("window is not available");
+    let on_blur = Closure::wrap(Box::new(|| {
+        BLURRED_AT.with(|blurred_at| blurred_at.set(Some(js_sys::Date::now())));
+    }) as Box<dyn FnMut()>);
+    let on_focus = Closure::wrap(Box::new(move || {
+        let idle = BLURRED_AT
+            .with(|blurred_at| blurred_at.take())
+            .map(|blurred_at| js_sys::Date::now() - blurred_at);
+        if idle.map_or(false, |idle| idle >= STALE_SESSION_THRESHOLD) && !is_refreshing() {
+            on_stale_focus();
+        };
+    }) as Box<dyn FnMut()>);
+    window
+        .add_event_listener_with_callback("blur", on_blur.as_ref().unchecked_ref())
+        .expect("add blur listener failed");
+    window
+        .add_event_listener_with_callback("focus", on_focus.as_ref().unchecked_ref())
+        .expect("add focus listener failed");
+    on_blur.forget();
+    on_focus.forget();
+}
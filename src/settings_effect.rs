@@ -0,0 +1,58 @@
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::BTreeMap;
+use stremio_core::types::profile::Settings;
+
+#[derive(Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum SettingEffect {
+    Immediate,
+    ReloadModels,
+    RestartRuntime,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RestartRequired {
+    pub settings: Vec<String>,
+}
+
+pub fn setting_effect(key: &str) -> SettingEffect {
+    match key {
+        "streamingServerUrl" => SettingEffect::ReloadModels,
+        "hardwareDecoding" => SettingEffect::RestartRuntime,
+        _ => SettingEffect::Immediate,
+    }
+}
+
+pub fn settings_effects(settings: &Settings) -> BTreeMap<String, SettingEffect> {
+    settings_values(settings)
+        .into_iter()
+        .map(|(key, _)| {
+            let effect = setting_effect(&key);
+            (key, effect)
+        })
+        .collect()
+}
+
+pub fn restart_required(before: &Settings, after: &Settings) -> Option<RestartRequired> {
+    let before = settings_values(before);
+    let settings = settings_values(after)
+        .into_iter()
+        .filter(|(key, value)| before.get(key) != Some(value))
+        .map(|(key, _)| key)
+        .filter(|key| setting_effect(key) == SettingEffect::RestartRuntime)
+        .collect::<Vec<_>>();
+    if settings.is_empty() {
+        None
+    } else {
+        Some(RestartRequired { settings })
+    }
+}
+
+fn settings_values(settings: &Settings) -> BTreeMap<String, Value> {
+    match serde_json::to_value(settings) {
+        Ok(Value::Object(settings)) => settings.into_iter().collect(),
+        _ => Default::default(),
+    }
+}
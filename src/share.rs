@@ -0,0 +1,22 @@
+use std::cell::RefCell;
+use url::Url;
+
+thread_local! {
+    static SHARE_BASE_URL: RefCell<Url> =
+        RefCell::new(Url::parse("https://web.strem.io/").expect("share base url parse failed"));
+}
+
+pub fn share_base_url() -> Url {
+    SHARE_BASE_URL.with(|share_base_url| share_base_url.borrow().to_owned())
+}
+
+pub fn set_share_base_url(url: Url) {
+    SHARE_BASE_URL.with(|share_base_url| *share_base_url.borrow_mut() = url);
+}
+
+pub fn share_url(route: &str) -> String {
+    share_base_url()
+        .join(route)
+        .expect("share url build failed")
+        .to_string()
+}
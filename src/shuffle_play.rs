@@ -0,0 +1,195 @@
+use crate::addon_priorities;
+use crate::media_support::{self, Playable};
+use crate::model::StreamDeepLinks;
+use itertools::Itertools;
+use percent_encoding::{utf8_percent_encode, NON_ALPHANUMERIC};
+use serde::{Deserialize, Serialize};
+use std::cmp::Reverse;
+use stremio_core::constants::STREAM_RESOURCE_NAME;
+use stremio_core::types::addon::{Descriptor, ResourcePath, ResourceRequest};
+use stremio_core::types::library::LibraryItem;
+use stremio_core::types::resource::{MetaItem, MetaItemPreview, Stream};
+use url::Url;
+
+pub const MAX_SHUFFLE_ATTEMPTS: usize = 3;
+const SPECIALS_SEASON: u32 = 0;
+
+#[derive(Deserialize)]
+#[serde(tag = "source", rename_all = "camelCase")]
+pub enum ShuffleSource {
+    #[serde(rename_all = "camelCase")]
+    Catalog { request: ResourceRequest },
+    #[serde(rename_all = "camelCase")]
+    Library { r#type: Option<String> },
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ShufflePlay {
+    #[serde(flatten)]
+    pub source: ShuffleSource,
+    #[serde(default)]
+    pub unwatched_only: bool,
+}
+
+#[derive(Clone)]
+pub struct ShuffleCandidate {
+    pub r#type: String,
+    pub id: String,
+    pub name: String,
+    pub poster: Option<String>,
+    pub video_id: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct CatalogResponse {
+    pub metas: Vec<MetaItemPreview>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ShufflePick {
+    pub r#type: String,
+    pub id: String,
+    pub name: String,
+    pub poster: Option<String>,
+    pub video_id: String,
+    pub stream: Stream,
+    pub deep_links: StreamDeepLinks,
+}
+
+impl From<&LibraryItem> for ShuffleCandidate {
+    fn from(library_item: &LibraryItem) -> Self {
+        ShuffleCandidate {
+            r#type: library_item.r#type.to_owned(),
+            id: library_item.id.to_owned(),
+            name: library_item.name.to_owned(),
+            poster: library_item.poster.to_owned(),
+            video_id: library_item
+                .state
+                .video_id
+                .to_owned()
+                .or_else(|| library_item.behavior_hints.default_video_id.to_owned()),
+        }
+    }
+}
+
+impl From<MetaItemPreview> for ShuffleCandidate {
+    fn from(meta_item: MetaItemPreview) -> Self {
+        ShuffleCandidate {
+            r#type: meta_item.r#type,
+            id: meta_item.id,
+            name: meta_item.name,
+            poster: meta_item.poster,
+            video_id: meta_item.behavior_hints.default_video_id,
+        }
+    }
+}
+
+pub fn is_watched(library_item: &LibraryItem) -> bool {
+    library_item.state.times_watched > 0 || library_item.state.flagged_watched > 0
+}
+
+// Follows the addon protocol, extra props go in a single path segment before the extension
+pub fn catalog_url(request: &ResourceRequest) -> Result<Url, url::ParseError> {
+    let extra = request
+        .path
+        .extra
+        .iter()
+        .map(|extra| {
+            format!(
+                "{}={}",
+                utf8_percent_encode(&extra.name, NON_ALPHANUMERIC),
+                utf8_percent_encode(&extra.value, NON_ALPHANUMERIC)
+            )
+        })
+        .join("&");
+    request.base.join(&format!(
+        "{}/{}/{}{}.json",
+        request.path.resource,
+        utf8_percent_encode(&request.path.r#type, NON_ALPHANUMERIC),
+        utf8_percent_encode(&request.path.id, NON_ALPHANUMERIC),
+        if extra.is_empty() {
+            String::new()
+        } else {
+            format!("/{}", extra)
+        }
+    ))
+}
+
+pub fn take_random<T>(candidates: &mut Vec<T>) -> Option<T> {
+    if candidates.is_empty() {
+        return None;
+    };
+    let index = (js_sys::Math::random() * candidates.len() as f64) as usize;
+    Some(candidates.swap_remove(index.min(candidates.len() - 1)))
+}
+
+// Series without a known video start from their first regular episode
+pub fn first_video_id(meta_item: &MetaItem) -> Option<String> {
+    meta_item
+        .videos
+        .iter()
+        .filter(|video| {
+            video
+                .series_info
+                .as_ref()
+                .map_or(true, |series_info| series_info.season != SPECIALS_SEASON)
+        })
+        .min_by_key(|video| {
+            video
+                .series_info
+                .as_ref()
+                .map(|series_info| (series_info.season, series_info.episode))
+        })
+        .map(|video| video.id.to_owned())
+}
+
+// The first stream the browser can play, taken from the addon with the highest priority
+pub fn best_stream(
+    responses: Vec<(ResourceRequest, Vec<Stream>)>,
+) -> Option<(ResourceRequest, Stream)> {
+    responses
+        .into_iter()
+        .sorted_by_key(|(request, _)| Reverse(addon_priorities::priority(&request.base)))
+        .find_map(|(request, streams)| {
+            streams
+                .into_iter()
+                .find(|stream| !matches!(media_support::playable(stream), Playable::Unsupported))
+                .map(|stream| (request, stream))
+        })
+}
+
+pub fn stream_requests(
+    addons: &[Descriptor],
+    r#type: &str,
+    video_id: &str,
+) -> Vec<ResourceRequest> {
+    let path = ResourcePath::without_extra(STREAM_RESOURCE_NAME, r#type, video_id);
+    addons
+        .iter()
+        .filter(|addon| addon.manifest.is_resource_supported(&path))
+        .map(|addon| ResourceRequest {
+            base: addon.transport_url.to_owned(),
+            path: path.to_owned(),
+        })
+        .collect()
+}
+
+pub fn pick(
+    candidate: ShuffleCandidate,
+    video_id: String,
+    stream_request: &ResourceRequest,
+    meta_request: &ResourceRequest,
+    stream: Stream,
+) -> ShufflePick {
+    ShufflePick {
+        deep_links: StreamDeepLinks::from((&stream, stream_request, meta_request)),
+        r#type: candidate.r#type,
+        id: candidate.id,
+        name: candidate.name,
+        poster: candidate.poster,
+        video_id,
+        stream,
+    }
+}
@@ -0,0 +1,512 @@
+use crate::model::WebModelField;
+use serde::ser::{
+    SerializeMap, SerializeSeq, SerializeStruct, SerializeStructVariant, SerializeTuple,
+    SerializeTupleStruct, SerializeTupleVariant,
+};
+use serde::{Serialize, Serializer};
+use serde_json::{Map, Value};
+
+#[derive(Debug, PartialEq)]
+pub enum PathSegment {
+    Key(String),
+    Index(u32),
+}
+
+pub struct StateSelector {
+    pub field: WebModelField,
+    pub path: Vec<PathSegment>,
+}
+
+pub fn is_selector(field: &str) -> bool {
+    field.contains('.') || field.contains('[')
+}
+
+// Selectors look like "metaDetails.streams[0].content", the field accepts both camel and snake case
+pub fn parse(selector: &str) -> Result<StateSelector, String> {
+    let mut path = vec![];
+    for segment in selector.split('.') {
+        let (key, indexes) = match segment.find('[') {
+            Some(position) => segment.split_at(position),
+            None => (segment, ""),
+        };
+        if key.is_empty() || !(indexes.is_empty() || indexes.ends_with(']')) {
+            return Err(format!("invalid selector segment: {}", segment));
+        };
+        path.push(PathSegment::Key(key.to_owned()));
+        for index in indexes.split_terminator(']') {
+            let index = index
+                .strip_prefix('[')
+                .and_then(|index| index.parse::<u32>().ok())
+                .ok_or_else(|| format!("invalid selector index: {}", segment))?;
+            path.push(PathSegment::Index(index));
+        }
+    }
+    let field = match path.first() {
+        Some(PathSegment::Key(key)) => {
+            serde_json::from_value::<WebModelField>(Value::String(snake_case(key)))
+                .map_err(|_| format!("unknown field: {}", key))?
+        }
+        _ => return Err("selector must start with a field".to_owned()),
+    };
+    path.remove(0);
+    Ok(StateSelector { field, path })
+}
+
+// Only the entries on the path are serialized, everything next to them is skipped.
+// Paths that don't exist select nothing instead of failing, the same way optional chaining does.
+pub fn select<T: Serialize + ?Sized>(
+    state: &T,
+    path: &[PathSegment],
+) -> Result<Option<Value>, serde_json::Error> {
+    if path.is_empty() {
+        serde_json::to_value(state).map(Some)
+    } else {
+        state.serialize(PathSerializer { path })
+    }
+}
+
+fn is_key(segment: &PathSegment, key: &str) -> bool {
+    matches!(segment, PathSegment::Key(segment_key) if segment_key == key)
+}
+
+fn key_string<T: Serialize + ?Sized>(key: &T) -> Result<Option<String>, serde_json::Error> {
+    Ok(match serde_json::to_value(key)? {
+        Value::String(key) => Some(key),
+        Value::Number(key) => Some(key.to_string()),
+        Value::Bool(key) => Some(key.to_string()),
+        _ => None,
+    })
+}
+
+// Never constructed with an empty path, values reached by the whole path go through serde_json
+struct PathSerializer<'a> {
+    path: &'a [PathSegment],
+}
+
+impl<'a> PathSerializer<'a> {
+    fn entries(self) -> Compound<'a> {
+        Compound::Select {
+            path: self.path,
+            index: 0,
+            key_matches: false,
+            selected: None,
+        }
+    }
+    // Variants serialize as an object with the variant name as the only key
+    fn variant_entries(self, variant: &'static str, value: Value) -> Compound<'a> {
+        if !is_key(&self.path[0], variant) {
+            Compound::Skip
+        } else if self.path.len() == 1 {
+            Compound::Collect(value)
+        } else {
+            PathSerializer {
+                path: &self.path[1..],
+            }
+            .entries()
+        }
+    }
+}
+
+enum Compound<'a> {
+    Skip,
+    Collect(Value),
+    Select {
+        path: &'a [PathSegment],
+        index: u32,
+        key_matches: bool,
+        selected: Option<Value>,
+    },
+}
+
+impl<'a> Compound<'a> {
+    fn element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), serde_json::Error> {
+        match self {
+            Compound::Skip => {}
+            Compound::Collect(Value::Array(values)) => values.push(serde_json::to_value(value)?),
+            Compound::Collect(_) => {}
+            Compound::Select {
+                path,
+                index,
+                selected,
+                ..
+            } => {
+                if let PathSegment::Index(selected_index) = path[0] {
+                    if selected_index == *index {
+                        *selected = select(value, &path[1..])?;
+                    };
+                };
+                *index += 1;
+            }
+        };
+        Ok(())
+    }
+    fn field<T: Serialize + ?Sized>(
+        &mut self,
+        key: &str,
+        value: &T,
+    ) -> Result<(), serde_json::Error> {
+        match self {
+            Compound::Skip => {}
+            Compound::Collect(Value::Object(values)) => {
+                values.insert(key.to_owned(), serde_json::to_value(value)?);
+            }
+            Compound::Collect(_) => {}
+            Compound::Select { path, selected, .. } => {
+                if is_key(&path[0], key) {
+                    *selected = select(value, &path[1..])?;
+                };
+            }
+        };
+        Ok(())
+    }
+    fn end(self) -> Result<Option<Value>, serde_json::Error> {
+        Ok(match self {
+            Compound::Skip => None,
+            Compound::Collect(value) => Some(value),
+            Compound::Select { selected, .. } => selected,
+        })
+    }
+}
+
+macro_rules! serialize_leaf {
+    ($($method:ident($($arg:ident: $type:ty),*)),* $(,)?) => {
+        $(
+            fn $method(self, $($arg: $type),*) -> Result<Self::Ok, Self::Error> {
+                Ok(None)
+            }
+        )*
+    };
+}
+
+impl<'a> Serializer for PathSerializer<'a> {
+    type Ok = Option<Value>;
+    type Error = serde_json::Error;
+    type SerializeSeq = Compound<'a>;
+    type SerializeTuple = Compound<'a>;
+    type SerializeTupleStruct = Compound<'a>;
+    type SerializeTupleVariant = Compound<'a>;
+    type SerializeMap = Compound<'a>;
+    type SerializeStruct = Compound<'a>;
+    type SerializeStructVariant = Compound<'a>;
+    serialize_leaf!(
+        serialize_bool(_value: bool),
+        serialize_i8(_value: i8),
+        serialize_i16(_value: i16),
+        serialize_i32(_value: i32),
+        serialize_i64(_value: i64),
+        serialize_u8(_value: u8),
+        serialize_u16(_value: u16),
+        serialize_u32(_value: u32),
+        serialize_u64(_value: u64),
+        serialize_f32(_value: f32),
+        serialize_f64(_value: f64),
+        serialize_char(_value: char),
+        serialize_str(_value: &str),
+        serialize_bytes(_value: &[u8]),
+        serialize_none(),
+        serialize_unit(),
+        serialize_unit_struct(_name: &'static str),
+        serialize_unit_variant(_name: &'static str, _variant_index: u32, _variant: &'static str),
+    );
+    fn serialize_some<T: Serialize + ?Sized>(self, value: &T) -> Result<Self::Ok, Self::Error> {
+        value.serialize(self)
+    }
+    fn serialize_newtype_struct<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        value: &T,
+    ) -> Result<Self::Ok, Self::Error> {
+        value.serialize(self)
+    }
+    fn serialize_newtype_variant<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        value: &T,
+    ) -> Result<Self::Ok, Self::Error> {
+        if is_key(&self.path[0], variant) {
+            select(value, &self.path[1..])
+        } else {
+            Ok(None)
+        }
+    }
+    fn serialize_seq(self, _len: Option<usize>) -> Result<Self::SerializeSeq, Self::Error> {
+        Ok(self.entries())
+    }
+    fn serialize_tuple(self, _len: usize) -> Result<Self::SerializeTuple, Self::Error> {
+        Ok(self.entries())
+    }
+    fn serialize_tuple_struct(
+        self,
+        _name: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeTupleStruct, Self::Error> {
+        Ok(self.entries())
+    }
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeTupleVariant, Self::Error> {
+        Ok(self.variant_entries(variant, Value::Array(vec![])))
+    }
+    fn serialize_map(self, _len: Option<usize>) -> Result<Self::SerializeMap, Self::Error> {
+        Ok(self.entries())
+    }
+    fn serialize_struct(
+        self,
+        _name: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeStruct, Self::Error> {
+        Ok(self.entries())
+    }
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeStructVariant, Self::Error> {
+        Ok(self.variant_entries(variant, Value::Object(Map::new())))
+    }
+}
+
+impl<'a> SerializeSeq for Compound<'a> {
+    type Ok = Option<Value>;
+    type Error = serde_json::Error;
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Self::Error> {
+        self.element(value)
+    }
+    fn end(self) -> Result<Self::Ok, Self::Error> {
+        Compound::end(self)
+    }
+}
+
+impl<'a> SerializeTuple for Compound<'a> {
+    type Ok = Option<Value>;
+    type Error = serde_json::Error;
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Self::Error> {
+        self.element(value)
+    }
+    fn end(self) -> Result<Self::Ok, Self::Error> {
+        Compound::end(self)
+    }
+}
+
+impl<'a> SerializeTupleStruct for Compound<'a> {
+    type Ok = Option<Value>;
+    type Error = serde_json::Error;
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Self::Error> {
+        self.element(value)
+    }
+    fn end(self) -> Result<Self::Ok, Self::Error> {
+        Compound::end(self)
+    }
+}
+
+impl<'a> SerializeTupleVariant for Compound<'a> {
+    type Ok = Option<Value>;
+    type Error = serde_json::Error;
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Self::Error> {
+        self.element(value)
+    }
+    fn end(self) -> Result<Self::Ok, Self::Error> {
+        Compound::end(self)
+    }
+}
+
+// Map keys are compared the way they end up in JSON, as strings
+impl<'a> SerializeMap for Compound<'a> {
+    type Ok = Option<Value>;
+    type Error = serde_json::Error;
+    fn serialize_key<T: Serialize + ?Sized>(&mut self, key: &T) -> Result<(), Self::Error> {
+        if let Compound::Select {
+            path, key_matches, ..
+        } = self
+        {
+            *key_matches = match key_string(key)? {
+                Some(key) => is_key(&path[0], &key),
+                None => false,
+            };
+        };
+        Ok(())
+    }
+    fn serialize_value<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Self::Error> {
+        if let Compound::Select {
+            path,
+            key_matches: true,
+            selected,
+            ..
+        } = self
+        {
+            *selected = select(value, &path[1..])?;
+        };
+        Ok(())
+    }
+    fn end(self) -> Result<Self::Ok, Self::Error> {
+        Compound::end(self)
+    }
+}
+
+impl<'a> SerializeStruct for Compound<'a> {
+    type Ok = Option<Value>;
+    type Error = serde_json::Error;
+    fn serialize_field<T: Serialize + ?Sized>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<(), Self::Error> {
+        self.field(key, value)
+    }
+    fn end(self) -> Result<Self::Ok, Self::Error> {
+        Compound::end(self)
+    }
+}
+
+impl<'a> SerializeStructVariant for Compound<'a> {
+    type Ok = Option<Value>;
+    type Error = serde_json::Error;
+    fn serialize_field<T: Serialize + ?Sized>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<(), Self::Error> {
+        self.field(key, value)
+    }
+    fn end(self) -> Result<Self::Ok, Self::Error> {
+        Compound::end(self)
+    }
+}
+
+fn snake_case(key: &str) -> String {
+    key.chars().fold(String::new(), |mut result, character| {
+        if character.is_ascii_uppercase() {
+            result.push('_');
+            result.push(character.to_ascii_lowercase());
+        } else {
+            result.push(character);
+        };
+        result
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeMap;
+
+    #[derive(Serialize)]
+    #[serde(rename_all = "camelCase")]
+    struct State {
+        meta_items: Vec<Item>,
+        selected: Option<Item>,
+        content: Content,
+        extra: BTreeMap<u32, &'static str>,
+    }
+
+    #[derive(Serialize)]
+    struct Item {
+        id: &'static str,
+        name: &'static str,
+    }
+
+    #[derive(Serialize)]
+    enum Content {
+        Ready(Vec<Item>),
+    }
+
+    fn state(selected: Option<Item>) -> State {
+        State {
+            meta_items: vec![Item { id: "a", name: "A" }, Item { id: "b", name: "B" }],
+            selected,
+            content: Content::Ready(vec![Item { id: "c", name: "C" }]),
+            extra: vec![(1, "one")].into_iter().collect(),
+        }
+    }
+
+    fn selected(state: &State, path: &str) -> Option<Value> {
+        select(state, &parse(&format!("board.{}", path)).unwrap().path).unwrap()
+    }
+
+    #[test]
+    fn parse_selectors() {
+        let selector = parse("metaDetails.streams[0].content").unwrap();
+        assert!(matches!(selector.field, WebModelField::MetaDetails));
+        assert_eq!(
+            selector.path,
+            vec![
+                PathSegment::Key("streams".to_owned()),
+                PathSegment::Index(0),
+                PathSegment::Key("content".to_owned()),
+            ]
+        );
+        let selector = parse("meta_details.items[1][2]").unwrap();
+        assert!(matches!(selector.field, WebModelField::MetaDetails));
+        assert_eq!(
+            selector.path,
+            vec![
+                PathSegment::Key("items".to_owned()),
+                PathSegment::Index(1),
+                PathSegment::Index(2),
+            ]
+        );
+        assert!(parse("player").unwrap().path.is_empty());
+    }
+
+    #[test]
+    fn parse_invalid_selectors() {
+        assert!(parse("unknown.items").is_err());
+        assert!(parse("board..items").is_err());
+        assert!(parse("board.items[x]").is_err());
+        assert!(parse("board.items[0").is_err());
+        assert!(parse("[0].items").is_err());
+    }
+
+    #[test]
+    fn select_whole_state() {
+        let state = state(None);
+        assert_eq!(
+            select(&state, &[]).unwrap(),
+            Some(serde_json::to_value(&state).unwrap())
+        );
+    }
+
+    #[test]
+    fn select_nested_values() {
+        let state = state(Some(Item { id: "s", name: "S" }));
+        assert_eq!(
+            selected(&state, "metaItems[1].name"),
+            Some(Value::from("B"))
+        );
+        assert_eq!(
+            selected(&state, "metaItems[0]"),
+            Some(serde_json::json!({ "id": "a", "name": "A" }))
+        );
+        assert_eq!(selected(&state, "selected.id"), Some(Value::from("s")));
+        assert_eq!(
+            selected(&state, "content.Ready[0].id"),
+            Some(Value::from("c"))
+        );
+        assert_eq!(selected(&state, "extra.1"), Some(Value::from("one")));
+    }
+
+    #[test]
+    fn select_missing_values() {
+        let state = state(None);
+        assert_eq!(selected(&state, "metaItems[2].name"), None);
+        assert_eq!(selected(&state, "metaItems.name"), None);
+        assert_eq!(selected(&state, "selected.id"), None);
+        assert_eq!(selected(&state, "content.Loading"), None);
+        assert_eq!(selected(&state, "missing"), None);
+        assert_eq!(selected(&state, "metaItems[0].id.length"), None);
+    }
+
+    #[test]
+    fn select_null_values() {
+        let state = state(None);
+        assert_eq!(selected(&state, "selected"), Some(Value::Null));
+    }
+}
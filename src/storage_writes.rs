@@ -0,0 +1,140 @@
+use crate::event::{self, WebEvent};
+use serde::Serialize;
+use std::cell::{Cell, RefCell};
+use std::collections::HashMap;
+use wasm_bindgen::closure::Closure;
+use wasm_bindgen::{JsCast, JsValue};
+
+const FLUSH_DELAY: i32 = 1000;
+
+thread_local! {
+    static PENDING: RefCell<HashMap<String, Option<String>>> = Default::default();
+    static FLUSH_HANDLE: Cell<Option<i32>> = Cell::new(None);
+    static WATCHING: Cell<bool> = Cell::new(false);
+    static STATS: RefCell<StorageWriteStats> = Default::default();
+}
+
+#[derive(Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StorageWriteStats {
+    pub written: u32,
+    pub coalesced: u32,
+    pub failed: u32,
+    pub pending: usize,
+    pub last_error: Option<String>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StorageWriteFailure {
+    pub key: String,
+    pub message: String,
+}
+
+// Reads have to see writes that are still waiting for the flush
+pub fn pending(key: &str) -> Option<Option<String>> {
+    PENDING.with(|pending| pending.borrow().get(key).cloned())
+}
+
+// A write replaces any pending write of the same key, so only the latest value reaches the storage
+pub fn schedule(key: String, value: Option<String>) {
+    let coalesced = PENDING.with(|pending| pending.borrow_mut().insert(key, value).is_some());
+    if coalesced {
+        STATS.with(|stats| stats.borrow_mut().coalesced += 1);
+    };
+    watch();
+    if FLUSH_HANDLE.with(|handle| handle.get()).is_some() {
+        return;
+    };
+    let flush = Closure::once_into_js(flush);
+    let handle = web_sys::window()
+        .expect("window is not available")
+        .set_timeout_with_callback_and_timeout_and_arguments_0(flush.unchecked_ref(), FLUSH_DELAY)
+        .expect("set timeout failed");
+    FLUSH_HANDLE.with(|current| current.set(Some(handle)));
+}
+
+pub fn flush() {
+    if let Some(handle) = FLUSH_HANDLE.with(|handle| handle.take()) {
+        web_sys::window()
+            .expect("window is not available")
+            .clear_timeout_with_handle(handle);
+    };
+    let writes = PENDING.with(|pending| pending.borrow_mut().drain().collect::<Vec<_>>());
+    if writes.is_empty() {
+        return;
+    };
+    let storage = web_sys::window()
+        .expect("window is not available")
+        .local_storage()
+        .ok()
+        .flatten();
+    for (key, value) in writes {
+        let result = match (&storage, &value) {
+            (Some(storage), Some(value)) => storage.set_item(&key, value),
+            (Some(storage), None) => storage.remove_item(&key),
+            (None, _) => Err(JsValue::from_str("storage is not available")),
+        };
+        match result {
+            Ok(_) => STATS.with(|stats| stats.borrow_mut().written += 1),
+            Err(error) => {
+                let message = error
+                    .dyn_ref::<js_sys::Error>()
+                    .map(|error| String::from(error.message()))
+                    .or_else(|| error.as_string())
+                    .unwrap_or_else(|| "unknown error".to_owned());
+                STATS.with(|stats| {
+                    let mut stats = stats.borrow_mut();
+                    stats.failed += 1;
+                    stats.last_error = Some(message.to_owned());
+                });
+                event::emit(&WebEvent::StorageWriteFailed(StorageWriteFailure {
+                    key,
+                    message,
+                }));
+            }
+        };
+    }
+}
+
+pub fn stats() -> StorageWriteStats {
+    StorageWriteStats {
+        pending: PENDING.with(|pending| pending.borrow().len()),
+        ..STATS.with(|stats| stats.borrow().to_owned())
+    }
+}
+
+// Timers don't fire once the page is gone, so hiding the page flushes right away
+fn watch() {
+    if WATCHING.with(|watching| watching.replace(true)) {
+        return;
+    };
+    let window = web_sys::window().expect("window is not available");
+    let document = js_sys::Reflect::get(&window, &JsValue::from_str("document"))
+        .expect("document is not available");
+    let on_visibility_change = Closure::wrap(Box::new({
+        let document = document.to_owned();
+        move || {
+            let visibility_state =
+                js_sys::Reflect::get(&document, &JsValue::from_str("visibilityState"))
+                    .ok()
+                    .and_then(|visibility_state| visibility_state.as_string());
+            if visibility_state.as_deref() == Some("hidden") {
+                flush();
+            };
+        }
+    }) as Box<dyn FnMut()>);
+    let on_page_hide = Closure::wrap(Box::new(flush) as Box<dyn FnMut()>);
+    document
+        .unchecked_ref::<web_sys::EventTarget>()
+        .add_event_listener_with_callback(
+            "visibilitychange",
+            on_visibility_change.as_ref().unchecked_ref(),
+        )
+        .expect("add visibilitychange listener failed");
+    window
+        .add_event_listener_with_callback("pagehide", on_page_hide.as_ref().unchecked_ref())
+        .expect("add pagehide listener failed");
+    on_visibility_change.forget();
+    on_page_hide.forget();
+}
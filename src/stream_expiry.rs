@@ -0,0 +1,133 @@
+use crate::env::WebEnv;
+use chrono::offset::TimeZone;
+use chrono::{DateTime, Duration, NaiveDateTime, Utc};
+use percent_encoding::{utf8_percent_encode, NON_ALPHANUMERIC};
+use serde::Deserialize;
+use std::mem;
+use stremio_core::runtime::Env;
+use stremio_core::types::addon::ResourceRequest;
+use stremio_core::types::resource::{Stream, StreamSource};
+use url::Url;
+
+const EXPIRES_QUERY_PARAMS: &[&str] = &["expires", "Expires", "exp"];
+const AMZ_DATE_QUERY_PARAM: &str = "X-Amz-Date";
+const AMZ_EXPIRES_QUERY_PARAM: &str = "X-Amz-Expires";
+const AMZ_DATE_FORMAT: &str = "%Y%m%dT%H%M%SZ";
+// Presigned S3 urls can't be valid for longer than 7 days
+const AMZ_MAX_EXPIRES: i64 = 7 * 24 * 60 * 60;
+
+#[derive(Deserialize)]
+pub struct StreamsResponse {
+    pub streams: Vec<Stream>,
+}
+
+// Expiry isn't part of the addon protocol, it's inferred from the query of signed urls
+pub fn expires_at(stream: &Stream) -> Option<DateTime<Utc>> {
+    match &stream.source {
+        StreamSource::Url { url } => parse_expires(url),
+        _ => None,
+    }
+}
+
+// Values that are out of range are treated as no expiry rather than trusted
+fn parse_expires(url: &Url) -> Option<DateTime<Utc>> {
+    let query_value = |name: &str| {
+        url.query_pairs()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.into_owned())
+    };
+    EXPIRES_QUERY_PARAMS
+        .iter()
+        .find_map(|name| query_value(name))
+        .and_then(|expires| expires.parse::<i64>().ok())
+        .and_then(|expires| Utc.timestamp_opt(expires, 0).single())
+        .or_else(|| {
+            let date = query_value(AMZ_DATE_QUERY_PARAM)?;
+            let date = NaiveDateTime::parse_from_str(&date, AMZ_DATE_FORMAT).ok()?;
+            let expires = query_value(AMZ_EXPIRES_QUERY_PARAM)?.parse::<i64>().ok()?;
+            if expires < 0 || expires > AMZ_MAX_EXPIRES {
+                return None;
+            };
+            DateTime::<Utc>::from_utc(date, Utc).checked_add_signed(Duration::seconds(expires))
+        })
+}
+
+pub fn is_expired(stream: &Stream) -> bool {
+    expires_at(stream).map_or(false, |expires_at| expires_at <= WebEnv::now())
+}
+
+pub fn streams_url(stream_request: &ResourceRequest) -> Result<Url, url::ParseError> {
+    stream_request.base.join(&format!(
+        "{}/{}/{}.json",
+        utf8_percent_encode(&stream_request.path.resource, NON_ALPHANUMERIC),
+        utf8_percent_encode(&stream_request.path.r#type, NON_ALPHANUMERIC),
+        utf8_percent_encode(&stream_request.path.id, NON_ALPHANUMERIC)
+    ))
+}
+
+// Signed urls change on every request, so the stream is matched by everything but its url
+pub fn find_stream(streams: Vec<Stream>, stream: &Stream) -> Option<Stream> {
+    streams.into_iter().find(|candidate| {
+        mem::discriminant(&candidate.source) == mem::discriminant(&stream.source)
+            && candidate.title == stream.title
+            && candidate.behavior_hints == stream.behavior_hints
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(url: &str) -> Option<DateTime<Utc>> {
+        parse_expires(&Url::parse(url).unwrap())
+    }
+
+    #[test]
+    fn expires_query_param() {
+        assert_eq!(
+            parse("https://cdn.example.com/video.mp4?expires=1700000000"),
+            Some(Utc.timestamp(1_700_000_000, 0))
+        );
+        assert_eq!(
+            parse("https://cdn.example.com/video.mp4?exp=1700000000&token=abc"),
+            Some(Utc.timestamp(1_700_000_000, 0))
+        );
+    }
+
+    #[test]
+    fn amz_presigned_url() {
+        assert_eq!(
+            parse("https://bucket.s3.amazonaws.com/video.mp4?X-Amz-Date=20231114T221320Z&X-Amz-Expires=3600"),
+            Some(Utc.ymd(2023, 11, 14).and_hms(23, 13, 20))
+        );
+    }
+
+    #[test]
+    fn out_of_range_values() {
+        assert_eq!(
+            parse("https://cdn.example.com/video.mp4?expires=99999999999999999"),
+            None
+        );
+        assert_eq!(
+            parse("https://bucket.s3.amazonaws.com/video.mp4?X-Amz-Date=20231114T221320Z&X-Amz-Expires=9223372036854775807"),
+            None
+        );
+        assert_eq!(
+            parse("https://bucket.s3.amazonaws.com/video.mp4?X-Amz-Date=20231114T221320Z&X-Amz-Expires=-1"),
+            None
+        );
+    }
+
+    #[test]
+    fn unrelated_query_params() {
+        assert_eq!(
+            parse("https://cdn.example.com/video.mp4?e=1700000000"),
+            None
+        );
+        assert_eq!(
+            parse("https://cdn.example.com/video.mp4?expires=tomorrow"),
+            None
+        );
+        assert_eq!(parse("https://cdn.example.com/video.mp4"), None);
+    }
+}
@@ -0,0 +1,83 @@
+use serde::Serialize;
+use std::cell::RefCell;
+use std::collections::{HashMap, VecDeque};
+use stremio_core::types::resource::{Stream, StreamSource};
+use url::Url;
+
+const SEEDER_MARKERS: &[&str] = &["👤", "seeders", "seeds"];
+const INSTANT_SEEDERS: u32 = 50;
+const SHORT_WAIT_SEEDERS: u32 = 10;
+const INSTANT_START_DELAY: f64 = 5000.0;
+const SHORT_WAIT_START_DELAY: f64 = 20_000.0;
+const MAX_START_DELAYS: usize = 20;
+
+thread_local! {
+    static START_DELAYS: RefCell<HashMap<Url, VecDeque<i64>>> = Default::default();
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum WaitTier {
+    Instant,
+    ShortWait,
+    Slow,
+}
+
+// Addons put the seeders in the title in all sorts of ways, e.g. "👤 123" or "Seeders: 123"
+pub fn seeders(title: &str) -> Option<u32> {
+    let title = title.to_lowercase();
+    SEEDER_MARKERS.iter().find_map(|marker| {
+        title.match_indices(marker).find_map(|(position, _)| {
+            let digits = title[position + marker.len()..]
+                .trim_start_matches(|character: char| character == ':' || character.is_whitespace())
+                .chars()
+                .take_while(|character| character.is_ascii_digit())
+                .collect::<String>();
+            digits.parse::<u32>().ok()
+        })
+    })
+}
+
+pub fn record_start_delay(addon_transport_url: Url, start_delay: i64) {
+    START_DELAYS.with(|start_delays| {
+        let mut start_delays = start_delays.borrow_mut();
+        let start_delays = start_delays.entry(addon_transport_url).or_default();
+        if start_delays.len() >= MAX_START_DELAYS {
+            start_delays.pop_front();
+        };
+        start_delays.push_back(start_delay);
+    });
+}
+
+fn average_start_delay(addon_transport_url: &Url) -> Option<f64> {
+    START_DELAYS.with(|start_delays| {
+        start_delays
+            .borrow()
+            .get(addon_transport_url)
+            .filter(|start_delays| !start_delays.is_empty())
+            .map(|start_delays| start_delays.iter().sum::<i64>() as f64 / start_delays.len() as f64)
+    })
+}
+
+// Seeders in the title are the best hint, torrents from the same addon starting slowly before is the next one
+pub fn wait_tier(stream: &Stream, addon_transport_url: Option<&Url>) -> Option<WaitTier> {
+    if !matches!(stream.source, StreamSource::Torrent { .. }) {
+        return None;
+    };
+    match stream.title.as_deref().and_then(seeders) {
+        Some(seeders) if seeders >= INSTANT_SEEDERS => Some(WaitTier::Instant),
+        Some(seeders) if seeders >= SHORT_WAIT_SEEDERS => Some(WaitTier::ShortWait),
+        Some(_) => Some(WaitTier::Slow),
+        None => addon_transport_url
+            .and_then(average_start_delay)
+            .map(|start_delay| {
+                if start_delay < INSTANT_START_DELAY {
+                    WaitTier::Instant
+                } else if start_delay < SHORT_WAIT_START_DELAY {
+                    WaitTier::ShortWait
+                } else {
+                    WaitTier::Slow
+                }
+            }),
+    }
+}
@@ -1,46 +1,224 @@
-use crate::env::WebEnv;
-use crate::model::WebModel;
-use futures::{future, StreamExt};
+use crate::account;
+use crate::action_error;
+use crate::action_settlement::{self, Settlement};
+use crate::addon_mirrors::{self, ADDON_MIRRORS_STORAGE_KEY};
+use crate::addon_priorities::{self, ADDON_PRIORITIES_STORAGE_KEY};
+use crate::addon_stats;
+use crate::analytics::{self, ANALYTICS_ENABLED_STORAGE_KEY, ANALYTICS_INSTALL_ID_STORAGE_KEY};
+use crate::analytics_route;
+use crate::artwork::{ArtworkSelection, ARTWORK_UI_STATE_KEY};
+use crate::board_layout::{self, BoardLayout, BOARD_LAYOUTS_STORAGE_KEY};
+use crate::debug_log::{self, RecordedDebugLog};
+use crate::dev_addon;
+use crate::effect_groups;
+use crate::env::{FetchOptions, WebEnv};
+use crate::episode_numbering::{self, EpisodeNumbering, EPISODE_NUMBERING_STORAGE_KEY};
+use crate::errors_feed;
+use crate::event::{self, WebEvent};
+use crate::external_subtitles::{self, ExternalSubtitlesSource, EXTERNAL_SUBTITLES_STORAGE_KEY};
+use crate::home_layout::{self, HomeLayout, HOME_LAYOUTS_STORAGE_KEY};
+use crate::incognito;
+use crate::init_options::InitOptions;
+use crate::js_value::{from_js_value, to_js_value};
+use crate::library_backup::{
+    self, BackupConfigRequest, BackupSecrets, BackupSnapshot, BackupStatus, LibraryBackup,
+    BACKUP_SNAPSHOT_VERSION, LIBRARY_BACKUP_STORAGE_KEY,
+};
+use crate::library_recent::{self, RecentPolicy, LIBRARY_RECENT_POLICY_STORAGE_KEY};
+use crate::library_refresh::{self, LibraryItemRefresh, MetaResponse};
+use crate::manifest_validation;
+use crate::media_support;
+use crate::meta_prefetch::{self, VisibleItem};
+use crate::middleware;
+use crate::model::{self, WebModel, WebModelField};
+use crate::mounted_fields;
+use crate::panic_hook;
+use crate::pending_actions;
+use crate::pending_removal;
+use crate::permissions;
+use crate::play_queue;
+use crate::player_analytics;
+use crate::ratings::{self, Rating, RATINGS_STORAGE_KEY};
+use crate::request_limiter;
+use crate::route_timing;
+use crate::runtime_pause;
+use crate::session_refresh::{self, GetUserResponse};
+use crate::settings_effect;
+use crate::share;
+use crate::shuffle_play::{
+    self, CatalogResponse, ShuffleCandidate, ShufflePick, ShufflePlay, ShuffleSource,
+};
+use crate::state_selector;
+use crate::storage_writes;
+use crate::stream_expiry::{self, StreamsResponse};
+use crate::stream_wait;
+use crate::subscriptions;
+use crate::support_bundle;
+use crate::torrent_files::{
+    self, CreateTorrentRequest, CreateTorrentResponse, FileResolution, TorrentSource,
+};
+use crate::ui_state::{self, UiState, UI_STATE_STORAGE_KEY};
+use crate::version_info;
+use crate::watch_limits::{
+    self, WatchLimitError, WatchLimitsRequest, WatchLimitsState, WATCH_LIMITS_STORAGE_KEY,
+};
+use crate::watch_session::{self, WatchSessionChange};
+use futures::future::AbortHandle;
+use futures::{future, FutureExt, StreamExt};
+use http::Request;
 use lazy_static::lazy_static;
+use serde::Serialize;
+use std::cell::{Cell, RefCell};
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::sync::RwLock;
 use stremio_core::constants::{
-    LIBRARY_RECENT_STORAGE_KEY, LIBRARY_STORAGE_KEY, PROFILE_STORAGE_KEY,
+    LIBRARY_RECENT_STORAGE_KEY, LIBRARY_STORAGE_KEY, META_RESOURCE_NAME, PROFILE_STORAGE_KEY,
+    SCHEMA_VERSION_STORAGE_KEY,
 };
 use stremio_core::models::common::Loadable;
-use stremio_core::runtime::{Env, EnvError, Runtime};
+use stremio_core::runtime::msg::{Action, ActionCtx, ActionLoad, Event};
+use stremio_core::runtime::{Env, EnvError, Runtime, RuntimeEvent};
+use stremio_core::types::addon::{Descriptor, Manifest, ResourcePath, ResourceRequest};
 use stremio_core::types::library::LibraryBucket;
 use stremio_core::types::profile::Profile;
+use stremio_core::types::resource::{Stream, StreamSource, Subtitles};
+use url::Url;
+use wasm_bindgen::closure::Closure;
 use wasm_bindgen::prelude::wasm_bindgen;
-use wasm_bindgen::JsValue;
+use wasm_bindgen::{JsCast, JsValue};
+
+// Re-resolution happens right before playback, a slow addon shouldn't hold it up
+const STREAM_RE_RESOLVE_TIMEOUT: u32 = 10_000;
+const RX_CHUNK_CAPACITY: usize = 100;
+const DEFAULT_RUNTIME_BUFFER_SIZE: usize = 1000;
+const ROUTE_FIELDS: &[&str] = &[
+    "board",
+    "discover",
+    "library",
+    "continue_watching",
+    "search",
+    "meta_details",
+    "remote_addons",
+    "installed_addons",
+    "addon_details",
+    "player",
+];
+// Modals are loaded over a route, which stays loaded underneath
+const OVERLAY_ROUTE_FIELDS: &[&str] = &["addon_details"];
+
+#[derive(Serialize)]
+#[serde(tag = "type")]
+enum RuntimeError {
+    NotReady,
+}
+
+enum DispatchOutcome {
+    Dispatched(Action, u32),
+    Skipped(&'static str),
+}
 
 lazy_static! {
     static ref RUNTIME: RwLock<Option<Loadable<Runtime<WebEnv, WebModel>, EnvError>>> =
         Default::default();
 }
 
+thread_local! {
+    static RX_LOOP_HANDLE: RefCell<Option<AbortHandle>> = Default::default();
+    static NEW_STATE_DEBOUNCE: Cell<Option<u32>> = Cell::new(None);
+    static NEW_STATE_TIMEOUT_HANDLE: Cell<Option<i32>> = Cell::new(None);
+}
+
 #[wasm_bindgen(start)]
 pub fn start() {
-    std::panic::set_hook(Box::new(console_error_panic_hook::hook));
+    std::panic::set_hook(Box::new(panic_hook::hook));
 }
 
 #[wasm_bindgen]
-pub async fn initialize_runtime(emit: js_sys::Function) -> Result<(), JsValue> {
+pub async fn initialize_runtime(
+    emit: js_sys::Function,
+    options: JsValue,
+    get_app_context: Option<js_sys::Function>,
+    on_panic: Option<js_sys::Function>,
+) -> Result<(), JsValue> {
+    panic_hook::set_handler(on_panic);
     if RUNTIME.read().expect("runtime read failed").is_some() {
-        panic!("unable to initialize runtime multiple times");
+        return Err(JsValue::from_str(
+            "unable to initialize runtime multiple times, destroy it first",
+        ));
+    };
+    let options = if options.is_undefined() || options.is_null() {
+        InitOptions::default()
+    } else {
+        from_js_value::<InitOptions>(options)
+            .map_err(|error| JsValue::from_str(&error.to_string()))?
     };
+    permissions::init(options.permissions);
+    media_support::init(options.media_types);
+    analytics::init(options.analytics_url);
+    analytics::set_get_app_context(get_app_context);
+    request_limiter::init(options.max_concurrent_addon_requests);
+    WebEnv::set_storage_key_prefix(options.storage_key_prefix.unwrap_or_default());
+    let new_state_debounce = options.new_state_debounce.filter(|debounce| *debounce > 0);
+    NEW_STATE_DEBOUNCE.with(|current| current.set(new_state_debounce));
+    let runtime_buffer_size = options
+        .runtime_buffer_size
+        .unwrap_or(DEFAULT_RUNTIME_BUFFER_SIZE);
 
     *RUNTIME.write().expect("runtime write failed") = Some(Loadable::Loading);
     let migration_result = WebEnv::migrate_storage_schema().await;
     match migration_result {
         Ok(_) => {
-            let storage_result = future::try_join3(
-                WebEnv::get_storage::<Profile>(PROFILE_STORAGE_KEY),
-                WebEnv::get_storage::<LibraryBucket>(LIBRARY_RECENT_STORAGE_KEY),
-                WebEnv::get_storage::<LibraryBucket>(LIBRARY_STORAGE_KEY),
+            let storage_result = future::try_join4(
+                future::try_join4(
+                    WebEnv::get_storage::<Profile>(PROFILE_STORAGE_KEY),
+                    WebEnv::get_storage::<LibraryBucket>(LIBRARY_RECENT_STORAGE_KEY),
+                    WebEnv::get_storage::<LibraryBucket>(LIBRARY_STORAGE_KEY),
+                    WebEnv::get_storage::<EpisodeNumbering>(EPISODE_NUMBERING_STORAGE_KEY),
+                ),
+                future::try_join4(
+                    WebEnv::get_storage::<UiState>(UI_STATE_STORAGE_KEY),
+                    WebEnv::get_storage::<BTreeMap<Url, Vec<Url>>>(ADDON_MIRRORS_STORAGE_KEY),
+                    WebEnv::get_storage::<HashMap<Url, i32>>(ADDON_PRIORITIES_STORAGE_KEY),
+                    WebEnv::get_storage::<HashMap<String, HashMap<String, Rating>>>(
+                        RATINGS_STORAGE_KEY,
+                    ),
+                ),
+                future::try_join4(
+                    WebEnv::get_storage::<RecentPolicy>(LIBRARY_RECENT_POLICY_STORAGE_KEY),
+                    WebEnv::get_storage::<String>(ANALYTICS_INSTALL_ID_STORAGE_KEY),
+                    WebEnv::get_storage::<WatchLimitsState>(WATCH_LIMITS_STORAGE_KEY),
+                    WebEnv::get_storage::<HashMap<String, HomeLayout>>(HOME_LAYOUTS_STORAGE_KEY),
+                ),
+                future::try_join4(
+                    WebEnv::get_storage::<HashMap<String, BoardLayout>>(BOARD_LAYOUTS_STORAGE_KEY),
+                    WebEnv::get_storage::<HashMap<String, Vec<Subtitles>>>(
+                        EXTERNAL_SUBTITLES_STORAGE_KEY,
+                    ),
+                    WebEnv::get_storage::<bool>(ANALYTICS_ENABLED_STORAGE_KEY),
+                    WebEnv::get_storage::<LibraryBackup>(LIBRARY_BACKUP_STORAGE_KEY),
+                ),
             )
             .await;
             match storage_result {
-                Ok((profile, recent_bucket, other_bucket)) => {
+                Ok((
+                    (profile, recent_bucket, other_bucket, episode_numbering),
+                    (ui_state, addon_mirrors, addon_priorities, ratings),
+                    (library_recent_policy, analytics_install_id, watch_limits, home_layouts),
+                    (board_layouts, external_subtitles, analytics_enabled, library_backup),
+                )) => {
+                    ui_state::init(ui_state.unwrap_or_default());
+                    addon_mirrors::init(addon_mirrors.unwrap_or_default());
+                    addon_priorities::init(addon_priorities.unwrap_or_default());
+                    ratings::init(ratings.unwrap_or_default());
+                    library_recent::init(library_recent_policy.unwrap_or_default());
+                    episode_numbering::init(episode_numbering.unwrap_or_default());
+                    board_layout::init(board_layouts.unwrap_or_default());
+                    home_layout::init(home_layouts.unwrap_or_default());
+                    external_subtitles::init(external_subtitles.unwrap_or_default());
+                    analytics::set_enabled(analytics_enabled.unwrap_or(true));
+                    library_backup::init(library_backup.unwrap_or_default());
+                    watch_limits::init(watch_limits.unwrap_or_default());
+                    analytics::start_session(analytics_install_id);
                     let profile = profile.unwrap_or_default();
                     let mut library = LibraryBucket::new(profile.uid(), vec![]);
                     if let Some(recent_bucket) = recent_bucket {
@@ -50,56 +228,1642 @@ pub async fn initialize_runtime(emit: js_sys::Function) -> Result<(), JsValue> {
                         library.merge_bucket(other_bucket);
                     };
                     let (model, effects) = WebModel::new(profile, library);
-                    let (runtime, rx) = Runtime::<WebEnv, _>::new(model, effects, 1000);
-                    WebEnv::exec(rx.for_each(move |msg| {
-                        emit.call1(&JsValue::NULL, &JsValue::from_serde(&msg).unwrap())
-                            .expect("emit event failed");
+                    let (runtime, rx) =
+                        Runtime::<WebEnv, _>::new(model, effects, runtime_buffer_size);
+                    event::set_emit(emit);
+                    // Messages that are ready together, like the ones of a dispatched batch,
+                    // are emitted with a single trailing NewState
+                    let rx_loop = rx.ready_chunks(RX_CHUNK_CAPACITY).for_each(|msgs| {
+                        let mut new_state = false;
+                        let mut session_refreshed = false;
+                        let mut events = vec![];
+                        for msg in msgs {
+                            match msg {
+                                RuntimeEvent::NewState => {
+                                    new_state = true;
+                                }
+                                RuntimeEvent::CoreEvent(event) => {
+                                    match &event {
+                                        Event::Error { .. } => {
+                                            watch_session::record_error();
+                                            support_bundle::record_error(&event);
+                                            errors_feed::record_event(&event);
+                                        }
+                                        Event::UserAuthenticated { .. } => {
+                                            WebEnv::exec(load_account_details());
+                                        }
+                                        Event::UserLoggedOut { .. } => account::clear(),
+                                        _ => {}
+                                    };
+                                    if session_refresh::complete_pull(&event) {
+                                        session_refreshed = true;
+                                    };
+                                    event::emit(&RuntimeEvent::CoreEvent(event.to_owned()));
+                                    events.push(event);
+                                }
+                            };
+                        }
+                        if new_state {
+                            if let Some(Loadable::Ready(runtime)) =
+                                &*RUNTIME.read().expect("runtime read failed")
+                            {
+                                let model = runtime.model().expect("model read failed");
+                                route_timing::check(&model, js_sys::Date::now());
+                                if analytics::is_enabled() {
+                                    analytics_route::update(&model);
+                                };
+                            };
+                            schedule_new_state();
+                        };
+                        action_settlement::settle(&events);
+                        if session_refreshed {
+                            finish_session_refresh();
+                        };
                         future::ready(())
-                    }));
+                    });
+                    let (rx_loop, rx_loop_handle) = future::abortable(rx_loop);
+                    WebEnv::exec(rx_loop.map(|_| ()));
+                    RX_LOOP_HANDLE.with(|current| *current.borrow_mut() = Some(rx_loop_handle));
                     *RUNTIME.write().expect("runtime write failed") =
                         Some(Loadable::Ready(runtime));
+                    session_refresh::watch(|| WebEnv::exec(refresh_session()));
+                    WebEnv::exec(load_account_details());
+                    library_backup::watch(|| {
+                        if !runtime_pause::is_paused() && library_backup::is_due(WebEnv::now()) {
+                            WebEnv::exec(run_library_backup().map(|_| ()));
+                        };
+                    });
+                    flush_pending_actions();
                     Ok(())
                 }
                 Err(error) => {
                     *RUNTIME.write().expect("runtime write failed") =
                         Some(Loadable::Err(error.to_owned()));
-                    Err(JsValue::from_serde(&error).unwrap())
+                    Err(to_js_value(&error).unwrap())
                 }
             }
         }
         Err(error) => {
             *RUNTIME.write().expect("runtime write failed") = Some(Loadable::Err(error.to_owned()));
-            Err(JsValue::from_serde(&error).unwrap())
+            Err(to_js_value(&error).unwrap())
         }
     }
 }
 
 #[wasm_bindgen]
-pub fn get_state(field: &JsValue) -> JsValue {
+pub fn destroy_runtime() -> Result<(), JsValue> {
+    let mut runtime = RUNTIME.write().expect("runtime write failed");
+    if let Some(Loadable::Loading) = &*runtime {
+        return Err(JsValue::from_str(
+            "unable to destroy runtime while it is initializing",
+        ));
+    };
+    // Effects in flight are dropped, the removals committed below still get theirs
+    WebEnv::cancel_effects();
+    if let Some(Loadable::Ready(runtime)) = &*runtime {
+        for (id, settlements) in pending_removal::flush() {
+            dispatch_removal(runtime, id, settlements);
+        }
+    };
+    if let Some(rx_loop_handle) = RX_LOOP_HANDLE.with(|current| current.borrow_mut().take()) {
+        rx_loop_handle.abort();
+    };
+    if let Some(handle) = NEW_STATE_TIMEOUT_HANDLE.with(|handle| handle.take()) {
+        web_sys::window()
+            .expect("window is not available")
+            .clear_timeout_with_handle(handle);
+    };
+    storage_writes::flush();
+    pending_actions::clear();
+    action_settlement::clear();
+    effect_groups::clear();
+    session_refresh::finish();
+    library_backup::unwatch();
+    runtime_pause::clear();
+    ui_state::clear();
+    ratings::clear();
+    errors_feed::clear();
+    subscriptions::clear();
+    mounted_fields::clear();
+    model::take_changed_fields();
+    incognito::set_enabled(false);
+    debug_log::clear();
+    *runtime = None;
+    Ok(())
+}
+
+#[wasm_bindgen]
+pub fn add_event_listener(event_name: String, callback: js_sys::Function) -> u32 {
+    event::add_event_listener(event_name, callback)
+}
+
+#[wasm_bindgen]
+pub fn remove_event_listener(id: u32) -> bool {
+    event::remove_event_listener(id)
+}
+
+#[wasm_bindgen]
+pub fn get_runtime_status() -> JsValue {
+    match &*RUNTIME.read().expect("runtime read failed") {
+        None => JsValue::from_str("NotInitialized"),
+        Some(Loadable::Loading) => JsValue::from_str("Loading"),
+        Some(Loadable::Ready(_)) => JsValue::from_str("Ready"),
+        Some(Loadable::Err(error)) => to_js_value(&serde_json::json!({ "error": error })).unwrap(),
+    }
+}
+
+#[wasm_bindgen]
+pub fn get_state(field: &JsValue) -> Result<JsValue, JsValue> {
     match &*RUNTIME.read().expect("runtime read failed") {
         Some(Loadable::Ready(runtime)) => {
             let model = runtime.model().expect("model read failed");
-            match field.into_serde() {
-                Ok(field) => model.get_state(&field),
-                Err(_) => JsValue::NULL,
+            match field.as_string() {
+                Some(selector) if state_selector::is_selector(&selector) => {
+                    let selector = state_selector::parse(&selector)
+                        .map_err(|error| JsValue::from_str(&error))?;
+                    let state =
+                        state_selector::select(&model.field_state(&selector.field), &selector.path)
+                            .map_err(|error| JsValue::from_str(&error.to_string()))?;
+                    Ok(state
+                        .map(|state| to_js_value(&state).unwrap())
+                        .unwrap_or(JsValue::UNDEFINED))
+                }
+                _ => match from_js_value(field.to_owned()) {
+                    Ok(field) => Ok(model.get_state(&field)),
+                    Err(_) => Ok(JsValue::NULL),
+                },
             }
         }
-        _ => panic!("runtime is not ready"),
+        _ => Err(runtime_not_ready()),
     }
 }
 
 #[wasm_bindgen]
-pub fn dispatch(action: &JsValue, field: &JsValue) {
+pub fn subscribe(
+    field: JsValue,
+    callback: js_sys::Function,
+    patches: Option<bool>,
+) -> Result<u32, JsValue> {
+    subscriptions::subscribe(field, callback, patches.unwrap_or(false))
+        .map_err(|error| JsValue::from_str(&error))
+}
+
+#[wasm_bindgen]
+pub fn unsubscribe(id: u32) -> bool {
+    subscriptions::unsubscribe(id)
+}
+
+// Unmounted fields skip their updates and subscriptions until they are mounted again
+#[wasm_bindgen]
+pub fn mount_field(field: &JsValue) -> Result<(), JsValue> {
+    let field = from_js_value::<WebModelField>(field.to_owned())
+        .map_err(|error| JsValue::from_str(&error.to_string()))?;
+    if !mounted_fields::mount(&field) {
+        return Ok(());
+    };
     match &*RUNTIME.read().expect("runtime read failed") {
-        Some(Loadable::Ready(runtime)) => match (action.into_serde(), field.into_serde()) {
-            (Ok(action), Ok(field)) => {
+        Some(Loadable::Ready(runtime)) => {
+            let reload_action = runtime
+                .model()
+                .expect("model read failed")
+                .reload_action(&field);
+            if let Some(action) = reload_action {
                 runtime.dispatch_to_field(action, &field);
+            };
+        }
+        _ => return Ok(()),
+    };
+    model::mark_changed(&[field]);
+    notify_subscriptions();
+    Ok(())
+}
+
+#[wasm_bindgen]
+pub fn unmount_field(field: &JsValue) -> Result<(), JsValue> {
+    let field = from_js_value::<WebModelField>(field.to_owned())
+        .map_err(|error| JsValue::from_str(&error.to_string()))?;
+    mounted_fields::unmount(&field);
+    Ok(())
+}
+
+#[wasm_bindgen]
+pub fn dispatch(action: &JsValue, field: &JsValue) -> Result<(), JsValue> {
+    dispatch_action(action, field, &mut None).map(|_| ())
+}
+
+// Actions that have to wait take the settlement with them, the caller settles every other outcome
+fn dispatch_action(
+    action: &JsValue,
+    field: &JsValue,
+    settlement: &mut Option<Settlement>,
+) -> Result<DispatchOutcome, JsValue> {
+    permissions::check(middleware::action_path(action))
+        .map_err(|error| to_js_value(&error).unwrap())?;
+    if session_refresh::is_refreshing()
+        || matches!(
+            &*RUNTIME.read().expect("runtime read failed"),
+            None | Some(Loadable::Loading)
+        )
+    {
+        pending_actions::push(action.to_owned(), field.to_owned(), settlement.take());
+        return Ok(DispatchOutcome::Skipped("action was queued"));
+    };
+    // Queued actions are recorded once they are flushed, so a replay keeps the original order
+    debug_log::record_action(action, field);
+    let action = match middleware::apply_middlewares(action, field) {
+        Some(action) => action,
+        None => {
+            return Ok(DispatchOutcome::Skipped(
+                "action was dropped by a middleware",
+            ))
+        }
+    };
+    permissions::check(middleware::action_path(&action))
+        .map_err(|error| to_js_value(&error).unwrap())?;
+    match &*RUNTIME.read().expect("runtime read failed") {
+        Some(Loadable::Ready(runtime)) => {
+            let action = action_error::deserialize_action(&action)
+                .map_err(|error| to_js_value(&error).unwrap())?;
+            let field = from_js_value::<WebModelField>(field.to_owned()).ok();
+            watch_limits::check(&action).map_err(|error| to_js_value(&error).unwrap())?;
+            match &action {
+                Action::Ctx(ActionCtx::RemoveFromLibrary(id)) => {
+                    pending_removal::schedule(
+                        id.to_owned(),
+                        settlement.take(),
+                        commit_scheduled_removal,
+                    );
+                    emit_new_state();
+                    return Ok(DispatchOutcome::Skipped("library removal is pending"));
+                }
+                Action::Ctx(ActionCtx::Logout) => {
+                    for (id, settlements) in pending_removal::flush() {
+                        dispatch_removal(runtime, id, settlements);
+                    }
+                }
+                _ => {}
+            };
+            if let Action::Load(ActionLoad::Player(selected)) = &action {
+                if let Some(info_hash) = torrent_files::unresolved_info_hash(&selected.stream) {
+                    if torrent_files::start(&info_hash) {
+                        let streaming_server_url = runtime
+                            .model()
+                            .expect("model read failed")
+                            .ctx
+                            .profile
+                            .settings
+                            .streaming_server_url
+                            .to_owned();
+                        let episode = selected
+                            .stream_request
+                            .as_ref()
+                            .and_then(|request| torrent_files::video_episode(&request.path.id));
+                        WebEnv::exec(resolve_torrent_file(
+                            streaming_server_url,
+                            info_hash,
+                            episode,
+                        ));
+                    };
+                };
+            };
+            if let Some(watch_limits) = watch_limits::record(&action) {
+                WebEnv::exec(
+                    WebEnv::set_storage(WATCH_LIMITS_STORAGE_KEY, Some(&watch_limits)).map(|_| ()),
+                );
+            };
+            if !incognito::is_allowed(&action) {
+                return Ok(DispatchOutcome::Skipped(
+                    "action was dropped in incognito mode",
+                ));
+            };
+            if !incognito::is_enabled() {
+                if let (true, Action::Ctx(ActionCtx::InstallAddon(descriptor))) =
+                    (analytics::is_enabled(), &action)
+                {
+                    analytics::track(
+                        "InstallAddon",
+                        serde_json::json!({
+                            "addonId": descriptor.manifest.id,
+                            "addonTransportUrl": descriptor.transport_url,
+                        }),
+                    );
+                };
+                if analytics::is_enabled() {
+                    route_timing::start(&action, field.as_ref(), js_sys::Date::now());
+                };
+                route_timing::cancel(&action, field.as_ref());
+                analytics_route::navigate(&action, field.as_ref());
+                for change in watch_session::update(&action, field.as_ref()) {
+                    player_analytics::track(&change);
+                    if let WatchSessionChange::Playing {
+                        stream:
+                            Stream {
+                                source: StreamSource::Torrent { .. },
+                                ..
+                            },
+                        addon_transport_url: Some(addon_transport_url),
+                        start_delay,
+                    } = &change
+                    {
+                        stream_wait::record_start_delay(
+                            addon_transport_url.to_owned(),
+                            *start_delay,
+                        );
+                    };
+                    if let WatchSessionChange::Ended(summary) = change {
+                        event::emit(&WebEvent::WatchSessionEnded(summary));
+                    };
+                }
+            };
+            let restart_required = match &action {
+                Action::Ctx(ActionCtx::UpdateSettings(settings)) => {
+                    settings_effect::restart_required(
+                        &runtime
+                            .model()
+                            .expect("model read failed")
+                            .ctx
+                            .profile
+                            .settings,
+                        settings,
+                    )
+                }
+                _ => None,
+            };
+            if let Action::Load(_) = &action {
+                if let Some(route) = field.as_ref().and_then(route_timing::route_name) {
+                    WebEnv::start_route_load(route);
+                };
+            };
+            let group_id = effect_groups::run(|| match field {
+                Some(field) => runtime.dispatch_to_field(action.to_owned(), &field),
+                None => runtime.dispatch(action.to_owned()),
+            });
+            if let Some(restart_required) = restart_required {
+                event::emit(&WebEvent::RestartRequired(restart_required));
+            };
+            Ok(DispatchOutcome::Dispatched(action, group_id))
+        }
+        _ => Err(runtime_not_ready()),
+    }
+}
+
+#[wasm_bindgen]
+pub fn dispatch_async(action: &JsValue, field: &JsValue) -> Result<js_sys::Promise, JsValue> {
+    let (promise, settlement) = action_settlement::promise();
+    let mut settlement = Some(settlement);
+    let outcome = dispatch_action(action, field, &mut settlement)?;
+    if let Some(settlement) = settlement {
+        settle_dispatch(settlement, Ok(outcome));
+    };
+    Ok(promise)
+}
+
+// Core events reach the rx loop asynchronously, so settling is registered after dispatch
+fn settle_dispatch(settlement: Settlement, outcome: Result<DispatchOutcome, JsValue>) {
+    match outcome {
+        Ok(DispatchOutcome::Dispatched(action, group_id)) => {
+            action_settlement::register(&action, group_id, settlement)
+        }
+        Ok(DispatchOutcome::Skipped(reason)) => settlement.reject(&JsValue::from_str(reason)),
+        Err(error) => settlement.reject(&error),
+    }
+}
+
+#[wasm_bindgen]
+pub fn dispatch_batch(actions: JsValue, field: &JsValue) -> Result<(), JsValue> {
+    if !js_sys::Array::is_array(&actions) {
+        return Err(JsValue::from_str("actions must be an array"));
+    };
+    let actions = js_sys::Array::from(&actions).iter().collect::<Vec<_>>();
+    for action in actions.iter() {
+        let core_action = action_error::deserialize_action(action)
+            .map_err(|error| to_js_value(&error).unwrap())?;
+        watch_limits::check(&core_action).map_err(|error| to_js_value(&error).unwrap())?;
+        permissions::check(middleware::action_path(action))
+            .map_err(|error| to_js_value(&error).unwrap())?;
+    }
+    for action in actions.iter() {
+        dispatch(action, field)?;
+    }
+    Ok(())
+}
+
+#[wasm_bindgen]
+pub fn load_route(action: &JsValue, field: &JsValue) -> Result<(), JsValue> {
+    let field_name = field
+        .as_string()
+        .ok_or_else(|| JsValue::from_str("route field must be a string"))?;
+    if OVERLAY_ROUTE_FIELDS.contains(&field_name.as_str()) {
+        return dispatch(action, field);
+    };
+    let route_fields = ROUTE_FIELDS
+        .iter()
+        .filter(|route_field| **route_field != field_name);
+    let unload_fields = match &*RUNTIME.read().expect("runtime read failed") {
+        Some(Loadable::Ready(runtime)) => {
+            let model = runtime.model().expect("model read failed");
+            route_fields
+                .filter(|route_field| {
+                    from_js_value::<WebModelField>(JsValue::from_str(route_field))
+                        .map(|route_field| model.is_loaded(&route_field))
+                        .unwrap_or_default()
+                })
+                .collect::<Vec<_>>()
+        }
+        _ => route_fields.collect::<Vec<_>>(),
+    };
+    // Only loaded models are unloaded, in the same pass as the load,
+    // so the rx loop emits all of their changes with a single NewState
+    let unload = to_js_value(&serde_json::json!({ "action": "Unload" })).unwrap();
+    for route_field in unload_fields {
+        dispatch(&unload, &JsValue::from_str(route_field))?;
+    }
+    dispatch(action, field)
+}
+
+#[wasm_bindgen]
+pub fn dismiss_error(id: String) {
+    if errors_feed::dismiss(&id) {
+        emit_new_state();
+    };
+}
+
+#[wasm_bindgen]
+pub fn set_flush_pending_actions(flush: bool) {
+    pending_actions::set_flush(flush);
+}
+
+#[wasm_bindgen]
+pub fn get_analytics_context() -> Result<JsValue, JsValue> {
+    match &*RUNTIME.read().expect("runtime read failed") {
+        Some(Loadable::Ready(runtime)) => {
+            let model = runtime.model().expect("model read failed");
+            Ok(to_js_value(&analytics_route::context(&model)).unwrap())
+        }
+        _ => Err(runtime_not_ready()),
+    }
+}
+
+#[wasm_bindgen]
+pub fn register_middleware(middleware: js_sys::Function) {
+    middleware::register_middleware(middleware);
+}
+
+#[wasm_bindgen]
+pub async fn reload_dev_addon(transport_url: String) -> Result<JsValue, JsValue> {
+    permissions::check(vec!["Ctx".to_owned(), "InstallAddon".to_owned()])
+        .map_err(|error| to_js_value(&error).unwrap())?;
+    let transport_url =
+        Url::parse(&transport_url).map_err(|error| JsValue::from_str(&error.to_string()))?;
+    if !dev_addon::is_dev_addon_host(transport_url.host_str()) {
+        return Err(JsValue::from_str(
+            "dev addons must be served from localhost",
+        ));
+    };
+    let mut manifest_url = transport_url.to_owned();
+    manifest_url
+        .query_pairs_mut()
+        .append_pair("_", &WebEnv::now().timestamp_millis().to_string());
+    let request = Request::get(manifest_url.as_str())
+        .body(())
+        .expect("request builder failed");
+    let manifest = WebEnv::fetch::<_, Manifest>(request)
+        .await
+        .map_err(|error| to_js_value(&error).unwrap())?;
+    match &*RUNTIME.read().expect("runtime read failed") {
+        Some(Loadable::Ready(runtime)) => {
+            let installed_addon = runtime
+                .model()
+                .expect("model read failed")
+                .ctx
+                .profile
+                .addons
+                .iter()
+                .find(|addon| addon.transport_url == transport_url)
+                .cloned();
+            let changes = dev_addon::manifest_changes(
+                installed_addon.as_ref().map(|addon| &addon.manifest),
+                &manifest,
+            );
+            let changes = to_js_value(&changes).unwrap();
+            web_sys::console::log_2(&JsValue::from_str("dev addon changes"), &changes);
+            let descriptor = match installed_addon {
+                Some(installed_addon) => {
+                    runtime.dispatch(Action::Ctx(ActionCtx::UninstallAddon(
+                        installed_addon.to_owned(),
+                    )));
+                    Descriptor {
+                        manifest,
+                        ..installed_addon
+                    }
+                }
+                None => Descriptor {
+                    manifest,
+                    transport_url,
+                    flags: Default::default(),
+                },
+            };
+            runtime.dispatch(Action::Ctx(ActionCtx::InstallAddon(descriptor)));
+            Ok(changes)
+        }
+        _ => Err(runtime_not_ready()),
+    }
+}
+
+#[wasm_bindgen]
+pub fn get_data_saver() -> JsValue {
+    to_js_value(&WebEnv::data_saver()).unwrap()
+}
+
+#[wasm_bindgen]
+pub fn set_data_saver(enabled: Option<bool>) -> Result<(), JsValue> {
+    WebEnv::set_data_saver(enabled).map_err(|error| to_js_value(&error).unwrap())
+}
+
+// The shell calls this on visibilitychange and pagehide, so progress isn't lost on tab close
+#[wasm_bindgen]
+pub fn flush_storage() {
+    storage_writes::flush();
+}
+
+// Meant for hosting shells that background the webview, nothing runs until the runtime is resumed.
+// Pending storage writes are flushed right away since a backgrounded page may never come back.
+#[wasm_bindgen]
+pub fn pause_runtime() {
+    if runtime_pause::pause() {
+        storage_writes::flush();
+    };
+}
+
+#[wasm_bindgen]
+pub fn resume_runtime() {
+    if let Some(resumed) = runtime_pause::resume() {
+        event::replay(resumed.events);
+        for effect in resumed.effects {
+            WebEnv::exec(effect);
+        }
+        notify_subscriptions();
+    };
+}
+
+#[wasm_bindgen]
+pub fn get_diagnostics() -> JsValue {
+    to_js_value(&WebEnv::diagnostics()).unwrap()
+}
+
+#[wasm_bindgen]
+pub fn get_version_info() -> JsValue {
+    to_js_value(&version_info::version_info()).unwrap()
+}
+
+#[wasm_bindgen]
+pub fn set_removal_undo_window(undo_window: u32) {
+    pending_removal::set_undo_window(undo_window);
+}
+
+#[wasm_bindgen]
+pub fn undo_remove_from_library(id: String) -> bool {
+    let undone = pending_removal::undo(&id);
+    if undone {
+        emit_new_state();
+    };
+    undone
+}
+
+#[wasm_bindgen]
+pub fn flush_pending_removals() -> Result<(), JsValue> {
+    match &*RUNTIME.read().expect("runtime read failed") {
+        Some(Loadable::Ready(runtime)) => {
+            for (id, settlements) in pending_removal::flush() {
+                dispatch_removal(runtime, id, settlements);
+            }
+            Ok(())
+        }
+        _ => Err(runtime_not_ready()),
+    }
+}
+
+fn dispatch_removal(runtime: &Runtime<WebEnv, WebModel>, id: String, settlements: Vec<Settlement>) {
+    let action = Action::Ctx(ActionCtx::RemoveFromLibrary(id));
+    let group_id = effect_groups::run(|| runtime.dispatch(action.to_owned()));
+    for settlement in settlements {
+        action_settlement::register(&action, group_id, settlement);
+    }
+}
+
+// Runs from the undo window timer, only callers of dispatch_async hear about a dropped removal
+fn commit_scheduled_removal(id: String, settlements: Vec<Settlement>) {
+    match &*RUNTIME.read().expect("runtime read failed") {
+        Some(Loadable::Ready(runtime)) => dispatch_removal(runtime, id, settlements),
+        _ => {
+            let error = runtime_not_ready();
+            web_sys::console::error_2(&JsValue::from("pending removal dropped"), &error);
+            for settlement in settlements {
+                settlement.reject(&error);
+            }
+        }
+    };
+}
+
+#[wasm_bindgen]
+pub async fn set_item_ui_state(id: String, key: String, value: JsValue) -> Result<(), JsValue> {
+    let value = from_js_value::<serde_json::Value>(value)
+        .map_err(|error| JsValue::from_str(&error.to_string()))?;
+    let ui_state = ui_state::set(id, key, value).map_err(|error| JsValue::from_str(&error))?;
+    WebEnv::set_storage(UI_STATE_STORAGE_KEY, Some(&ui_state))
+        .await
+        .map_err(|error| to_js_value(&error).unwrap())?;
+    emit_new_state();
+    Ok(())
+}
+
+#[wasm_bindgen]
+pub async fn set_artwork_selection(id: String, selection: JsValue) -> Result<(), JsValue> {
+    let selection = from_js_value::<ArtworkSelection>(selection)
+        .map_err(|error| JsValue::from_str(&error.to_string()))?;
+    let selection =
+        serde_json::to_value(&selection).expect("artwork selection serialization failed");
+    let ui_state = ui_state::set(id, ARTWORK_UI_STATE_KEY.to_owned(), selection)
+        .map_err(|error| JsValue::from_str(&error))?;
+    WebEnv::set_storage(UI_STATE_STORAGE_KEY, Some(&ui_state))
+        .await
+        .map_err(|error| to_js_value(&error).unwrap())?;
+    emit_new_state();
+    Ok(())
+}
+
+#[wasm_bindgen]
+pub async fn export_support_bundle() -> Result<JsValue, JsValue> {
+    let stored_schema_version = WebEnv::get_storage::<u32>(SCHEMA_VERSION_STORAGE_KEY)
+        .await
+        .ok()
+        .flatten();
+    match &*RUNTIME.read().expect("runtime read failed") {
+        Some(Loadable::Ready(runtime)) => {
+            let model = runtime.model().expect("model read failed");
+            Ok(to_js_value(&support_bundle::support_bundle(
+                &model,
+                stored_schema_version,
+            ))
+            .unwrap())
+        }
+        _ => Err(runtime_not_ready()),
+    }
+}
+
+#[wasm_bindgen]
+pub fn set_debug_recording(recording: bool) {
+    debug_log::set_recording(recording);
+}
+
+#[wasm_bindgen]
+pub fn export_debug_log() -> String {
+    serde_json::to_string(&debug_log::debug_log()).expect("debug log serialization failed")
+}
+
+#[wasm_bindgen]
+pub fn replay_debug_log(recorded: String) -> Result<(), JsValue> {
+    let recorded = serde_json::from_str::<RecordedDebugLog>(&recorded)
+        .map_err(|error| JsValue::from_str(&error.to_string()))?;
+    for (action, field) in debug_log::replay_actions(recorded) {
+        dispatch(
+            &to_js_value(&action).unwrap(),
+            &to_js_value(&field).unwrap(),
+        )?;
+    }
+    Ok(())
+}
+
+#[wasm_bindgen]
+pub async fn set_addon_mirrors(transport_url: String, mirrors: JsValue) -> Result<(), JsValue> {
+    let transport_url =
+        Url::parse(&transport_url).map_err(|error| JsValue::from_str(&error.to_string()))?;
+    let mirrors = from_js_value::<Vec<Url>>(mirrors)
+        .map_err(|error| JsValue::from_str(&error.to_string()))?;
+    let addon_mirrors = addon_mirrors::set_mirrors(transport_url, mirrors);
+    WebEnv::set_storage(ADDON_MIRRORS_STORAGE_KEY, Some(&addon_mirrors))
+        .await
+        .map_err(|error| to_js_value(&error).unwrap())?;
+    emit_new_state();
+    Ok(())
+}
+
+#[wasm_bindgen]
+pub async fn set_addon_priority(transport_url: String, priority: i32) -> Result<(), JsValue> {
+    let transport_url =
+        Url::parse(&transport_url).map_err(|error| JsValue::from_str(&error.to_string()))?;
+    let addon_priorities = addon_priorities::set_priority(transport_url, priority);
+    WebEnv::set_storage(ADDON_PRIORITIES_STORAGE_KEY, Some(&addon_priorities))
+        .await
+        .map_err(|error| to_js_value(&error).unwrap())?;
+    emit_new_state();
+    Ok(())
+}
+
+#[wasm_bindgen]
+pub async fn pin_board_row(row_id: String, pinned: bool) -> Result<(), JsValue> {
+    let board_layouts = board_layout::set_pinned(&profile_uid()?, row_id, pinned);
+    persist_board_layouts(board_layouts).await
+}
+
+#[wasm_bindgen]
+pub async fn reorder_board_rows(row_ids: JsValue) -> Result<(), JsValue> {
+    let row_ids = from_js_value::<Vec<String>>(row_ids)
+        .map_err(|error| JsValue::from_str(&error.to_string()))?;
+    let board_layouts = board_layout::set_order(&profile_uid()?, row_ids);
+    persist_board_layouts(board_layouts).await
+}
+
+#[wasm_bindgen]
+pub async fn reset_board_layout() -> Result<(), JsValue> {
+    let board_layouts = board_layout::reset(&profile_uid()?);
+    persist_board_layouts(board_layouts).await
+}
+
+#[wasm_bindgen]
+pub fn get_home_state() -> Result<JsValue, JsValue> {
+    match &*RUNTIME.read().expect("runtime read failed") {
+        Some(Loadable::Ready(runtime)) => {
+            let model = runtime.model().expect("model read failed");
+            Ok(model.get_home(&home_layout::home_layout(&model.ctx.profile.uid())))
+        }
+        _ => Err(runtime_not_ready()),
+    }
+}
+
+#[wasm_bindgen]
+pub async fn set_home_layout(home_layout: JsValue) -> Result<(), JsValue> {
+    let home_layout = from_js_value::<HomeLayout>(home_layout)
+        .map_err(|error| JsValue::from_str(&error.to_string()))?;
+    if !home_layout.is_valid() {
+        return Err(JsValue::from_str("calendar days must be between 1 and 31"));
+    };
+    let home_layouts = home_layout::set(&profile_uid()?, home_layout);
+    persist_home_layouts(home_layouts).await
+}
+
+#[wasm_bindgen]
+pub async fn reset_home_layout() -> Result<(), JsValue> {
+    let home_layouts = home_layout::reset(&profile_uid()?);
+    persist_home_layouts(home_layouts).await
+}
+
+#[wasm_bindgen]
+pub async fn set_library_backup(config: JsValue) -> Result<(), JsValue> {
+    let config = if config.is_undefined() || config.is_null() {
+        None
+    } else {
+        let config = from_js_value::<BackupConfigRequest>(config)
+            .map_err(|error| JsValue::from_str(&error.to_string()))?;
+        if !config.is_valid() {
+            return Err(JsValue::from_str(
+                "library backup needs a passphrase and a positive interval",
+            ));
+        };
+        Some(config)
+    };
+    persist_library_backup(library_backup::set_config(config)).await
+}
+
+#[wasm_bindgen]
+pub fn unlock_library_backup(passphrase: String, password: Option<String>) -> Result<(), JsValue> {
+    if passphrase.is_empty() {
+        return Err(JsValue::from_str("library backup needs a passphrase"));
+    };
+    library_backup::set_secrets(Some(BackupSecrets {
+        passphrase,
+        password,
+    }));
+    emit_new_state();
+    Ok(())
+}
+
+#[wasm_bindgen]
+pub async fn backup_library_now() -> Result<(), JsValue> {
+    run_library_backup().await
+}
+
+#[wasm_bindgen]
+pub async fn set_watch_limits(limits: JsValue, pin: Option<String>) -> Result<(), JsValue> {
+    let limits = if limits.is_undefined() || limits.is_null() {
+        None
+    } else {
+        let limits = from_js_value::<WatchLimitsRequest>(limits)
+            .map_err(|error| JsValue::from_str(&error.to_string()))?;
+        if !limits.is_valid() {
+            return Err(JsValue::from_str("bedtime must be within a single day"));
+        };
+        Some(limits)
+    };
+    persist_watch_limits(watch_limits::verify_pin(pin.as_deref()).await).await?;
+    let watch_limits = watch_limits::set_limits(limits)
+        .await
+        .map_err(|error| to_js_value(&error).unwrap())?;
+    WebEnv::set_storage(WATCH_LIMITS_STORAGE_KEY, Some(&watch_limits))
+        .await
+        .map_err(|error| to_js_value(&error).unwrap())?;
+    emit_new_state();
+    Ok(())
+}
+
+#[wasm_bindgen]
+pub async fn unlock_watch_limits(pin: String) -> Result<(), JsValue> {
+    persist_watch_limits(watch_limits::unlock(&pin).await).await?;
+    emit_new_state();
+    Ok(())
+}
+
+// Pin attempts change the state whether they pass or not
+async fn persist_watch_limits(result: Result<(), WatchLimitError>) -> Result<(), JsValue> {
+    WebEnv::set_storage(WATCH_LIMITS_STORAGE_KEY, Some(&watch_limits::state()))
+        .await
+        .map_err(|error| to_js_value(&error).unwrap())?;
+    result.map_err(|error| to_js_value(&error).unwrap())
+}
+
+#[wasm_bindgen]
+pub async fn set_analytics_enabled(enabled: bool) -> Result<(), JsValue> {
+    analytics::set_enabled(enabled);
+    WebEnv::set_storage(ANALYTICS_ENABLED_STORAGE_KEY, Some(&enabled))
+        .await
+        .map_err(|error| to_js_value(&error).unwrap())?;
+    emit_new_state();
+    Ok(())
+}
+
+#[wasm_bindgen]
+pub fn set_analytics_context(context: JsValue) -> Result<(), JsValue> {
+    let context = from_js_value::<serde_json::Value>(context)
+        .map_err(|error| JsValue::from_str(&error.to_string()))?;
+    analytics::set_context(context);
+    Ok(())
+}
+
+#[wasm_bindgen]
+pub async fn add_external_subtitles(subtitles: JsValue) -> Result<(), JsValue> {
+    let subtitles = from_js_value::<ExternalSubtitlesSource>(subtitles)
+        .map_err(|error| JsValue::from_str(&error.to_string()))?
+        .into_subtitles()
+        .map_err(|error| JsValue::from_str(&error))?;
+    let video_id = match &*RUNTIME.read().expect("runtime read failed") {
+        Some(Loadable::Ready(runtime)) => runtime
+            .model()
+            .expect("model read failed")
+            .player
+            .selected
+            .as_ref()
+            .and_then(|selected| selected.stream_request.as_ref())
+            .map(|stream_request| stream_request.path.id.to_owned())
+            .ok_or_else(|| JsValue::from_str("no video is being played"))?,
+        _ => return Err(runtime_not_ready()),
+    };
+    let external_subtitles = external_subtitles::add_external_subtitles(video_id, subtitles)
+        .map_err(|error| JsValue::from_str(&error))?;
+    WebEnv::set_storage(EXTERNAL_SUBTITLES_STORAGE_KEY, Some(&external_subtitles))
+        .await
+        .map_err(|error| to_js_value(&error).unwrap())?;
+    emit_new_state();
+    Ok(())
+}
+
+// Incognito mode is a setting the watch limits PIN protects like the limits themselves
+#[wasm_bindgen]
+pub async fn set_incognito(enabled: bool, pin: Option<String>) -> Result<(), JsValue> {
+    if watch_limits::is_pin_protected() {
+        persist_watch_limits(watch_limits::verify_pin(pin.as_deref()).await).await?;
+    };
+    incognito::set_enabled(enabled);
+    emit_new_state();
+    Ok(())
+}
+
+#[wasm_bindgen]
+pub fn reorder_play_queue(meta_id: String, video_ids: JsValue) -> Result<(), JsValue> {
+    let video_ids = from_js_value::<Vec<String>>(video_ids)
+        .map_err(|error| JsValue::from_str(&error.to_string()))?;
+    play_queue::reorder(meta_id, video_ids);
+    emit_new_state();
+    Ok(())
+}
+
+#[wasm_bindgen]
+pub fn skip_play_queue_item(meta_id: String, video_id: String) {
+    play_queue::skip(meta_id, video_id);
+    emit_new_state();
+}
+
+#[wasm_bindgen]
+pub fn get_addon_stats() -> Result<JsValue, JsValue> {
+    match &*RUNTIME.read().expect("runtime read failed") {
+        Some(Loadable::Ready(runtime)) => {
+            let model = runtime.model().expect("model read failed");
+            Ok(to_js_value(&addon_stats::addon_stats(&model.ctx.profile.addons)).unwrap())
+        }
+        _ => Err(runtime_not_ready()),
+    }
+}
+
+#[wasm_bindgen]
+pub async fn refresh_library_item(id: String) -> Result<(), JsValue> {
+    permissions::check(vec!["Ctx".to_owned(), "AddToLibrary".to_owned()])
+        .map_err(|error| to_js_value(&error).unwrap())?;
+    let (library_item, addon) = match &*RUNTIME.read().expect("runtime read failed") {
+        Some(Loadable::Ready(runtime)) => {
+            let model = runtime.model().expect("model read failed");
+            let library_item = model
+                .ctx
+                .library
+                .items
+                .get(&id)
+                .filter(|library_item| !library_item.removed && !library_item.temp)
+                .cloned()
+                .ok_or_else(|| JsValue::from_str("library item not found"))?;
+            let meta_path = ResourcePath::without_extra(
+                META_RESOURCE_NAME,
+                &library_item.r#type,
+                &library_item.id,
+            );
+            let addon = model
+                .ctx
+                .profile
+                .addons
+                .iter()
+                .find(|addon| addon.manifest.is_resource_supported(&meta_path))
+                .cloned()
+                .ok_or_else(|| JsValue::from_str("no addon provides meta for this item"))?;
+            (library_item, addon)
+        }
+        _ => return Err(runtime_not_ready()),
+    };
+    let meta_url =
+        library_refresh::meta_url(&addon.transport_url, &library_item.r#type, &library_item.id)
+            .map_err(|error| JsValue::from_str(&error.to_string()))?;
+    let request = Request::get(meta_url.as_str())
+        .body(())
+        .expect("request builder failed");
+    let meta_item = WebEnv::fetch::<_, MetaResponse>(request)
+        .await
+        .map_err(|error| to_js_value(&error).unwrap())?
+        .meta;
+    let meta_item = library_refresh::meta_item_preview(meta_item);
+    let changes = library_refresh::library_item_changes(&library_item, &meta_item);
+    match &*RUNTIME.read().expect("runtime read failed") {
+        Some(Loadable::Ready(runtime)) => {
+            runtime.dispatch(Action::Ctx(ActionCtx::AddToLibrary(meta_item)));
+        }
+        _ => return Err(runtime_not_ready()),
+    };
+    event::emit(&WebEvent::LibraryItemRefreshed(LibraryItemRefresh {
+        id,
+        changes,
+    }));
+    Ok(())
+}
+
+#[wasm_bindgen]
+pub fn set_visible_meta_items(items: JsValue) -> Result<(), JsValue> {
+    let items = from_js_value::<Vec<VisibleItem>>(items)
+        .map_err(|error| JsValue::from_str(&error.to_string()))?;
+    meta_prefetch::set_visible_items(items);
+    meta_prefetch::schedule(prefetch_visible_meta_items);
+    Ok(())
+}
+
+// Reloads the player with the chosen file of a multi-file torrent
+#[wasm_bindgen]
+pub fn select_torrent_file(file_idx: u16) -> Result<(), JsValue> {
+    let selected = match &*RUNTIME.read().expect("runtime read failed") {
+        Some(Loadable::Ready(runtime)) => runtime
+            .model()
+            .expect("model read failed")
+            .player
+            .selected
+            .to_owned(),
+        _ => return Err(runtime_not_ready()),
+    };
+    let mut selected = selected.ok_or_else(|| JsValue::from_str("no stream is selected"))?;
+    match &mut selected.stream.source {
+        StreamSource::Torrent {
+            file_idx: selected_file_idx,
+            ..
+        } => *selected_file_idx = Some(file_idx),
+        _ => return Err(JsValue::from_str("selected stream is not a torrent")),
+    };
+    let action = to_js_value(&serde_json::json!({
+        "action": "Load",
+        "args": {
+            "model": "Player",
+            "args": selected,
+        },
+    }))
+    .unwrap();
+    dispatch(&action, &JsValue::NULL)
+}
+
+#[wasm_bindgen]
+pub fn set_share_base_url(url: String) -> Result<(), JsValue> {
+    let url = Url::parse(&url).map_err(|error| JsValue::from_str(&error.to_string()))?;
+    share::set_share_base_url(url);
+    emit_new_state();
+    Ok(())
+}
+
+#[wasm_bindgen]
+pub fn load_video_details(video_id: String) -> Result<(), JsValue> {
+    match &*RUNTIME.read().expect("runtime read failed") {
+        Some(Loadable::Ready(runtime)) => {
+            let model = runtime.model().expect("model read failed");
+            let video =
+                model.meta_details.meta_items.iter().find_map(|meta_item| {
+                    match &meta_item.content {
+                        Loadable::Ready(meta_item) => {
+                            meta_item.videos.iter().find(|video| video.id == video_id)
+                        }
+                        _ => None,
+                    }
+                });
+            if let Some(video) = video {
+                event::emit(&WebEvent::VideoDetails(video.to_owned()));
+            };
+            Ok(())
+        }
+        _ => Err(runtime_not_ready()),
+    }
+}
+
+#[wasm_bindgen]
+pub async fn set_rating(id: String, rating: JsValue) -> Result<(), JsValue> {
+    let rating = from_js_value::<Option<Rating>>(rating)
+        .map_err(|error| JsValue::from_str(&error.to_string()))?;
+    if !rating.as_ref().map_or(true, Rating::is_valid) {
+        return Err(JsValue::from_str("ratings must be between 1 and 5 stars"));
+    };
+    let ratings = ratings::set_rating(&profile_uid()?, id, rating);
+    WebEnv::set_storage(RATINGS_STORAGE_KEY, Some(&ratings))
+        .await
+        .map_err(|error| to_js_value(&error).unwrap())?;
+    emit_new_state();
+    Ok(())
+}
+
+#[wasm_bindgen]
+pub async fn set_library_recent_policy(policy: JsValue) -> Result<(), JsValue> {
+    let policy = from_js_value::<RecentPolicy>(policy)
+        .map_err(|error| JsValue::from_str(&error.to_string()))?;
+    if !policy.is_valid() {
+        return Err(JsValue::from_str(
+            "recent library policy must keep at least one item",
+        ));
+    };
+    library_recent::init(policy);
+    WebEnv::set_storage(LIBRARY_RECENT_POLICY_STORAGE_KEY, Some(&policy))
+        .await
+        .map_err(|error| to_js_value(&error).unwrap())?;
+    emit_new_state();
+    Ok(())
+}
+
+#[wasm_bindgen]
+pub async fn set_episode_numbering(numbering: JsValue) -> Result<(), JsValue> {
+    let numbering = from_js_value::<EpisodeNumbering>(numbering)
+        .map_err(|error| JsValue::from_str(&error.to_string()))?;
+    episode_numbering::init(numbering);
+    WebEnv::set_storage(EPISODE_NUMBERING_STORAGE_KEY, Some(&numbering))
+        .await
+        .map_err(|error| to_js_value(&error).unwrap())?;
+    emit_new_state();
+    Ok(())
+}
+
+#[wasm_bindgen]
+pub fn validate_manifest(manifest: &JsValue) -> JsValue {
+    to_js_value(&manifest_validation::validate(manifest)).unwrap()
+}
+
+#[wasm_bindgen]
+pub fn get_capabilities() -> JsValue {
+    to_js_value(&permissions::capabilities()).unwrap()
+}
+
+#[wasm_bindgen]
+pub async fn re_resolve_stream(
+    stream: JsValue,
+    stream_request: JsValue,
+) -> Result<JsValue, JsValue> {
+    let stream =
+        from_js_value::<Stream>(stream).map_err(|error| JsValue::from_str(&error.to_string()))?;
+    let stream_request = from_js_value::<ResourceRequest>(stream_request)
+        .map_err(|error| JsValue::from_str(&error.to_string()))?;
+    let streams_url = stream_expiry::streams_url(&stream_request)
+        .map_err(|error| JsValue::from_str(&error.to_string()))?;
+    let request = Request::get(streams_url.as_str())
+        .body(())
+        .expect("request builder failed");
+    let streams = WebEnv::fetch_with_options::<_, StreamsResponse>(
+        request,
+        FetchOptions {
+            timeout: Some(STREAM_RE_RESOLVE_TIMEOUT),
+            ..Default::default()
+        },
+    )
+    .await
+    .map_err(|error| to_js_value(&error).unwrap())?
+    .streams;
+    let stream = stream_expiry::find_stream(streams, &stream)
+        .ok_or_else(|| JsValue::from_str("stream is no longer provided by the addon"))?;
+    Ok(to_js_value(&stream).unwrap())
+}
+
+// Picks a random item of a catalog or of the library and emits it with the best stream it has,
+// a few other items are tried when the picked one has nothing playable
+#[wasm_bindgen]
+pub async fn shuffle_play(args: JsValue) -> Result<(), JsValue> {
+    let args = from_js_value::<ShufflePlay>(args)
+        .map_err(|error| JsValue::from_str(&error.to_string()))?;
+    let unwatched_only = args.unwatched_only;
+    let (library_candidates, watched_ids, addons) =
+        match &*RUNTIME.read().expect("runtime read failed") {
+            Some(Loadable::Ready(runtime)) => {
+                let model = runtime.model().expect("model read failed");
+                let library_items = model
+                    .ctx
+                    .library
+                    .items
+                    .values()
+                    .filter(|library_item| !library_item.removed && !library_item.temp);
+                let watched_ids = library_items
+                    .clone()
+                    .filter(|library_item| shuffle_play::is_watched(library_item))
+                    .map(|library_item| library_item.id.to_owned())
+                    .collect::<HashSet<_>>();
+                let library_candidates = match &args.source {
+                    ShuffleSource::Library { r#type } => library_items
+                        .filter(|library_item| {
+                            r#type
+                                .as_ref()
+                                .map_or(true, |r#type| library_item.r#type == *r#type)
+                        })
+                        .filter(|library_item| {
+                            !unwatched_only || !shuffle_play::is_watched(library_item)
+                        })
+                        .map(ShuffleCandidate::from)
+                        .collect::<Vec<_>>(),
+                    ShuffleSource::Catalog { .. } => vec![],
+                };
+                (
+                    library_candidates,
+                    watched_ids,
+                    model.ctx.profile.addons.to_owned(),
+                )
             }
-            (Ok(action), Err(_)) => {
-                runtime.dispatch(action);
+            _ => return Err(runtime_not_ready()),
+        };
+    let mut candidates = match args.source {
+        ShuffleSource::Catalog { request } => {
+            let catalog_url = shuffle_play::catalog_url(&request)
+                .map_err(|error| JsValue::from_str(&error.to_string()))?;
+            let request = Request::get(catalog_url.as_str())
+                .body(())
+                .expect("request builder failed");
+            WebEnv::fetch::<_, CatalogResponse>(request)
+                .await
+                .map_err(|error| to_js_value(&error).unwrap())?
+                .metas
+                .into_iter()
+                .filter(|meta_item| !unwatched_only || !watched_ids.contains(&meta_item.id))
+                .map(ShuffleCandidate::from)
+                .collect::<Vec<_>>()
+        }
+        ShuffleSource::Library { .. } => library_candidates,
+    };
+    for _ in 0..shuffle_play::MAX_SHUFFLE_ATTEMPTS {
+        let candidate = match shuffle_play::take_random(&mut candidates) {
+            Some(candidate) => candidate,
+            None => break,
+        };
+        if let Some(pick) = resolve_shuffle_pick(candidate, &addons).await {
+            event::emit(&WebEvent::ShufflePlayReady(pick));
+            return Ok(());
+        };
+    }
+    Err(JsValue::from_str("no playable item found"))
+}
+
+async fn resolve_shuffle_pick(
+    candidate: ShuffleCandidate,
+    addons: &[Descriptor],
+) -> Option<ShufflePick> {
+    let meta_path =
+        ResourcePath::without_extra(META_RESOURCE_NAME, &candidate.r#type, &candidate.id);
+    let meta_addon = addons
+        .iter()
+        .find(|addon| addon.manifest.is_resource_supported(&meta_path))?;
+    let meta_request = ResourceRequest {
+        base: meta_addon.transport_url.to_owned(),
+        path: meta_path,
+    };
+    let video_id = match &candidate.video_id {
+        Some(video_id) => video_id.to_owned(),
+        None => {
+            let meta_url = library_refresh::meta_url(
+                &meta_addon.transport_url,
+                &candidate.r#type,
+                &candidate.id,
+            )
+            .ok()?;
+            let request = Request::get(meta_url.as_str())
+                .body(())
+                .expect("request builder failed");
+            let meta_item = WebEnv::fetch::<_, MetaResponse>(request).await.ok()?.meta;
+            shuffle_play::first_video_id(&meta_item).unwrap_or_else(|| candidate.id.to_owned())
+        }
+    };
+    let stream_requests = shuffle_play::stream_requests(addons, &candidate.r#type, &video_id);
+    let responses = future::join_all(stream_requests.into_iter().map(|stream_request| {
+        let streams_url = stream_expiry::streams_url(&stream_request);
+        async move {
+            let request = Request::get(streams_url.ok()?.as_str())
+                .body(())
+                .expect("request builder failed");
+            let streams = WebEnv::fetch::<_, StreamsResponse>(request)
+                .await
+                .ok()?
+                .streams;
+            Some((stream_request, streams))
+        }
+    }))
+    .await
+    .into_iter()
+    .flatten()
+    .collect::<Vec<_>>();
+    let (stream_request, stream) = shuffle_play::best_stream(responses)?;
+    Some(shuffle_play::pick(
+        candidate,
+        video_id,
+        &stream_request,
+        &meta_request,
+        stream,
+    ))
+}
+
+async fn resolve_torrent_file(
+    streaming_server_url: Url,
+    info_hash: String,
+    episode: Option<(u32, u32)>,
+) {
+    let file_resolution = match torrent_files::create_torrent_url(&streaming_server_url, &info_hash)
+    {
+        Some(create_torrent_url) => {
+            let request = Request::post(create_torrent_url.as_str())
+                .body(CreateTorrentRequest {
+                    torrent: TorrentSource {
+                        info_hash: info_hash.to_owned(),
+                    },
+                })
+                .expect("request builder failed");
+            match WebEnv::fetch::<_, CreateTorrentResponse>(request).await {
+                Ok(response) => torrent_files::resolve(&response.files, episode),
+                Err(_) => FileResolution::Failed,
             }
-            _ => {}
+        }
+        None => FileResolution::Failed,
+    };
+    torrent_files::finish(info_hash, file_resolution);
+    emit_new_state();
+}
+
+// With a debounce the state is emitted once at the end of the interval, however many updates it had
+fn schedule_new_state() {
+    match NEW_STATE_DEBOUNCE.with(|debounce| debounce.get()) {
+        Some(debounce) => {
+            if NEW_STATE_TIMEOUT_HANDLE
+                .with(|handle| handle.get())
+                .is_some()
+            {
+                return;
+            };
+            let emit = Closure::once_into_js(|| {
+                NEW_STATE_TIMEOUT_HANDLE.with(|handle| handle.set(None));
+                emit_changed_state();
+            });
+            let handle = web_sys::window()
+                .expect("window is not available")
+                .set_timeout_with_callback_and_timeout_and_arguments_0(
+                    emit.unchecked_ref(),
+                    debounce as i32,
+                )
+                .expect("set timeout failed");
+            NEW_STATE_TIMEOUT_HANDLE.with(|current| current.set(Some(handle)));
+        }
+        None => emit_changed_state(),
+    };
+}
+
+// State kept by the bridge is serialized into several fields, so its changes count for all of them
+fn emit_new_state() {
+    model::mark_changed(WebModelField::ALL);
+    emit_changed_state();
+}
+
+fn emit_changed_state() {
+    event::emit(&RuntimeEvent::NewState);
+    if !runtime_pause::is_paused() {
+        notify_subscriptions();
+    };
+}
+
+fn notify_subscriptions() {
+    let changed_fields = model::take_changed_fields();
+    if let Some(Loadable::Ready(_)) = &*RUNTIME.read().expect("runtime read failed") {
+        subscriptions::notify(
+            &changed_fields,
+            |field| get_state(field).unwrap_or(JsValue::NULL),
+            |field| match &*RUNTIME.read().expect("runtime read failed") {
+                Some(Loadable::Ready(runtime)) => runtime
+                    .model()
+                    .expect("model read failed")
+                    .get_state_value(field),
+                _ => serde_json::Value::Null,
+            },
+        );
+    };
+}
+
+fn runtime_not_ready() -> JsValue {
+    to_js_value(&RuntimeError::NotReady).unwrap()
+}
+
+fn profile_uid() -> Result<Option<String>, JsValue> {
+    match &*RUNTIME.read().expect("runtime read failed") {
+        Some(Loadable::Ready(runtime)) => Ok(runtime
+            .model()
+            .expect("model read failed")
+            .ctx
+            .profile
+            .uid()),
+        _ => Err(runtime_not_ready()),
+    }
+}
+
+async fn persist_board_layouts(board_layouts: HashMap<String, BoardLayout>) -> Result<(), JsValue> {
+    WebEnv::set_storage(BOARD_LAYOUTS_STORAGE_KEY, Some(&board_layouts))
+        .await
+        .map_err(|error| to_js_value(&error).unwrap())?;
+    emit_new_state();
+    Ok(())
+}
+
+async fn persist_home_layouts(home_layouts: HashMap<String, HomeLayout>) -> Result<(), JsValue> {
+    WebEnv::set_storage(HOME_LAYOUTS_STORAGE_KEY, Some(&home_layouts))
+        .await
+        .map_err(|error| to_js_value(&error).unwrap())?;
+    emit_new_state();
+    Ok(())
+}
+
+async fn persist_library_backup(library_backup: LibraryBackup) -> Result<(), JsValue> {
+    WebEnv::set_storage(LIBRARY_BACKUP_STORAGE_KEY, Some(&library_backup))
+        .await
+        .map_err(|error| to_js_value(&error).unwrap())?;
+    emit_new_state();
+    Ok(())
+}
+
+// The snapshot is serialized under the runtime lock, encryption and upload happen without it
+async fn run_library_backup() -> Result<(), JsValue> {
+    let config = match library_backup::library_backup().config {
+        Some(config) => config,
+        None => return Err(JsValue::from_str("library backup is not configured")),
+    };
+    let secrets = match library_backup::secrets() {
+        Some(secrets) => secrets,
+        None => return Err(JsValue::from_str("library backup is locked")),
+    };
+    if library_backup::is_in_progress() {
+        return Err(JsValue::from_str("library backup is already in progress"));
+    };
+    let (uid, snapshot) = match &*RUNTIME.read().expect("runtime read failed") {
+        Some(Loadable::Ready(runtime)) => {
+            let model = runtime.model().expect("model read failed");
+            let snapshot = serde_json::to_string(&BackupSnapshot {
+                version: BACKUP_SNAPSHOT_VERSION,
+                created_at: WebEnv::now(),
+                profile: &model.ctx.profile,
+                library: &model.ctx.library,
+            })
+            .map_err(|error| JsValue::from_str(&error.to_string()))?;
+            (model.ctx.profile.uid(), snapshot)
+        }
+        _ => return Err(runtime_not_ready()),
+    };
+    library_backup::set_in_progress(true);
+    emit_new_state();
+    let result = match library_backup::encrypt(&secrets.passphrase, &snapshot).await {
+        Ok(envelope) => {
+            let body = serde_json::to_string(&envelope).expect("backup serialization failed");
+            library_backup::upload(&config.target, &secrets.password, &uid, &body)
+                .await
+                .map(|_| body.len())
+        }
+        Err(error) => Err(error),
+    };
+    library_backup::set_in_progress(false);
+    let status = match &result {
+        Ok(size) => BackupStatus {
+            time: WebEnv::now(),
+            error: None,
+            size: *size,
+        },
+        Err(error) => BackupStatus {
+            time: WebEnv::now(),
+            error: Some(error.to_owned()),
+            size: 0,
         },
-        _ => panic!("runtime is not ready"),
+    };
+    persist_library_backup(library_backup::record_status(status)).await?;
+    result
+        .map(|_| ())
+        .map_err(|error| JsValue::from_str(&error))
+}
+
+// Responses are kept aside for the next matching fetch, live models are left untouched
+fn prefetch_visible_meta_items() {
+    if WebEnv::data_saver().enabled {
+        return;
+    };
+    let meta_urls = match &*RUNTIME.read().expect("runtime read failed") {
+        Some(Loadable::Ready(runtime)) => {
+            let model = runtime.model().expect("model read failed");
+            meta_prefetch::visible_items()
+                .iter()
+                .flat_map(|item| {
+                    let meta_path =
+                        ResourcePath::without_extra(META_RESOURCE_NAME, &item.r#type, &item.id);
+                    model
+                        .ctx
+                        .profile
+                        .addons
+                        .iter()
+                        .filter(|addon| addon.manifest.is_resource_supported(&meta_path))
+                        .filter_map(|addon| {
+                            library_refresh::meta_url(&addon.transport_url, &item.r#type, &item.id)
+                                .ok()
+                        })
+                        .collect::<Vec<_>>()
+                })
+                .collect::<Vec<_>>()
+        }
+        _ => return,
+    };
+    for meta_url in meta_urls {
+        let request = Request::get(meta_url.as_str())
+            .body(())
+            .expect("request builder failed");
+        // Keyed the same way the env fetch sees the url, so the lookup matches
+        let url = request.uri().to_string();
+        if !meta_prefetch::try_start(&url) {
+            continue;
+        };
+        WebEnv::exec(
+            WebEnv::fetch_with_options::<_, serde_json::Value>(
+                request,
+                FetchOptions {
+                    prefetch: true,
+                    ..Default::default()
+                },
+            )
+            .map(move |result| {
+                let text = result.ok().map(|value| value.to_string());
+                meta_prefetch::finish(&url, text);
+            }),
+        );
     }
 }
+
+fn flush_pending_actions() {
+    for pending_action in pending_actions::take() {
+        let mut settlement = pending_action.settlement;
+        let outcome = dispatch_action(
+            &pending_action.action,
+            &pending_action.field,
+            &mut settlement,
+        );
+        if let Err(error) = &outcome {
+            web_sys::console::error_2(&JsValue::from("pending action failed"), error);
+        };
+        if let Some(settlement) = settlement {
+            settle_dispatch(settlement, outcome);
+        };
+    }
+}
+
+async fn load_account_details() {
+    let auth = match &*RUNTIME.read().expect("runtime read failed") {
+        Some(Loadable::Ready(runtime)) => runtime
+            .model()
+            .expect("model read failed")
+            .ctx
+            .profile
+            .auth
+            .as_ref()
+            .map(|auth| (auth.user.id.to_owned(), auth.key.to_owned())),
+        _ => None,
+    };
+    let (uid, auth_key) = match auth {
+        Some(auth) => auth,
+        None => return,
+    };
+    let request = Request::post(session_refresh::get_user_url().as_str())
+        .body(session_refresh::get_user_request(&auth_key))
+        .expect("request builder failed");
+    match WebEnv::fetch::<_, GetUserResponse>(request).await {
+        Ok(GetUserResponse {
+            error: None,
+            result: Some(account_details),
+        }) => {
+            account::set(uid, account_details);
+            emit_new_state();
+        }
+        Ok(_) => {}
+        Err(error) => {
+            web_sys::console::warn_1(&JsValue::from(format!(
+                "account details request failed: {}",
+                error
+            )));
+        }
+    };
+}
+
+// Dispatches are queued while the session is validated so they don't act on stale state
+async fn refresh_session() {
+    let auth_key = match &*RUNTIME.read().expect("runtime read failed") {
+        Some(Loadable::Ready(runtime)) => runtime
+            .model()
+            .expect("model read failed")
+            .ctx
+            .profile
+            .auth
+            .as_ref()
+            .map(|auth| auth.key.to_owned()),
+        _ => None,
+    };
+    let auth_key = match auth_key {
+        Some(auth_key) => auth_key,
+        None => return,
+    };
+    session_refresh::set_refreshing(true);
+    let request = Request::post(session_refresh::get_user_url().as_str())
+        .body(session_refresh::get_user_request(&auth_key))
+        .expect("request builder failed");
+    let result = WebEnv::fetch_with_options::<_, GetUserResponse>(
+        request,
+        FetchOptions {
+            timeout: Some(session_refresh::GET_USER_TIMEOUT),
+            ..Default::default()
+        },
+    )
+    .await;
+    if let Some(Loadable::Ready(runtime)) = &*RUNTIME.read().expect("runtime read failed") {
+        match result {
+            Ok(GetUserResponse {
+                error: None,
+                result,
+            }) => {
+                if let (Some(uid), Some(account_details)) = (
+                    runtime
+                        .model()
+                        .expect("model read failed")
+                        .ctx
+                        .profile
+                        .uid(),
+                    result,
+                ) {
+                    account::set(uid, account_details);
+                };
+                session_refresh::await_pulls(finish_session_refresh);
+                runtime.dispatch(Action::Ctx(ActionCtx::PullUserFromAPI));
+                runtime.dispatch(Action::Ctx(ActionCtx::PullAddonsFromAPI));
+                runtime.dispatch(Action::Ctx(ActionCtx::SyncLibraryWithAPI));
+                event::emit(&WebEvent::SessionRefreshed);
+                return;
+            }
+            Ok(GetUserResponse {
+                error: Some(error), ..
+            }) => {
+                event::emit(&WebEvent::SessionExpired(error));
+            }
+            Err(error) => {
+                web_sys::console::warn_1(&JsValue::from(format!(
+                    "session refresh failed: {}",
+                    error
+                )));
+            }
+        };
+    };
+    finish_session_refresh();
+}
+
+fn finish_session_refresh() {
+    session_refresh::finish();
+    flush_pending_actions();
+}
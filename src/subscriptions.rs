@@ -0,0 +1,138 @@
+use crate::js_value::{from_js_value, to_js_value};
+use crate::model::WebModelField;
+use crate::mounted_fields;
+use crate::state_selector;
+use serde_json::Value;
+use std::cell::{Cell, RefCell};
+use std::mem::{self, Discriminant};
+use wasm_bindgen::JsValue;
+
+thread_local! {
+    static NEXT_SUBSCRIPTION_ID: Cell<u32> = Cell::new(0);
+    static SUBSCRIPTIONS: RefCell<Vec<Subscription>> = Default::default();
+}
+
+struct Subscription {
+    id: u32,
+    field: JsValue,
+    model_field: Option<WebModelField>,
+    callback: js_sys::Function,
+    pending: bool,
+    // Patch subscriptions keep the last state they were sent, so it goes away with them
+    snapshot: Option<Value>,
+}
+
+// Patch subscriptions are invoked with a JSON Patch against the state of their previous call,
+// the first one replaces the whole document
+pub fn subscribe(field: JsValue, callback: js_sys::Function, patches: bool) -> Result<u32, String> {
+    let is_selector = field
+        .as_string()
+        .map_or(false, |field| state_selector::is_selector(&field));
+    if patches && is_selector {
+        return Err("patches are not available for selectors".to_owned());
+    };
+    let model_field = model_field(&field);
+    if patches && model_field.is_none() {
+        return Err("patches are only available for model fields".to_owned());
+    };
+    let id = NEXT_SUBSCRIPTION_ID.with(|next_id| {
+        let id = next_id.get();
+        next_id.set(id.wrapping_add(1));
+        id
+    });
+    SUBSCRIPTIONS.with(|subscriptions| {
+        subscriptions.borrow_mut().push(Subscription {
+            id,
+            model_field,
+            field,
+            callback,
+            pending: true,
+            snapshot: if patches { Some(Value::Null) } else { None },
+        })
+    });
+    Ok(id)
+}
+
+pub fn unsubscribe(id: u32) -> bool {
+    SUBSCRIPTIONS.with(|subscriptions| {
+        let mut subscriptions = subscriptions.borrow_mut();
+        let len = subscriptions.len();
+        subscriptions.retain(|subscription| subscription.id != id);
+        subscriptions.len() != len
+    })
+}
+
+// Only subscriptions to the fields changed by the update are invoked, selectors count as their field.
+// Subscriptions of unmounted fields stay pending and catch up once their field is mounted.
+pub fn notify(
+    changed_fields: &[Discriminant<WebModelField>],
+    get_state: impl Fn(&JsValue) -> JsValue,
+    get_state_value: impl Fn(&WebModelField) -> Value,
+) {
+    let changes = SUBSCRIPTIONS.with(|subscriptions| {
+        let mut subscriptions = subscriptions.borrow_mut();
+        let mut states: Vec<(JsValue, JsValue)> = vec![];
+        subscriptions
+            .iter_mut()
+            .filter_map(|subscription| {
+                if subscription.model_field.as_ref().map_or(false, |field| {
+                    changed_fields.contains(&mem::discriminant(field))
+                }) {
+                    subscription.pending = true;
+                };
+                let is_mounted = subscription
+                    .model_field
+                    .as_ref()
+                    .map_or(true, mounted_fields::is_mounted);
+                if !subscription.pending || !is_mounted {
+                    return None;
+                };
+                subscription.pending = false;
+                if let (Some(snapshot), Some(model_field)) = (
+                    subscription.snapshot.as_mut(),
+                    subscription.model_field.as_ref(),
+                ) {
+                    let state = get_state_value(model_field);
+                    let patch = json_patch::diff(snapshot, &state);
+                    *snapshot = state;
+                    return Some((
+                        subscription.callback.to_owned(),
+                        to_js_value(&patch).unwrap(),
+                    ));
+                };
+                let state = match states
+                    .iter()
+                    .find(|(field, _)| *field == subscription.field)
+                {
+                    Some((_, state)) => state.to_owned(),
+                    None => {
+                        let state = get_state(&subscription.field);
+                        states.push((subscription.field.to_owned(), state.to_owned()));
+                        state
+                    }
+                };
+                Some((subscription.callback.to_owned(), state))
+            })
+            .collect::<Vec<_>>()
+    });
+    for (callback, state) in changes {
+        if let Err(error) = callback.call1(&JsValue::NULL, &state) {
+            web_sys::console::error_2(&JsValue::from("subscription callback failed"), &error);
+        };
+    }
+}
+
+pub fn clear() {
+    SUBSCRIPTIONS.with(|subscriptions| subscriptions.borrow_mut().clear());
+}
+
+fn model_field(field: &JsValue) -> Option<WebModelField> {
+    match field.as_string() {
+        Some(selector) if state_selector::is_selector(&selector) => {
+            state_selector::parse(&selector)
+                .ok()
+                .map(|selector| selector.field)
+        }
+        _ => from_js_value(field.to_owned()).ok(),
+    }
+}
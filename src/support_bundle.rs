@@ -0,0 +1,135 @@
+use crate::env::{DataSaver, Diagnostics, WebEnv};
+use crate::model::WebModel;
+use serde::Serialize;
+use std::cell::RefCell;
+use std::collections::{HashMap, VecDeque};
+use stremio_core::constants::SCHEMA_VERSION;
+use stremio_core::runtime::msg::{CtxError, Event};
+use stremio_core::runtime::Env;
+use stremio_core::types::addon::DescriptorFlags;
+
+const MAX_RECENT_ERRORS: usize = 20;
+
+thread_local! {
+    static RECENT_ERRORS: RefCell<VecDeque<RecentError>> = Default::default();
+}
+
+#[derive(Clone, Copy, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum RecentErrorType {
+    Api,
+    Env,
+    Other,
+}
+
+// Only a summary is kept, the source event can carry credentials such as an auth request
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RecentError {
+    pub time: i64,
+    pub r#type: RecentErrorType,
+    pub code: Option<u64>,
+    pub message: String,
+    pub source: Option<String>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AddonSummary<'a> {
+    pub id: &'a String,
+    pub version: String,
+    // Transport urls often embed credentials, the origin is enough to tell addons apart
+    pub origin: String,
+    pub flags: &'a DescriptorFlags,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SchemaVersions {
+    pub stored: Option<u32>,
+    pub expected: u32,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Capabilities {
+    pub storage: bool,
+    pub data_saver: DataSaver,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SupportBundle<'a> {
+    pub version: &'static str,
+    pub generated_at: i64,
+    pub diagnostics: Diagnostics,
+    pub addons: Vec<AddonSummary<'a>>,
+    pub logged_in: bool,
+    pub library_items: usize,
+    pub recent_errors: Vec<RecentError>,
+    pub storage_sizes: HashMap<String, usize>,
+    pub capabilities: Capabilities,
+    pub schema_versions: SchemaVersions,
+}
+
+pub fn record_error(event: &Event) {
+    let (error, source) = match event {
+        Event::Error { error, source } => (error, source),
+        _ => return,
+    };
+    let (r#type, code, message) = match error {
+        CtxError::API(message) => (RecentErrorType::Api, None, message.to_owned()),
+        CtxError::Env(error) => (RecentErrorType::Env, Some(error.code()), error.message()),
+        CtxError::Other(message) => (RecentErrorType::Other, None, message.to_owned()),
+    };
+    let recent_error = RecentError {
+        time: WebEnv::now().timestamp_millis(),
+        r#type,
+        code,
+        message,
+        source: serde_json::to_value(source.as_ref())
+            .ok()
+            .and_then(|source| source.get("event")?.as_str().map(ToOwned::to_owned)),
+    };
+    RECENT_ERRORS.with(|recent_errors| {
+        let mut recent_errors = recent_errors.borrow_mut();
+        if recent_errors.len() >= MAX_RECENT_ERRORS {
+            recent_errors.pop_front();
+        };
+        recent_errors.push_back(recent_error);
+    });
+}
+
+pub fn support_bundle(model: &WebModel, stored_schema_version: Option<u32>) -> SupportBundle {
+    let storage_sizes = WebEnv::storage_sizes();
+    SupportBundle {
+        version: env!("CARGO_PKG_VERSION"),
+        generated_at: WebEnv::now().timestamp_millis(),
+        diagnostics: WebEnv::diagnostics(),
+        addons: model
+            .ctx
+            .profile
+            .addons
+            .iter()
+            .map(|addon| AddonSummary {
+                id: &addon.manifest.id,
+                version: addon.manifest.version.to_string(),
+                origin: addon.transport_url.origin().ascii_serialization(),
+                flags: &addon.flags,
+            })
+            .collect(),
+        logged_in: model.ctx.profile.auth.is_some(),
+        library_items: model.ctx.library.items.len(),
+        recent_errors: RECENT_ERRORS
+            .with(|recent_errors| recent_errors.borrow().iter().cloned().collect()),
+        capabilities: Capabilities {
+            storage: storage_sizes.is_ok(),
+            data_saver: WebEnv::data_saver(),
+        },
+        storage_sizes: storage_sizes.unwrap_or_default(),
+        schema_versions: SchemaVersions {
+            stored: stored_schema_version,
+            expected: SCHEMA_VERSION,
+        },
+    }
+}
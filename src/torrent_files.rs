@@ -0,0 +1,202 @@
+use serde::{Deserialize, Serialize};
+use std::cell::RefCell;
+use std::collections::HashMap;
+use stremio_core::types::resource::{Stream, StreamSource};
+use url::Url;
+
+const VIDEO_EXTENSIONS: &[&str] = &[
+    "mkv", "mp4", "avi", "m4v", "mov", "wmv", "webm", "ts", "mpg", "mpeg", "flv",
+];
+
+thread_local! {
+    static FILE_RESOLUTIONS: RefCell<HashMap<String, FileResolution>> = Default::default();
+}
+
+#[derive(Clone, Serialize)]
+#[serde(tag = "status", rename_all = "camelCase")]
+pub enum FileResolution {
+    Pending,
+    #[serde(rename_all = "camelCase")]
+    Resolved {
+        file_idx: u16,
+    },
+    Ambiguous {
+        candidates: Vec<FileCandidate>,
+    },
+    Failed,
+}
+
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FileCandidate {
+    pub file_idx: u16,
+    pub name: String,
+    pub size: u64,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CreateTorrentRequest {
+    pub torrent: TorrentSource,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TorrentSource {
+    pub info_hash: String,
+}
+
+#[derive(Deserialize)]
+pub struct TorrentFile {
+    pub name: String,
+    pub length: u64,
+}
+
+#[derive(Deserialize)]
+pub struct CreateTorrentResponse {
+    pub files: Vec<TorrentFile>,
+}
+
+// Only torrents without a fileIdx leave the choice of file to the streaming server
+pub fn unresolved_info_hash(stream: &Stream) -> Option<String> {
+    match &stream.source {
+        StreamSource::Torrent {
+            info_hash,
+            file_idx: None,
+            ..
+        } => Some(
+            info_hash
+                .iter()
+                .map(|byte| format!("{:02x}", byte))
+                .collect(),
+        ),
+        _ => None,
+    }
+}
+
+pub fn create_torrent_url(streaming_server_url: &Url, info_hash: &str) -> Option<Url> {
+    streaming_server_url
+        .join(&format!("{}/create", info_hash))
+        .ok()
+}
+
+// Series video ids end with the season and the episode, e.g. "tt0944947:1:2"
+pub fn video_episode(video_id: &str) -> Option<(u32, u32)> {
+    let mut parts = video_id.rsplit(':');
+    let episode = parts.next()?.parse().ok()?;
+    let season = parts.next()?.parse().ok()?;
+    parts.next()?;
+    Some((season, episode))
+}
+
+pub fn resolution(info_hash: &str) -> Option<FileResolution> {
+    FILE_RESOLUTIONS.with(|file_resolutions| file_resolutions.borrow().get(info_hash).cloned())
+}
+
+// Returns false when the torrent is already being resolved or has been resolved
+pub fn start(info_hash: &str) -> bool {
+    FILE_RESOLUTIONS.with(|file_resolutions| {
+        let mut file_resolutions = file_resolutions.borrow_mut();
+        match file_resolutions.get(info_hash) {
+            Some(FileResolution::Failed) | None => {
+                file_resolutions.insert(info_hash.to_owned(), FileResolution::Pending);
+                true
+            }
+            Some(_) => false,
+        }
+    })
+}
+
+pub fn finish(info_hash: String, file_resolution: FileResolution) {
+    FILE_RESOLUTIONS.with(|file_resolutions| {
+        file_resolutions
+            .borrow_mut()
+            .insert(info_hash, file_resolution)
+    });
+}
+
+// Season packs are matched by the episode markers in the file names, anything else plays the
+// largest video file like the streaming server does
+pub fn resolve(files: &[TorrentFile], episode: Option<(u32, u32)>) -> FileResolution {
+    let videos = files
+        .iter()
+        .enumerate()
+        .filter(|(_, file)| is_video(&file.name))
+        .map(|(file_idx, file)| FileCandidate {
+            file_idx: file_idx as u16,
+            name: file.name.to_owned(),
+            size: file.length,
+        })
+        .collect::<Vec<_>>();
+    let candidates = match episode {
+        Some(episode) if videos.len() > 1 => {
+            let matching = videos
+                .iter()
+                .filter(|video| name_episode(&video.name) == Some(episode))
+                .cloned()
+                .collect::<Vec<_>>();
+            if matching.is_empty() {
+                videos
+            } else {
+                matching
+            }
+        }
+        Some(_) => videos,
+        None => videos
+            .into_iter()
+            .max_by_key(|video| video.size)
+            .into_iter()
+            .collect(),
+    };
+    match candidates.as_slice() {
+        [] => FileResolution::Failed,
+        [candidate] => FileResolution::Resolved {
+            file_idx: candidate.file_idx,
+        },
+        _ => FileResolution::Ambiguous { candidates },
+    }
+}
+
+fn is_video(name: &str) -> bool {
+    name.rsplit('.').next().map_or(false, |extension| {
+        VIDEO_EXTENSIONS.contains(&extension.to_lowercase().as_str())
+    })
+}
+
+// Understands both "S01E02" and "1x02"
+fn name_episode(name: &str) -> Option<(u32, u32)> {
+    let name = name.to_lowercase();
+    let chars = name.chars().collect::<Vec<_>>();
+    let number_at = |position: usize| {
+        let digits = chars[position.min(chars.len())..]
+            .iter()
+            .take_while(|character| character.is_ascii_digit())
+            .collect::<String>();
+        digits
+            .parse::<u32>()
+            .ok()
+            .map(|number| (number, digits.len()))
+    };
+    (0..chars.len()).find_map(|position| {
+        let is_boundary = position == 0 || !chars[position - 1].is_alphanumeric();
+        match chars[position] {
+            's' if is_boundary => {
+                let (season, length) = number_at(position + 1)?;
+                if chars.get(position + 1 + length) != Some(&'e') {
+                    return None;
+                };
+                let (episode, _) = number_at(position + 2 + length)?;
+                Some((season, episode))
+            }
+            character if is_boundary && character.is_ascii_digit() => {
+                let (season, length) = number_at(position)?;
+                if chars.get(position + length) != Some(&'x') {
+                    return None;
+                };
+                let (episode, _) = number_at(position + length + 1)?;
+                Some((season, episode))
+            }
+            _ => None,
+        }
+    })
+}
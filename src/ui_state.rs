@@ -0,0 +1,93 @@
+use crate::env::WebEnv;
+use serde::{Deserialize, Serialize};
+use std::cell::RefCell;
+use std::collections::HashMap;
+use stremio_core::runtime::Env;
+
+pub const UI_STATE_STORAGE_KEY: &str = "ui_state";
+const MAX_ITEMS: usize = 1000;
+const MAX_KEYS_PER_ITEM: usize = 32;
+const MAX_VALUE_LENGTH: usize = 1024;
+
+thread_local! {
+    static UI_STATE: RefCell<UiState> = Default::default();
+}
+
+#[derive(Clone, Default, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct UiState(HashMap<String, ItemUiState>);
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct ItemUiState {
+    mtime: i64,
+    values: HashMap<String, serde_json::Value>,
+}
+
+pub fn init(ui_state: UiState) {
+    UI_STATE.with(|current| *current.borrow_mut() = ui_state);
+}
+
+pub fn get(id: &str) -> HashMap<String, serde_json::Value> {
+    UI_STATE.with(|ui_state| {
+        ui_state
+            .borrow()
+            .0
+            .get(id)
+            .map(|item_ui_state| item_ui_state.values.to_owned())
+            .unwrap_or_default()
+    })
+}
+
+pub fn set(id: String, key: String, value: serde_json::Value) -> Result<UiState, String> {
+    if serde_json::to_string(&value)
+        .map_err(|error| error.to_string())?
+        .len()
+        > MAX_VALUE_LENGTH
+    {
+        return Err(format!(
+            "ui state values must not exceed {} bytes",
+            MAX_VALUE_LENGTH
+        ));
+    };
+    UI_STATE.with(|ui_state| {
+        let mut ui_state = ui_state.borrow_mut();
+        let item_ui_state = ui_state
+            .0
+            .entry(id.to_owned())
+            .or_insert_with(|| ItemUiState {
+                mtime: 0,
+                values: HashMap::new(),
+            });
+        if value.is_null() {
+            item_ui_state.values.remove(&key);
+        } else {
+            if !item_ui_state.values.contains_key(&key)
+                && item_ui_state.values.len() >= MAX_KEYS_PER_ITEM
+            {
+                return Err(format!(
+                    "ui state is limited to {} keys per item",
+                    MAX_KEYS_PER_ITEM
+                ));
+            };
+            item_ui_state.values.insert(key, value);
+        };
+        item_ui_state.mtime = WebEnv::now().timestamp_millis();
+        if item_ui_state.values.is_empty() {
+            ui_state.0.remove(&id);
+        };
+        while ui_state.0.len() > MAX_ITEMS {
+            let oldest_id = ui_state
+                .0
+                .iter()
+                .min_by_key(|(_, item_ui_state)| item_ui_state.mtime)
+                .map(|(id, _)| id.to_owned())
+                .expect("ui state is empty");
+            ui_state.0.remove(&oldest_id);
+        }
+        Ok(ui_state.to_owned())
+    })
+}
+
+pub fn clear() {
+    UI_STATE.with(|ui_state| *ui_state.borrow_mut() = Default::default());
+}
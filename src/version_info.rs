@@ -0,0 +1,52 @@
+use serde::Serialize;
+use stremio_core::constants::SCHEMA_VERSION;
+
+// Manifests are validated against the v3 addon protocol, legacy addons are still reachable over their own transport
+const ADDON_PROTOCOL_VERSION: u32 = 3;
+const ADDON_TRANSPORTS: &[&str] = &["http", "legacy"];
+const ADDON_RESOURCES: &[&str] = &["catalog", "meta", "stream", "subtitles", "addon_catalog"];
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CoreVersion {
+    pub version: &'static str,
+    pub revision: Option<&'static str>,
+    pub schema_version: u32,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AddonProtocol {
+    pub version: u32,
+    pub transports: &'static [&'static str],
+    pub resources: &'static [&'static str],
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct VersionInfo {
+    pub core: CoreVersion,
+    pub core_web: &'static str,
+    pub addon_protocol: AddonProtocol,
+}
+
+pub fn version_info() -> VersionInfo {
+    let revision = env!("STREMIO_CORE_REVISION");
+    VersionInfo {
+        core: CoreVersion {
+            version: env!("STREMIO_CORE_VERSION"),
+            revision: if revision.is_empty() {
+                None
+            } else {
+                Some(revision)
+            },
+            schema_version: SCHEMA_VERSION,
+        },
+        core_web: env!("CARGO_PKG_VERSION"),
+        addon_protocol: AddonProtocol {
+            version: ADDON_PROTOCOL_VERSION,
+            transports: ADDON_TRANSPORTS,
+            resources: ADDON_RESOURCES,
+        },
+    }
+}
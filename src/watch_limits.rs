@@ -0,0 +1,462 @@
+use crate::env::{js_error_message, WebEnv};
+use chrono::{NaiveDate, Timelike};
+use serde::{Deserialize, Serialize};
+use std::cell::{Cell, RefCell};
+use stremio_core::runtime::msg::{Action, ActionLoad, ActionPlayer};
+use stremio_core::runtime::Env;
+use wasm_bindgen::{JsCast, JsValue};
+use wasm_bindgen_futures::JsFuture;
+
+pub const WATCH_LIMITS_STORAGE_KEY: &str = "watch_limits";
+// Same as the pause threshold of watch sessions, longer gaps between time updates aren't watch time
+const MAX_UPDATE_GAP: i64 = 5000;
+const MINUTES_PER_DAY: u32 = 24 * 60;
+const PIN_HASH_ITERATIONS: u32 = 100_000;
+const PIN_SALT_LENGTH: usize = 16;
+const PIN_HASH_BITS: u32 = 256;
+// After this many wrong pins every further attempt doubles the lockout, up to an hour
+const MAX_PIN_ATTEMPTS: u32 = 5;
+const PIN_LOCKOUT: i64 = 30 * 1000;
+const MAX_PIN_LOCKOUT: i64 = 60 * 60 * 1000;
+
+thread_local! {
+    static WATCH_LIMITS: RefCell<WatchLimitsState> = Default::default();
+    static LAST_UPDATE: Cell<Option<i64>> = Cell::new(None);
+    static OVERRIDE_DATE: Cell<Option<NaiveDate>> = Cell::new(None);
+}
+
+// Minutes since local midnight, a window that crosses midnight has its start after its end
+#[derive(Clone, Copy, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BedtimeWindow {
+    pub start: u32,
+    pub end: u32,
+}
+
+impl BedtimeWindow {
+    fn contains(&self, minute: u32) -> bool {
+        if self.start <= self.end {
+            minute >= self.start && minute < self.end
+        } else {
+            minute >= self.start || minute < self.end
+        }
+    }
+}
+
+// Only a salted PBKDF2 hash of the pin is kept
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PinHash {
+    pub salt: String,
+    pub iterations: u32,
+    pub hash: String,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WatchLimits {
+    pub daily_minutes: Option<u32>,
+    pub bedtime: Option<BedtimeWindow>,
+    pub pin: Option<PinHash>,
+}
+
+// The limits as the app sets them, with the pin in plain text
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WatchLimitsRequest {
+    pub daily_minutes: Option<u32>,
+    pub bedtime: Option<BedtimeWindow>,
+    pub pin: Option<String>,
+}
+
+impl WatchLimitsRequest {
+    pub fn is_valid(&self) -> bool {
+        self.bedtime.map_or(true, |bedtime| {
+            bedtime.start < MINUTES_PER_DAY && bedtime.end < MINUTES_PER_DAY
+        })
+    }
+}
+
+#[derive(Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WatchUsage {
+    pub date: Option<NaiveDate>,
+    pub watched: u64,
+}
+
+#[derive(Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PinAttempts {
+    pub failed: u32,
+    pub locked_until: Option<i64>,
+}
+
+// Failed attempts are persisted, so reloading the app doesn't lift a lockout
+#[derive(Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WatchLimitsState {
+    pub limits: Option<WatchLimits>,
+    pub usage: WatchUsage,
+    #[serde(default)]
+    pub pin_attempts: PinAttempts,
+}
+
+// The pin itself never leaves the bridge
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WatchLimitsSummary {
+    pub daily_minutes: Option<u32>,
+    pub bedtime: Option<BedtimeWindow>,
+    pub pin_protected: bool,
+    pub watched_today: u64,
+}
+
+#[derive(Clone, Copy, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum LimitReason {
+    DailyLimit,
+    Bedtime,
+}
+
+#[derive(Serialize)]
+#[serde(tag = "type")]
+pub enum WatchLimitError {
+    WatchLimitReached {
+        reason: LimitReason,
+    },
+    InvalidPin,
+    #[serde(rename_all = "camelCase")]
+    PinLocked {
+        retry_after: i64,
+    },
+    PinHashFailed {
+        message: String,
+    },
+}
+
+// Limits are about the user's day, so dates and bedtime use local time
+fn local_now() -> (NaiveDate, u32) {
+    let now = WebEnv::now().with_timezone(&WebEnv::timezone_offset());
+    (now.date().naive_local(), now.hour() * 60 + now.minute())
+}
+
+pub fn init(state: WatchLimitsState) {
+    WATCH_LIMITS.with(|current| *current.borrow_mut() = state);
+}
+
+pub fn summary() -> Option<WatchLimitsSummary> {
+    let date = local_now().0;
+    WATCH_LIMITS.with(|state| {
+        let state = state.borrow();
+        state.limits.as_ref().map(|limits| WatchLimitsSummary {
+            daily_minutes: limits.daily_minutes,
+            bedtime: limits.bedtime,
+            pin_protected: limits.pin.is_some(),
+            watched_today: if state.usage.date == Some(date) {
+                state.usage.watched
+            } else {
+                0
+            },
+        })
+    })
+}
+
+pub fn state() -> WatchLimitsState {
+    WATCH_LIMITS.with(|state| state.borrow().to_owned())
+}
+
+pub fn is_pin_protected() -> bool {
+    WATCH_LIMITS.with(|state| {
+        state
+            .borrow()
+            .limits
+            .as_ref()
+            .map_or(false, |limits| limits.pin.is_some())
+    })
+}
+
+pub async fn set_limits(
+    request: Option<WatchLimitsRequest>,
+) -> Result<WatchLimitsState, WatchLimitError> {
+    let limits = match request {
+        Some(request) => Some(WatchLimits {
+            daily_minutes: request.daily_minutes,
+            bedtime: request.bedtime,
+            pin: match request.pin {
+                Some(pin) => Some(new_pin_hash(&pin).await?),
+                None => None,
+            },
+        }),
+        None => None,
+    };
+    Ok(WATCH_LIMITS.with(|state| {
+        let mut state = state.borrow_mut();
+        state.limits = limits;
+        state.to_owned()
+    }))
+}
+
+// The attempt is counted before the result is returned, the caller persists the state either way
+pub async fn verify_pin(pin: Option<&str>) -> Result<(), WatchLimitError> {
+    let now = WebEnv::now().timestamp_millis();
+    let (pin_hash, locked_until) = WATCH_LIMITS.with(|state| {
+        let state = state.borrow();
+        (
+            state
+                .limits
+                .as_ref()
+                .and_then(|limits| limits.pin.to_owned()),
+            state.pin_attempts.locked_until,
+        )
+    });
+    let pin_hash = match pin_hash {
+        Some(pin_hash) => pin_hash,
+        None => return Ok(()),
+    };
+    if let Some(locked_until) = locked_until.filter(|locked_until| *locked_until > now) {
+        return Err(WatchLimitError::PinLocked {
+            retry_after: locked_until - now,
+        });
+    };
+    let matches = match pin {
+        Some(pin) => {
+            let salt =
+                base64::decode(&pin_hash.salt).map_err(|error| WatchLimitError::PinHashFailed {
+                    message: error.to_string(),
+                })?;
+            hash_pin(pin, &salt, pin_hash.iterations).await? == pin_hash.hash
+        }
+        None => false,
+    };
+    WATCH_LIMITS.with(|state| {
+        let attempts = &mut state.borrow_mut().pin_attempts;
+        if matches {
+            *attempts = PinAttempts::default();
+        } else {
+            attempts.failed += 1;
+            if attempts.failed >= MAX_PIN_ATTEMPTS {
+                let exponent = (attempts.failed - MAX_PIN_ATTEMPTS).min(16);
+                attempts.locked_until = Some(now + (PIN_LOCKOUT << exponent).min(MAX_PIN_LOCKOUT));
+            };
+        };
+    });
+    if matches {
+        Ok(())
+    } else {
+        Err(WatchLimitError::InvalidPin)
+    }
+}
+
+// An override lasts until the end of the local day
+pub async fn unlock(pin: &str) -> Result<(), WatchLimitError> {
+    verify_pin(Some(pin)).await?;
+    OVERRIDE_DATE.with(|override_date| override_date.set(Some(local_now().0)));
+    Ok(())
+}
+
+async fn new_pin_hash(pin: &str) -> Result<PinHash, WatchLimitError> {
+    let mut salt = [0u8; PIN_SALT_LENGTH];
+    web_sys::window()
+        .expect("window is not available")
+        .crypto()
+        .and_then(|crypto| crypto.get_random_values_with_u8_array(&mut salt))
+        .map_err(|error| WatchLimitError::PinHashFailed {
+            message: js_error_message(error),
+        })?;
+    Ok(PinHash {
+        salt: base64::encode(&salt),
+        iterations: PIN_HASH_ITERATIONS,
+        hash: hash_pin(pin, &salt, PIN_HASH_ITERATIONS).await?,
+    })
+}
+
+async fn hash_pin(pin: &str, salt: &[u8], iterations: u32) -> Result<String, WatchLimitError> {
+    let pin_hash_failed = |error: JsValue| WatchLimitError::PinHashFailed {
+        message: js_error_message(error),
+    };
+    let subtle = web_sys::window()
+        .expect("window is not available")
+        .crypto()
+        .map_err(pin_hash_failed)?
+        .subtle();
+    let usages = js_sys::Array::of1(&JsValue::from_str("deriveBits"));
+    let key = subtle
+        .import_key_with_str(
+            "raw",
+            &js_sys::Uint8Array::from(pin.as_bytes()),
+            "PBKDF2",
+            false,
+            &usages,
+        )
+        .map_err(pin_hash_failed)?;
+    let key = JsFuture::from(key)
+        .await
+        .map_err(pin_hash_failed)?
+        .unchecked_into::<web_sys::CryptoKey>();
+    let bits = subtle
+        .derive_bits_with_object(
+            &web_sys::Pbkdf2Params::new(
+                "PBKDF2",
+                &JsValue::from_str("SHA-256"),
+                iterations,
+                &js_sys::Uint8Array::from(salt),
+            ),
+            &key,
+            PIN_HASH_BITS,
+        )
+        .map_err(pin_hash_failed)?;
+    let bits = JsFuture::from(bits).await.map_err(pin_hash_failed)?;
+    Ok(base64::encode(js_sys::Uint8Array::new(&bits).to_vec()))
+}
+
+pub fn limit_reached() -> Option<LimitReason> {
+    let (date, minute) = local_now();
+    limit_reached_at(date, minute)
+}
+
+fn limit_reached_at(date: NaiveDate, minute: u32) -> Option<LimitReason> {
+    if OVERRIDE_DATE.with(|override_date| override_date.get()) == Some(date) {
+        return None;
+    };
+    WATCH_LIMITS.with(|state| {
+        let state = state.borrow();
+        let limits = state.limits.as_ref()?;
+        if limits
+            .bedtime
+            .map_or(false, |bedtime| bedtime.contains(minute))
+        {
+            return Some(LimitReason::Bedtime);
+        };
+        let watched = if state.usage.date == Some(date) {
+            state.usage.watched
+        } else {
+            0
+        };
+        match limits.daily_minutes {
+            Some(daily_minutes) if watched >= daily_minutes as u64 * 60 * 1000 => {
+                Some(LimitReason::DailyLimit)
+            }
+            _ => None,
+        }
+    })
+}
+
+pub fn check(action: &Action) -> Result<(), WatchLimitError> {
+    match (action, limit_reached()) {
+        (Action::Load(ActionLoad::Player(_)), Some(reason)) => {
+            Err(WatchLimitError::WatchLimitReached { reason })
+        }
+        _ => Ok(()),
+    }
+}
+
+// Returns the usage to persist whenever another watched minute is completed,
+// playback in incognito mode counts as well
+pub fn record(action: &Action) -> Option<WatchLimitsState> {
+    match action {
+        Action::Player(ActionPlayer::UpdateLibraryItemState { .. }) => {
+            record_watched(WebEnv::now().timestamp_millis(), local_now().0)
+        }
+        Action::Load(ActionLoad::Player(_)) | Action::Unload => {
+            LAST_UPDATE.with(|last_update| last_update.set(None));
+            None
+        }
+        _ => None,
+    }
+}
+
+fn record_watched(now: i64, date: NaiveDate) -> Option<WatchLimitsState> {
+    let elapsed = LAST_UPDATE
+        .with(|last_update| last_update.replace(Some(now)))
+        .map(|last_update| now - last_update)
+        .filter(|elapsed| *elapsed >= 0 && *elapsed <= MAX_UPDATE_GAP)
+        .unwrap_or_default() as u64;
+    WATCH_LIMITS.with(|state| {
+        let mut state = state.borrow_mut();
+        if state.usage.date != Some(date) {
+            state.usage = WatchUsage {
+                date: Some(date),
+                watched: 0,
+            };
+        };
+        let previous_minutes = state.usage.watched / 60_000;
+        state.usage.watched += elapsed;
+        if state.limits.is_some() && state.usage.watched / 60_000 > previous_minutes {
+            Some(state.to_owned())
+        } else {
+            None
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::incognito;
+
+    #[test]
+    fn bedtime_window_within_a_day() {
+        let bedtime = BedtimeWindow {
+            start: 13 * 60,
+            end: 15 * 60,
+        };
+        assert!(!bedtime.contains(13 * 60 - 1));
+        assert!(bedtime.contains(13 * 60));
+        assert!(bedtime.contains(15 * 60 - 1));
+        assert!(!bedtime.contains(15 * 60));
+    }
+
+    #[test]
+    fn bedtime_window_across_midnight() {
+        let bedtime = BedtimeWindow {
+            start: 22 * 60,
+            end: 7 * 60,
+        };
+        assert!(!bedtime.contains(22 * 60 - 1));
+        assert!(bedtime.contains(22 * 60));
+        assert!(bedtime.contains(MINUTES_PER_DAY - 1));
+        assert!(bedtime.contains(0));
+        assert!(bedtime.contains(7 * 60 - 1));
+        assert!(!bedtime.contains(7 * 60));
+        assert!(!bedtime.contains(12 * 60));
+    }
+
+    #[test]
+    fn empty_bedtime_window() {
+        let bedtime = BedtimeWindow {
+            start: 22 * 60,
+            end: 22 * 60,
+        };
+        assert!(!bedtime.contains(22 * 60));
+        assert!(!bedtime.contains(0));
+    }
+
+    #[test]
+    fn incognito_playback_counts_toward_the_daily_limit() {
+        let date = NaiveDate::from_ymd(2020, 5, 10);
+        init(WatchLimitsState {
+            limits: Some(WatchLimits {
+                daily_minutes: Some(1),
+                bedtime: None,
+                pin: None,
+            }),
+            ..Default::default()
+        });
+        incognito::set_enabled(true);
+        let time_update = Action::Player(ActionPlayer::UpdateLibraryItemState {
+            time: 0,
+            duration: 0,
+        });
+        assert!(!incognito::is_allowed(&time_update));
+        let persisted = (0..=15)
+            .filter_map(|update| record_watched(update * 4000, date))
+            .collect::<Vec<_>>();
+        incognito::set_enabled(false);
+        assert_eq!(persisted.len(), 1);
+        assert_eq!(persisted[0].usage.watched, 60_000);
+        assert!(matches!(
+            limit_reached_at(date, 12 * 60),
+            Some(LimitReason::DailyLimit)
+        ));
+    }
+}
@@ -0,0 +1,162 @@
+use crate::env::WebEnv;
+use crate::model::WebModelField;
+use serde::Serialize;
+use std::cell::RefCell;
+use stremio_core::runtime::msg::{Action, ActionLoad, ActionPlayer};
+use stremio_core::runtime::Env;
+use stremio_core::types::resource::Stream;
+use url::Url;
+
+// Time updates arrive every second or so while playing, a longer gap means playback was paused
+const PAUSE_THRESHOLD: i64 = 5000;
+// Playback drifts a bit from wall clock time, only larger jumps count as seeks
+const SEEK_THRESHOLD: u64 = 5000;
+
+thread_local! {
+    static WATCH_SESSION: RefCell<Option<WatchSession>> = Default::default();
+}
+
+struct WatchSession {
+    stream: Stream,
+    addon_transport_url: Option<Url>,
+    watch_time: u64,
+    pauses: u32,
+    seeks: u32,
+    errors: u32,
+    started_at: i64,
+    time: u64,
+    duration: u64,
+    last_update: Option<i64>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WatchSessionSummary {
+    pub stream: Stream,
+    pub addon_transport_url: Option<Url>,
+    pub watch_time: u64,
+    pub pauses: u32,
+    pub seeks: u32,
+    pub completion: f64,
+    pub errors: u32,
+}
+
+impl From<WatchSession> for WatchSessionSummary {
+    fn from(session: WatchSession) -> Self {
+        WatchSessionSummary {
+            stream: session.stream,
+            addon_transport_url: session.addon_transport_url,
+            watch_time: session.watch_time,
+            pauses: session.pauses,
+            seeks: session.seeks,
+            completion: if session.duration > 0 {
+                (session.time as f64 / session.duration as f64).min(1.0)
+            } else {
+                0.0
+            },
+            errors: session.errors,
+        }
+    }
+}
+
+pub struct Seek {
+    pub from: u64,
+    pub to: u64,
+}
+
+pub enum WatchSessionChange {
+    Started {
+        stream: Stream,
+        addon_transport_url: Option<Url>,
+    },
+    Playing {
+        stream: Stream,
+        addon_transport_url: Option<Url>,
+        start_delay: i64,
+    },
+    Seeked(Seek),
+    Ended(WatchSessionSummary),
+}
+
+// Loading a new stream ends the previous session before starting the next one
+pub fn update(action: &Action, field: Option<&WebModelField>) -> Vec<WatchSessionChange> {
+    WATCH_SESSION.with(|session| {
+        let mut session = session.borrow_mut();
+        match (action, field) {
+            (Action::Load(ActionLoad::Player(selected)), _) => {
+                let addon_transport_url = selected
+                    .stream_request
+                    .as_ref()
+                    .map(|request| request.base.to_owned());
+                session
+                    .replace(WatchSession {
+                        stream: selected.stream.to_owned(),
+                        addon_transport_url: addon_transport_url.to_owned(),
+                        watch_time: 0,
+                        pauses: 0,
+                        seeks: 0,
+                        errors: 0,
+                        started_at: WebEnv::now().timestamp_millis(),
+                        time: 0,
+                        duration: 0,
+                        last_update: None,
+                    })
+                    .map(|session| WatchSessionChange::Ended(session.into()))
+                    .into_iter()
+                    .chain(Some(WatchSessionChange::Started {
+                        stream: selected.stream.to_owned(),
+                        addon_transport_url,
+                    }))
+                    .collect()
+            }
+            (Action::Unload, None) | (Action::Unload, Some(WebModelField::Player)) => session
+                .take()
+                .map(|session| WatchSessionChange::Ended(session.into()))
+                .into_iter()
+                .collect(),
+            (Action::Player(ActionPlayer::UpdateLibraryItemState { time, duration }), _) => {
+                let mut changes = vec![];
+                if let Some(session) = session.as_mut() {
+                    let now = WebEnv::now().timestamp_millis();
+                    if session.last_update.is_none() {
+                        changes.push(WatchSessionChange::Playing {
+                            stream: session.stream.to_owned(),
+                            addon_transport_url: session.addon_transport_url.to_owned(),
+                            start_delay: now - session.started_at,
+                        });
+                    };
+                    if let Some(last_update) = session.last_update {
+                        let elapsed = now - last_update;
+                        let expected_time = session.time + elapsed.max(0) as u64;
+                        if *time + SEEK_THRESHOLD < session.time
+                            || *time > expected_time + SEEK_THRESHOLD
+                        {
+                            session.seeks += 1;
+                            changes.push(WatchSessionChange::Seeked(Seek {
+                                from: session.time,
+                                to: *time,
+                            }));
+                        } else if elapsed > PAUSE_THRESHOLD {
+                            session.pauses += 1;
+                        } else if *time > session.time {
+                            session.watch_time += (*time - session.time).min(elapsed.max(0) as u64);
+                        };
+                    };
+                    session.time = *time;
+                    session.duration = *duration;
+                    session.last_update = Some(now);
+                };
+                changes
+            }
+            _ => vec![],
+        }
+    })
+}
+
+pub fn record_error() {
+    WATCH_SESSION.with(|session| {
+        if let Some(session) = session.borrow_mut().as_mut() {
+            session.errors += 1;
+        };
+    });
+}